@@ -0,0 +1,49 @@
+// Terminal front-end: runs a simulation and redraws it with ANSI colours,
+// so it can be watched over SSH without a window.
+//
+//     cargo run --release --bin simulation -- [width] [height] [steps] [delay_ms]
+use ecosim::config::SimulationConfig;
+use ecosim::ecosystem::{Ecosystem, SimulationStats};
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+fn print(ecosystem: &Ecosystem, stats: &SimulationStats) {
+    println!("Iteration {}{}", ecosystem.iteration_count, if ecosystem.is_night() { " (night)" } else { "" });
+    println!("Plants: {} | Herbivores: {} | Carnivores: {} | Omnivores: {}",
+             ecosystem.plants.len(), ecosystem.herbivores.len(), ecosystem.carnivores.len(), ecosystem.omnivores.len());
+    println!("Births  H: {} C: {} O: {} | Deaths  H: {} C: {} O: {}",
+             stats.herbivore_births, stats.carnivore_births, stats.omnivore_births,
+             stats.herbivore_deaths, stats.carnivore_deaths, stats.omnivore_deaths);
+    println!("Consumptions  H: {} C: {} O: {}",
+             stats.herbivore_consumptions,
+             stats.carnivore_consumptions + stats.carnivore_consumptions_omnivores,
+             stats.omnivore_consumptions_plants + stats.omnivore_consumptions_herbivores);
+}
+
+fn main() {
+    let usage = || -> ! {
+        eprintln!("Usage: simulation [width] [height] [steps] [delay_ms]");
+        std::process::exit(2);
+    };
+    let args: Vec<usize> = std::env::args().skip(1).map(|arg| arg.parse().unwrap_or_else(|_| usage())).collect();
+    let arg = |index: usize, default: usize| args.get(index).copied().unwrap_or(default);
+    let defaults = SimulationConfig::default();
+    let width = arg(0, defaults.grid_width);
+    let height = arg(1, defaults.grid_height);
+    let (steps, delay) = (arg(2, 200), arg(3, 100));
+    if width == 0 || height == 0 {
+        usage();
+    }
+
+    let mut ecosystem = Ecosystem::new(width, height);
+    let mut stats = SimulationStats::default();
+    for _ in 0..steps {
+        ecosystem.step(&mut stats);
+        print!("\x1b[2J\x1b[H");
+        ecosystem.draw();
+        print(&ecosystem, &stats);
+        std::io::stdout().flush().ok();
+        thread::sleep(Duration::from_millis(delay as u64));
+    }
+}
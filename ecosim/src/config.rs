@@ -1,76 +1,637 @@
-#[derive(Clone)]
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimulationConfig {
+    /// Label shown next to the simulation's number, such as "No Terrain".
+    /// Empty for unnamed configs.
+    pub name: String,
+    pub description: Option<String>,
     pub grid_width: usize,
     pub grid_height: usize,
-    pub initial_light_plants: usize,
-    pub initial_dark_plants: usize,
+    /// Treat the grid as a torus: movement, lookups and spawning wrap
+    /// around the edges instead of stopping at them.
+    pub wrap_edges: bool,
+    /// Which neighbouring cells animals can move into in a single step.
+    pub movement_neighborhood: MovementNeighborhood,
+    /// Pause a running simulation as soon as a species dies out.
+    pub pause_on_extinction: bool,
+    /// Process animals in id order each step, so outcomes don't depend on
+    /// the order left behind by earlier removals. Costs a sort of every
+    /// animal vector per step.
+    pub stable_processing: bool,
+    /// Seed for the ecosystem's random number generator. Two ecosystems
+    /// built from the same config and seed evolve identically.
+    pub seed: u64,
+    /// Plant species in the simulation; plants refer to theirs by its index
+    /// in this list.
+    pub plant_species: Vec<PlantSpecies>,
     pub initial_herbivores: usize,
     pub initial_carnivores: usize,
     pub initial_omnivores: usize,
+    pub initial_scavengers: usize,
+    /// How initial agents are spread over the grid when no map file is used.
+    pub initial_distribution: InitialDistribution,
     pub plant_growth_rate: f32,
+    /// Grow plants into free neighbouring cells instead of random cells.
+    pub plant_spread_local: bool,
+    /// Only let a plant reproduce when another plant of its species is within
+    /// `pollination_radius` cells; takes precedence over `plant_spread_local`.
+    pub plant_pollination: bool,
+    pub pollination_radius: usize,
+    /// Length in iterations of a full day; the second half of each day is
+    /// night. 0 disables the cycle.
+    pub day_length: usize,
+    /// Multiplier applied to plant growth at night.
+    pub night_growth_factor: f32,
+    /// Multiplier applied to carnivore move chance at night.
+    pub night_carnivore_move_factor: f32,
+    /// Length in iterations of each of the four seasons. 0 disables seasons.
+    pub season_length: usize,
     pub herbivore_energy_gain: i32,
     pub herbivore_energy_loss: i32,
     pub herbivore_initial_energy: i32,
     pub herbivore_reproduction_threshold: i32,
+    pub herbivore_reproduction_rate: f32,
+    /// Energy a parent hands to each offspring. Reproducing then needs
+    /// the threshold plus this much energy; 0 splits the energy in half.
+    pub herbivore_reproduction_cost: i32,
     pub carnivore_energy_gain: i32,
     pub carnivore_energy_loss: i32,
     pub carnivore_initial_energy: i32,
     pub carnivore_reproduction_threshold: i32,
+    pub carnivore_reproduction_rate: f32,
+    pub carnivore_reproduction_cost: i32,
     pub omnivore_energy_gain_plants: i32,
     pub omnivore_energy_gain_herbivores: i32,
+    /// Chance that an omnivore sharing a cell with both a herbivore and a
+    /// plant eats the herbivore rather than the plant.
+    pub omnivore_meat_preference: f32,
     pub omnivore_energy_loss: i32,
     pub omnivore_initial_energy: i32,
     pub omnivore_reproduction_threshold: i32,
+    pub omnivore_reproduction_rate: f32,
+    pub omnivore_reproduction_cost: i32,
+    /// Energy a scavenger gains from eating a corpse, its only food.
+    pub scavenger_energy_gain: i32,
+    pub scavenger_energy_loss: i32,
+    pub scavenger_initial_energy: i32,
+    pub scavenger_reproduction_threshold: i32,
+    pub scavenger_reproduction_rate: f32,
+    pub scavenger_reproduction_cost: i32,
+    /// Age in iterations at which an animal dies of old age; 0 means no limit.
+    pub herbivore_max_age: usize,
+    pub carnivore_max_age: usize,
+    pub omnivore_max_age: usize,
+    /// Most energy an animal can store; gains beyond it are lost.
+    pub herbivore_max_energy: i32,
+    pub carnivore_max_energy: i32,
+    pub omnivore_max_energy: i32,
+    pub scavenger_max_energy: i32,
+    /// Population at which a species stops reproducing; None means no cap.
+    pub herbivore_max_population: Option<usize>,
+    pub carnivore_max_population: Option<usize>,
+    pub omnivore_max_population: Option<usize>,
+    /// Energy at or below which an animal starves to death.
+    pub herbivore_starvation_threshold: i32,
+    pub carnivore_starvation_threshold: i32,
+    pub omnivore_starvation_threshold: i32,
+    pub scavenger_starvation_threshold: i32,
+    /// Chebyshev distance at which an animal spots food and heads for it
+    /// instead of wandering randomly; 0 disables directed movement.
+    pub herbivore_vision_radius: usize,
+    pub carnivore_vision_radius: usize,
+    pub omnivore_vision_radius: usize,
+    pub scavenger_vision_radius: usize,
+    /// Chance that a herbivore next to a carnivore or omnivore steps away
+    /// from it instead of moving normally.
+    pub herbivore_flee_chance: f32,
+    /// Energy an animal spends each time it moves to another cell.
+    pub move_energy_cost: i32,
+    /// Multiplier on every per-step energy loss: metabolism, moving,
+    /// disease and overfeeding. Fractions of a unit carry over between
+    /// steps; at 1 losses are exactly the configured integers.
+    pub time_scale: f32,
+    /// Energy above which a carnivore starts paying an extra metabolic cost.
+    pub carnivore_overfed_threshold: i32,
+    /// Energy above which an omnivore starts paying an extra metabolic cost.
+    pub omnivore_overfed_threshold: i32,
+    /// Fraction of the energy above the overfed threshold lost each step.
+    /// This is a soft ceiling: unlike a hard `max_energy` clamp, a well-fed
+    /// predator can still exceed the threshold but drifts back towards it.
+    pub overfed_decay_rate: f32,
+    /// Standard deviation of the Gaussian noise added to each gene when an
+    /// animal reproduces.
+    pub mutation_std: f32,
+    /// When true, water kills any animal that steps onto it; otherwise
+    /// animals drink there instead.
+    pub water_is_lethal: bool,
+    /// Energy an animal gains per step spent on a water cell.
+    pub water_drink_gain: i32,
+    /// Drinking never raises an animal's energy above this value. Keep it
+    /// below the reproduction thresholds, or animals on a lake can breed on
+    /// water alone.
+    pub water_drink_cap: i32,
+    /// When true, trees kill any animal that steps onto them.
+    pub tree_is_lethal: bool,
     pub water_spawn_chance: f32,
     pub water_lifespan: usize,
+    /// Side in cells of the square block a new lake or tree patch covers.
+    /// Blocks never exceed the grid.
+    pub water_block_size: usize,
     pub tree_spawn_chance: f32,
     pub tree_lifespan: usize,
+    pub tree_block_size: usize,
+    /// Chebyshev radius around each water cell in which plant species'
+    /// water affinities apply.
+    pub water_influence_radius: usize,
+    /// Multiplier on `plant_growth_rate` for plants sprouting near water,
+    /// before their water affinity.
+    pub water_plant_growth_mult: f32,
+    /// Chebyshev radius around each tree in which plant species' tree
+    /// affinities apply.
+    pub tree_influence_radius: usize,
+    /// Chance per step that a free cell near a tree sprouts a plant, before
+    /// its tree affinity.
+    #[serde(alias = "tree_dark_plant_chance")]
+    pub tree_plant_chance: f32,
+    /// Impassable rocks scattered over the grid at the start.
+    pub rock_count: usize,
+    /// Size in cells of the features of the generated elevation map; water
+    /// prefers to spawn on low ground. 0 disables elevation.
+    pub elevation_scale: usize,
+    /// From -1 to 1: how strongly plant growth favours low (negative) or
+    /// high (positive) ground. 0 ignores elevation.
+    pub plant_elevation_preference: f32,
+    /// Iterations a corpse lies on its cell before decaying into a plant of
+    /// the first species.
+    pub corpse_decay_time: usize,
+    /// Chance per step that an animal on an edge shared with a neighbouring
+    /// simulation crosses over, when migration is switched on.
+    pub migration_rate: f32,
+    /// Chance per step, while the weather is clear, that rain or a drought
+    /// sets in. 0 disables weather.
+    pub weather_event_chance: f32,
+    /// Iterations a rain or drought lasts.
+    pub weather_duration: usize,
+    /// Chance per step that a random healthy animal falls ill. 0 disables
+    /// the disease unless infected animals are loaded from a snapshot.
+    pub disease_spawn_chance: f32,
+    /// Chance per step that an infected animal infects each susceptible
+    /// animal on a neighbouring cell.
+    pub transmission_rate: f32,
+    /// Steps an infected animal survives unless it recovers first.
+    pub disease_duration: usize,
+    /// Chance per step that an infected animal recovers.
+    pub recovery_chance: f32,
+    /// Extra energy an infected animal loses each step.
+    pub disease_energy_loss: i32,
+    /// Steps a recovered animal can't be infected again.
+    pub immunity_duration: usize,
+    /// ASCII map used for the initial layout instead of random placement.
+    pub from_map_file: Option<PathBuf>,
+    /// Playback rate of recorded GIFs, in frames per second.
+    pub gif_frame_rate: u32,
+    /// Recorded frames use `cell_size / gif_downscale` pixels per cell.
+    pub gif_downscale: f32,
+    /// Recording stops on its own after this many frames. Frames are kept
+    /// uncompressed until the GIF is written, at 4 bytes per pixel: a
+    /// 57x52 grid at 4 px per cell is about 190 KB per frame.
+    pub gif_max_frames: usize,
+    /// Positions, including the current one, shown in an animal's trail
+    /// when trails are turned on.
+    pub trail_length: usize,
+    /// Name of the colour scheme the window uses ("Default", "Colorblind" or
+    /// "High Contrast"), and whether animals and later plant species get a glyph
+    /// drawn on their cell. Saved and loaded with the rest of the config.
+    pub color_scheme: String,
+    pub species_glyphs: bool,
 }
 
 impl Default for SimulationConfig {
     fn default() -> Self {
         Self {
+            name: String::new(),
+            description: None,
             grid_width: 57,
             grid_height: 52,
-            initial_light_plants: 150,
-            initial_dark_plants: 75,
+            wrap_edges: false,
+            movement_neighborhood: MovementNeighborhood::Moore,
+            pause_on_extinction: false,
+            stable_processing: false,
+            seed: rand::random(),
+            plant_species: vec![PlantSpecies::light(150), PlantSpecies::dark(75)],
             initial_herbivores: 120,
             initial_carnivores: 40,
             initial_omnivores: 40,
+            initial_scavengers: 0,
+            initial_distribution: InitialDistribution::Uniform,
             plant_growth_rate: 0.20,
+            plant_spread_local: false,
+            plant_pollination: false,
+            pollination_radius: 3,
+            day_length: 0,
+            night_growth_factor: 0.5,
+            night_carnivore_move_factor: 1.25,
+            season_length: 0,
             herbivore_energy_gain: 7,
             herbivore_energy_loss: 1,
             herbivore_initial_energy: 30,
             herbivore_reproduction_threshold: 15,
+            herbivore_reproduction_rate: 1.0,
+            herbivore_reproduction_cost: 0,
             carnivore_energy_gain: 10,
             carnivore_energy_loss: 1,
             carnivore_initial_energy: 120,
             carnivore_reproduction_threshold: 20,
+            carnivore_reproduction_rate: 1.0,
+            carnivore_reproduction_cost: 0,
             omnivore_energy_gain_plants: 2,
             omnivore_energy_gain_herbivores: 5,
+            omnivore_meat_preference: 1.0,
             omnivore_energy_loss: 1,
             omnivore_initial_energy: 45,
             omnivore_reproduction_threshold: 25,
+            omnivore_reproduction_rate: 1.0,
+            omnivore_reproduction_cost: 0,
+            scavenger_energy_gain: 10,
+            scavenger_energy_loss: 1,
+            scavenger_initial_energy: 30,
+            scavenger_reproduction_threshold: 40,
+            scavenger_reproduction_rate: 0.5,
+            scavenger_reproduction_cost: 0,
+            herbivore_max_age: 0,
+            carnivore_max_age: 0,
+            omnivore_max_age: 0,
+            herbivore_max_energy: 10_000,
+            carnivore_max_energy: 10_000,
+            omnivore_max_energy: 10_000,
+            scavenger_max_energy: 10_000,
+            herbivore_max_population: None,
+            carnivore_max_population: None,
+            omnivore_max_population: None,
+            herbivore_starvation_threshold: 0,
+            carnivore_starvation_threshold: 0,
+            omnivore_starvation_threshold: 0,
+            scavenger_starvation_threshold: 0,
+            herbivore_vision_radius: 0,
+            carnivore_vision_radius: 0,
+            omnivore_vision_radius: 0,
+            scavenger_vision_radius: 3,
+            herbivore_flee_chance: 0.0,
+            move_energy_cost: 0,
+            time_scale: 1.0,
+            carnivore_overfed_threshold: 150,
+            omnivore_overfed_threshold: 100,
+            overfed_decay_rate: 0.1,
+            mutation_std: 0.02,
+            water_is_lethal: false,
+            water_drink_gain: 2,
+            water_drink_cap: 10,
+            tree_is_lethal: true,
             water_spawn_chance: 0.005,
             water_lifespan: 500,
+            water_block_size: 3,
             tree_spawn_chance: 0.005,
             tree_lifespan: 500,
+            tree_block_size: 2,
+            water_influence_radius: 5,
+            water_plant_growth_mult: 3.0,
+            tree_influence_radius: 5,
+            tree_plant_chance: 0.5,
+            rock_count: 0,
+            elevation_scale: 0,
+            plant_elevation_preference: 0.0,
+            corpse_decay_time: 20,
+            migration_rate: 0.05,
+            weather_event_chance: 0.0,
+            weather_duration: 50,
+            disease_spawn_chance: 0.0,
+            transmission_rate: 0.2,
+            disease_duration: 30,
+            recovery_chance: 0.05,
+            disease_energy_loss: 1,
+            immunity_duration: 50,
+            from_map_file: None,
+            gif_frame_rate: 10,
+            gif_downscale: 3.0,
+            gif_max_frames: 300,
+            trail_length: 6,
+            color_scheme: "Default".to_string(),
+            species_glyphs: false,
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InitialDistribution {
+    Uniform,
+    /// Gaussian blobs of standard deviation `spread` cells around
+    /// `clusters` random centres shared by all species.
+    Clustered { clusters: usize, spread: usize },
+    /// The first two plant species, herbivores and carnivores each start in
+    /// their own quadrant; omnivores and further plant species start in the
+    /// central region.
+    Quadrants,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlantSpecies {
+    pub name: String,
+    pub initial_count: usize,
+    /// Multiplier on `plant_growth_rate` for plants of this species.
+    pub growth_mult: f32,
+    /// Energy from eating one of these plants; when unset,
+    /// `herbivore_energy_gain` or `omnivore_energy_gain_plants` is used.
+    pub herbivore_energy_gain: Option<i32>,
+    pub omnivore_energy_gain: Option<i32>,
+    /// From -1 to 1: how the species fares near water. Negative kills its
+    /// plants within `water_influence_radius`; positive lets it sprout
+    /// there, more often the larger it is.
+    pub water_affinity: f32,
+    /// The same near trees, within `tree_influence_radius`.
+    pub tree_affinity: f32,
+}
+
+impl Default for PlantSpecies {
+    fn default() -> Self {
+        Self {
+            name: "Plant".to_string(),
+            initial_count: 0,
+            growth_mult: 1.0,
+            herbivore_energy_gain: None,
+            omnivore_energy_gain: None,
+            water_affinity: 0.0,
+            tree_affinity: 0.0,
+        }
+    }
+}
+
+impl PlantSpecies {
+    /// Thrives near water and dies near trees.
+    pub fn light(initial_count: usize) -> Self {
+        Self { name: "Light".to_string(), initial_count, water_affinity: 1.0, tree_affinity: -1.0, ..Self::default() }
+    }
+
+    /// Thrives near trees and dies near water.
+    pub fn dark(initial_count: usize) -> Self {
+        Self { name: "Dark".to_string(), initial_count, water_affinity: -1.0, tree_affinity: 1.0, ..Self::default() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MovementNeighborhood {
+    /// All 8 surrounding cells, diagonals included.
+    Moore,
+    /// Only the 4 orthogonally adjacent cells.
+    VonNeumann,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse { line: Option<usize>, message: String },
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "could not access config file: {}", err),
+            ConfigError::Parse { line: Some(line), message } => write!(f, "invalid config at line {}: {}", line, message),
+            ConfigError::Parse { line: None, message } => write!(f, "invalid config: {}", message),
+            ConfigError::Serialize(err) => write!(f, "could not serialize config: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Smallest grid width or height `validate` accepts.
+pub const MIN_GRID_SIZE: usize = 3;
+
 #[derive(Debug, Clone, PartialEq)]
+pub enum ConfigIssue {
+    GridTooSmall { width: usize, height: usize },
+    ChanceOutOfRange { field: &'static str, value: f32 },
+    TooManyAgents { what: &'static str, count: usize, cells: usize },
+    ZeroLifespan { field: &'static str },
+    EnergyCapBelowThreshold { field: &'static str, cap: i32, threshold: i32 },
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigIssue::GridTooSmall { width, height } => {
+                write!(f, "grid size {}x{} must be at least {}x{}", width, height, MIN_GRID_SIZE, MIN_GRID_SIZE)
+            },
+            ConfigIssue::ChanceOutOfRange { field, value } => write!(f, "{} is {} but must be between 0 and 1", field, value),
+            ConfigIssue::TooManyAgents { what, count, cells } => write!(f, "{} {} don't fit in {} cells", count, what, cells),
+            ConfigIssue::ZeroLifespan { field } => write!(f, "{} must be at least 1", field),
+            ConfigIssue::EnergyCapBelowThreshold { field, cap, threshold } => {
+                write!(f, "{} is {} but must be at least the reproduction threshold plus cost {}", field, cap, threshold)
+            },
+        }
+    }
+}
+
+impl SimulationConfig {
+    pub fn validate(&self) -> Result<(), Vec<ConfigIssue>> {
+        let mut issues = Vec::new();
+        if self.grid_width < MIN_GRID_SIZE || self.grid_height < MIN_GRID_SIZE {
+            issues.push(ConfigIssue::GridTooSmall { width: self.grid_width, height: self.grid_height });
+        }
+        let chances = [
+            ("plant_growth_rate", self.plant_growth_rate),
+            ("herbivore_reproduction_rate", self.herbivore_reproduction_rate),
+            ("carnivore_reproduction_rate", self.carnivore_reproduction_rate),
+            ("omnivore_reproduction_rate", self.omnivore_reproduction_rate),
+            ("omnivore_meat_preference", self.omnivore_meat_preference),
+            ("scavenger_reproduction_rate", self.scavenger_reproduction_rate),
+            ("herbivore_flee_chance", self.herbivore_flee_chance),
+            ("water_spawn_chance", self.water_spawn_chance),
+            ("tree_spawn_chance", self.tree_spawn_chance),
+            ("weather_event_chance", self.weather_event_chance),
+            ("disease_spawn_chance", self.disease_spawn_chance),
+            ("transmission_rate", self.transmission_rate),
+            ("recovery_chance", self.recovery_chance),
+        ];
+        for (field, value) in chances {
+            if !(0.0..=1.0).contains(&value) {
+                issues.push(ConfigIssue::ChanceOutOfRange { field, value });
+            }
+        }
+        // Animals and rocks each need a cell of their own; plants can't
+        // share one either once the grid fills up.
+        let cells = self.grid_width * self.grid_height;
+        let plants: usize = self.plant_species.iter().map(|species| species.initial_count).sum();
+        if plants > cells {
+            issues.push(ConfigIssue::TooManyAgents { what: "plants", count: plants, cells });
+        }
+        let blocking = self.initial_herbivores + self.initial_carnivores + self.initial_omnivores + self.initial_scavengers + self.rock_count;
+        if blocking > cells {
+            issues.push(ConfigIssue::TooManyAgents { what: "animals and rocks", count: blocking, cells });
+        }
+        let lifespans = [
+            ("water_lifespan", self.water_lifespan),
+            ("tree_lifespan", self.tree_lifespan),
+            ("disease_duration", self.disease_duration),
+        ];
+        for (field, lifespan) in lifespans {
+            if lifespan == 0 {
+                issues.push(ConfigIssue::ZeroLifespan { field });
+            }
+        }
+        let energy_caps = [
+            ("herbivore_max_energy", self.herbivore_max_energy, self.herbivore_reproduction_threshold + self.herbivore_reproduction_cost),
+            ("carnivore_max_energy", self.carnivore_max_energy, self.carnivore_reproduction_threshold + self.carnivore_reproduction_cost),
+            ("omnivore_max_energy", self.omnivore_max_energy, self.omnivore_reproduction_threshold + self.omnivore_reproduction_cost),
+            ("scavenger_max_energy", self.scavenger_max_energy, self.scavenger_reproduction_threshold + self.scavenger_reproduction_cost),
+        ];
+        for (field, cap, threshold) in energy_caps {
+            if cap < threshold {
+                issues.push(ConfigIssue::EnergyCapBelowThreshold { field, cap, threshold });
+            }
+        }
+        if issues.is_empty() { Ok(()) } else { Err(issues) }
+    }
+
+    /// Small reproducible config with a fixed seed, a handful of agents
+    /// and no terrain, for tests and headless runs.
+    pub fn minimal(width: usize, height: usize) -> Self {
+        Self {
+            grid_width: width,
+            grid_height: height,
+            seed: 0,
+            plant_species: vec![PlantSpecies::light(4), PlantSpecies::dark(2)],
+            initial_herbivores: 2,
+            initial_carnivores: 1,
+            initial_omnivores: 1,
+            water_spawn_chance: 0.0,
+            tree_spawn_chance: 0.0,
+            ..Self::default()
+        }
+    }
+
+    /// Copy of this config with random populations, spawn chances, growth
+    /// and reproduction rates, drawn from `seed`. The seed becomes the
+    /// config's seed, so the same seed always gives the same result. The
+    /// populations always fit the grid.
+    pub fn randomized(&self, seed: u64) -> Self {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let cells = self.grid_width * self.grid_height;
+        let plant_share = cells / (2 * self.plant_species.len()).max(4);
+        Self {
+            seed,
+            plant_species: self.plant_species.iter().map(|species| PlantSpecies { initial_count: rng.gen_range(0..=plant_share), ..species.clone() }).collect(),
+            initial_herbivores: rng.gen_range(0..=cells / 8),
+            initial_carnivores: rng.gen_range(0..=cells / 16),
+            initial_omnivores: rng.gen_range(0..=cells / 16),
+            rock_count: rng.gen_range(0..=cells / 16),
+            plant_growth_rate: rng.gen_range(0.0..=1.0),
+            water_spawn_chance: rng.gen_range(0.0..=0.05),
+            tree_spawn_chance: rng.gen_range(0.0..=0.05),
+            herbivore_reproduction_rate: rng.gen_range(0.0..=1.0),
+            carnivore_reproduction_rate: rng.gen_range(0.0..=1.0),
+            omnivore_reproduction_rate: rng.gen_range(0.0..=1.0),
+            ..self.clone()
+        }
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<(), ConfigError> {
+        let text = toml::to_string_pretty(self).map_err(ConfigError::Serialize)?;
+        fs::write(path, text).map_err(ConfigError::Io)
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let parse_error = |err: toml::de::Error| ConfigError::Parse {
+            line: err.span().map(|span| text[..span.start].matches('\n').count() + 1),
+            message: err.message().to_string(),
+        };
+        let mut config: Self = toml::from_str(text).map_err(parse_error)?;
+        let legacy: LegacyPlantCounts = toml::from_str(text).map_err(parse_error)?;
+        for (species, count) in [legacy.initial_light_plants, legacy.initial_dark_plants].into_iter().enumerate() {
+            if let (Some(plant), Some(count)) = (config.plant_species.get_mut(species), count) {
+                plant.initial_count = count;
+            }
+        }
+        Ok(config)
+    }
+}
+
+// Plant counts of configs saved before plant species, which become the
+// initial counts of the first two species.
+#[derive(Deserialize)]
+struct LegacyPlantCounts {
+    initial_light_plants: Option<usize>,
+    initial_dark_plants: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AgentType {
-    LightPlant,
-    DarkPlant,
+    Plant,
     Herbivore,
     Carnivore,
     Omnivore,
+    Scavenger,
     Water,
     Tree,
+    Corpse,
+    Rock,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Genome {
+    pub move_chance: f32,
+    pub energy_loss_mult: f32,
+}
+
+impl Default for Genome {
+    fn default() -> Self {
+        Self {
+            move_chance: 0.8,
+            energy_loss_mult: 1.0,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+impl Genome {
+    pub fn mutate(&self, rng: &mut impl Rng, std: f32) -> Self {
+        if std <= 0.0 {
+            return self.clone();
+        }
+        let mut gaussian = || {
+            // Box-Muller transform; 1 - u keeps the logarithm's argument in (0, 1].
+            let u1: f32 = 1.0 - rng.gen::<f32>();
+            let u2: f32 = rng.gen();
+            (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos() * std
+        };
+        Self {
+            move_chance: (self.move_chance + gaussian()).clamp(0.0, 1.0),
+            energy_loss_mult: (self.energy_loss_mult + gaussian()).clamp(0.5, 2.0),
+        }
+    }
+
+    /// Unrounded, so a multiplier below 1 saves energy even on a base loss
+    /// of 1; the fraction is carried as the agent's energy debt.
+    pub fn energy_loss(&self, base: i32) -> f32 {
+        base as f32 * self.energy_loss_mult
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredAgent")]
 pub struct Agent {
     pub id: u32,
     pub agent_type: AgentType,
@@ -80,9 +641,111 @@ pub struct Agent {
     pub pending_death: bool,
     pub death_cause: Option<String>,
     pub birth_iteration: Option<usize>,
+    pub parent_id: Option<u32>,
+    pub generation: u32,
+    #[serde(default)]
+    pub genome: Genome,
+    #[serde(default)]
+    pub infected: bool,
+    /// Steps since the animal was infected.
+    #[serde(default)]
+    pub infection_age: usize,
+    /// Steps of immunity left after recovering.
+    #[serde(default)]
+    pub immunity: usize,
+    /// Part of a unit of energy lost to scaled per-step costs but not yet
+    /// taken from `energy`.
+    #[serde(default)]
+    pub energy_debt: f32,
+    /// Index of a plant's species in `plant_species`; 0 for other agents.
+    #[serde(default)]
+    pub species: usize,
+}
+
+// Snapshots from before plant species stored light and dark plants as agent
+// types of their own; they load as the first and second species.
+#[derive(Deserialize)]
+enum StoredAgentType {
+    Plant,
+    LightPlant,
+    DarkPlant,
+    Herbivore,
+    Carnivore,
+    Omnivore,
+    Scavenger,
+    Water,
+    Tree,
+    Corpse,
+    Rock,
+}
+
+#[derive(Deserialize)]
+struct StoredAgent {
+    id: u32,
+    agent_type: StoredAgentType,
+    x: usize,
+    y: usize,
+    energy: i32,
+    pending_death: bool,
+    death_cause: Option<String>,
+    birth_iteration: Option<usize>,
+    parent_id: Option<u32>,
+    generation: u32,
+    #[serde(default)]
+    genome: Genome,
+    #[serde(default)]
+    infected: bool,
+    #[serde(default)]
+    infection_age: usize,
+    #[serde(default)]
+    immunity: usize,
+    #[serde(default)]
+    energy_debt: f32,
+    #[serde(default)]
+    species: usize,
+}
+
+impl From<StoredAgent> for Agent {
+    fn from(stored: StoredAgent) -> Self {
+        let (agent_type, species) = match stored.agent_type {
+            StoredAgentType::Plant => (AgentType::Plant, stored.species),
+            StoredAgentType::LightPlant => (AgentType::Plant, 0),
+            StoredAgentType::DarkPlant => (AgentType::Plant, 1),
+            StoredAgentType::Herbivore => (AgentType::Herbivore, stored.species),
+            StoredAgentType::Carnivore => (AgentType::Carnivore, stored.species),
+            StoredAgentType::Omnivore => (AgentType::Omnivore, stored.species),
+            StoredAgentType::Scavenger => (AgentType::Scavenger, stored.species),
+            StoredAgentType::Water => (AgentType::Water, stored.species),
+            StoredAgentType::Tree => (AgentType::Tree, stored.species),
+            StoredAgentType::Corpse => (AgentType::Corpse, stored.species),
+            StoredAgentType::Rock => (AgentType::Rock, stored.species),
+        };
+        Self {
+            id: stored.id,
+            agent_type,
+            x: stored.x,
+            y: stored.y,
+            energy: stored.energy,
+            pending_death: stored.pending_death,
+            death_cause: stored.death_cause,
+            birth_iteration: stored.birth_iteration,
+            parent_id: stored.parent_id,
+            generation: stored.generation,
+            genome: stored.genome,
+            infected: stored.infected,
+            infection_age: stored.infection_age,
+            immunity: stored.immunity,
+            energy_debt: stored.energy_debt,
+            species,
+        }
+    }
 }
 
 impl Agent {
+    pub fn is_susceptible(&self) -> bool {
+        !self.infected && self.immunity == 0 && !self.pending_death
+    }
+
     pub fn new(id: u32, agent_type: AgentType, x: usize, y: usize, energy: i32) -> Self {
         Self {
             id,
@@ -93,32 +756,90 @@ impl Agent {
             pending_death: false,
             death_cause: None,
             birth_iteration: None,
+            parent_id: None,
+            generation: 0,
+            genome: Genome::default(),
+            infected: false,
+            infection_age: 0,
+            immunity: 0,
+            energy_debt: 0.0,
+            species: 0,
+        }
+    }
+
+    pub fn new_plant(id: u32, species: usize, x: usize, y: usize) -> Self {
+        Self {
+            species,
+            ..Self::new(id, AgentType::Plant, x, y, 0)
+        }
+    }
+
+    pub fn new_animal(id: u32, agent_type: AgentType, x: usize, y: usize, energy: i32, birth: usize) -> Self {
+        Self {
+            birth_iteration: Some(birth),
+            ..Self::new(id, agent_type, x, y, energy)
+        }
+    }
+
+    pub fn new_offspring(id: u32, parent: &Agent, x: usize, y: usize, energy: i32, birth: usize, genome: Genome) -> Self {
+        Self {
+            parent_id: Some(parent.id),
+            generation: parent.generation + 1,
+            genome,
+            ..Self::new_animal(id, parent.agent_type.clone(), x, y, energy, birth)
         }
     }
 
     pub fn new_water(id: u32, x: usize, y: usize, birth: usize) -> Self {
         Self {
-            id,
-            agent_type: AgentType::Water,
-            x,
-            y,
-            energy: 0,
-            pending_death: false,
-            death_cause: None,
             birth_iteration: Some(birth),
+            ..Self::new(id, AgentType::Water, x, y, 0)
         }
     }
 
+    pub fn new_corpse(id: u32, x: usize, y: usize, birth: usize) -> Self {
+        Self {
+            birth_iteration: Some(birth),
+            ..Self::new(id, AgentType::Corpse, x, y, 0)
+        }
+    }
+
+    pub fn new_rock(id: u32, x: usize, y: usize) -> Self {
+        Self::new(id, AgentType::Rock, x, y, 0)
+    }
+
     pub fn new_tree(id: u32, x: usize, y: usize, birth: usize) -> Self {
         Self {
-            id,
-            agent_type: AgentType::Tree,
-            x,
-            y,
-            energy: 0,
-            pending_death: false,
-            death_cause: None,
             birth_iteration: Some(birth),
+            ..Self::new(id, AgentType::Tree, x, y, 0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutated_genes_stay_close_to_the_parent() {
+        let mut rng = ChaCha12Rng::seed_from_u64(1);
+        let parent = Genome::default();
+        let mut changed = false;
+        for _ in 0..1000 {
+            let child = parent.mutate(&mut rng, 0.02);
+            assert!((child.move_chance - parent.move_chance).abs() <= 0.2);
+            assert!((child.energy_loss_mult - parent.energy_loss_mult).abs() <= 0.2);
+            changed |= child != parent;
         }
+        assert!(changed);
+        assert_eq!(parent.mutate(&mut rng, 0.0), parent);
+    }
+
+    #[test]
+    fn configs_from_before_plant_species_keep_their_plant_counts() {
+        let config = SimulationConfig::from_toml("grid_width = 40\ninitial_light_plants = 123\ninitial_dark_plants = 45\n").unwrap();
+        assert_eq!(config.grid_width, 40);
+        let counts: Vec<usize> = config.plant_species.iter().map(|species| species.initial_count).collect();
+        assert_eq!(counts, vec![123, 45]);
     }
 }
@@ -1,17 +1,42 @@
-use crate::config::{SimulationConfig, Agent, AgentType};
-use rand::Rng;
+use crate::config::{SimulationConfig, Agent, AgentType, InitialDistribution, MovementNeighborhood, PlantSpecies};
+use crate::map;
+use crate::occupancy::{CellIndex, Occupancy};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
 
-#[derive(Default, Clone)]
+const WATER_SITE_CANDIDATES: usize = 4;
+
+/// Width in iterations of each bucket of the lifespan histograms.
+pub const LIFESPAN_BUCKET: usize = 10;
+
+/// Agent types in drawing precedence: a cell shows the first one it holds.
+pub const CELL_PRECEDENCE: [AgentType; 9] = [
+    AgentType::Rock,
+    AgentType::Tree,
+    AgentType::Water,
+    AgentType::Carnivore,
+    AgentType::Herbivore,
+    AgentType::Omnivore,
+    AgentType::Scavenger,
+    AgentType::Corpse,
+    AgentType::Plant,
+];
+
+#[derive(Default, Clone, Serialize)]
 pub struct SimulationStats {
-    pub light_plant_births: usize,
-    pub dark_plant_births: usize,
+    /// Births and deaths of each plant species, by index in
+    /// `plant_species`. Species past the end have none yet.
+    pub plant_births: Vec<usize>,
+    pub plant_deaths: Vec<usize>,
     pub herbivore_births: usize,
     pub carnivore_births: usize,
     pub omnivore_births: usize,
     pub water_births: usize,
     pub tree_births: usize,
-    pub light_plant_deaths: usize,
-    pub dark_plant_deaths: usize,
     pub herbivore_deaths: usize,
     pub carnivore_deaths: usize,
     pub omnivore_deaths: usize,
@@ -19,11 +44,347 @@ pub struct SimulationStats {
     pub tree_deaths: usize,
     pub herbivore_consumptions: usize,
     pub carnivore_consumptions: usize,
+    pub carnivore_consumptions_omnivores: usize,
     pub omnivore_consumptions_plants: usize,
     pub omnivore_consumptions_herbivores: usize,
+    pub scavenger_births: usize,
+    pub scavenger_deaths: usize,
+    pub scavenger_consumptions: usize,
+    pub rain_events: usize,
+    pub drought_events: usize,
+    pub infections: usize,
+    pub recoveries: usize,
+    pub disease_deaths: usize,
+    /// Deaths of each animal species by the cause recorded on the agent,
+    /// such as "Lack of Energy" or "Eaten by Carnivore".
+    pub herbivore_death_causes: HashMap<String, usize>,
+    pub carnivore_death_causes: HashMap<String, usize>,
+    pub omnivore_death_causes: HashMap<String, usize>,
+    pub scavenger_death_causes: HashMap<String, usize>,
+    /// Deaths of each animal species by age: entry `i` counts the animals
+    /// that died aged `i * LIFESPAN_BUCKET` up to the next bucket.
+    pub herbivore_lifespans: Vec<usize>,
+    pub carnivore_lifespans: Vec<usize>,
+    pub omnivore_lifespans: Vec<usize>,
+    pub scavenger_lifespans: Vec<usize>,
+    pub longest_lived: Option<LongestLived>,
+}
+
+// What `SimulationStats::write_json` writes: the stats' own fields next to
+// the iteration they were taken at and the terrain on the grid then.
+#[derive(Serialize)]
+struct StatsRecord<'a> {
+    iteration_count: usize,
+    waters: usize,
+    trees: usize,
+    oldest_water_age: Option<usize>,
+    oldest_tree_age: Option<usize>,
+    #[serde(flatten)]
+    stats: &'a SimulationStats,
+}
+
+impl SimulationStats {
+    /// These stats next to the current iteration and terrain of
+    /// `ecosystem`, the simulation they were collected from.
+    pub fn to_json(&self, ecosystem: &Ecosystem) -> String {
+        let record = StatsRecord {
+            iteration_count: ecosystem.iteration_count,
+            waters: ecosystem.waters.len(),
+            trees: ecosystem.trees.len(),
+            oldest_water_age: ecosystem.oldest_age(&AgentType::Water),
+            oldest_tree_age: ecosystem.oldest_age(&AgentType::Tree),
+            stats: self,
+        };
+        serde_json::to_string_pretty(&record).expect("stats are always serializable")
+    }
+
+    pub fn write_json(&self, ecosystem: &Ecosystem, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json(ecosystem))
+    }
+
+    fn add_plants(counts: &mut Vec<usize>, species: usize, count: usize) {
+        if counts.len() <= species {
+            counts.resize(species + 1, 0);
+        }
+        counts[species] += count;
+    }
+
+    fn plant_born(&mut self, species: usize) {
+        Self::add_plants(&mut self.plant_births, species, 1);
+    }
+
+    fn plants_died(&mut self, species: usize, count: usize) {
+        Self::add_plants(&mut self.plant_deaths, species, count);
+    }
+
+    /// Births of one plant species; 0 for one that never had any.
+    pub fn plant_births_of(&self, species: usize) -> usize {
+        self.plant_births.get(species).copied().unwrap_or(0)
+    }
+
+    pub fn plant_deaths_of(&self, species: usize) -> usize {
+        self.plant_deaths.get(species).copied().unwrap_or(0)
+    }
+
+    // Births or deaths of every plant species up to `plant_species`, then
+    // of each animal species, in `Population::living` order.
+    fn living(plants: &[usize], plant_species: usize, animals: [usize; 4]) -> Vec<usize> {
+        (0..plant_species).map(|species| plants.get(species).copied().unwrap_or(0)).chain(animals).collect()
+    }
+
+    fn births(&self, plant_species: usize) -> Vec<usize> {
+        Self::living(&self.plant_births, plant_species, [self.herbivore_births, self.carnivore_births, self.omnivore_births, self.scavenger_births])
+    }
+
+    fn deaths(&self, plant_species: usize) -> Vec<usize> {
+        Self::living(&self.plant_deaths, plant_species, [self.herbivore_deaths, self.carnivore_deaths, self.omnivore_deaths, self.scavenger_deaths])
+    }
+
+    /// Adds the stats of later iterations, such as those returned by
+    /// `Ecosystem::step_delta`, to these. The longest-lived record is taken
+    /// from `delta`, which carries on from the earlier one.
+    pub fn accumulate(&mut self, delta: &SimulationStats) {
+        let counters = [
+            (&mut self.herbivore_births, delta.herbivore_births),
+            (&mut self.carnivore_births, delta.carnivore_births),
+            (&mut self.omnivore_births, delta.omnivore_births),
+            (&mut self.water_births, delta.water_births),
+            (&mut self.tree_births, delta.tree_births),
+            (&mut self.herbivore_deaths, delta.herbivore_deaths),
+            (&mut self.carnivore_deaths, delta.carnivore_deaths),
+            (&mut self.omnivore_deaths, delta.omnivore_deaths),
+            (&mut self.water_deaths, delta.water_deaths),
+            (&mut self.tree_deaths, delta.tree_deaths),
+            (&mut self.herbivore_consumptions, delta.herbivore_consumptions),
+            (&mut self.carnivore_consumptions, delta.carnivore_consumptions),
+            (&mut self.carnivore_consumptions_omnivores, delta.carnivore_consumptions_omnivores),
+            (&mut self.omnivore_consumptions_plants, delta.omnivore_consumptions_plants),
+            (&mut self.omnivore_consumptions_herbivores, delta.omnivore_consumptions_herbivores),
+            (&mut self.scavenger_births, delta.scavenger_births),
+            (&mut self.scavenger_deaths, delta.scavenger_deaths),
+            (&mut self.scavenger_consumptions, delta.scavenger_consumptions),
+            (&mut self.rain_events, delta.rain_events),
+            (&mut self.drought_events, delta.drought_events),
+            (&mut self.infections, delta.infections),
+            (&mut self.recoveries, delta.recoveries),
+            (&mut self.disease_deaths, delta.disease_deaths),
+        ];
+        for (total, count) in counters {
+            *total += count;
+        }
+        let causes = [
+            (&mut self.herbivore_death_causes, &delta.herbivore_death_causes),
+            (&mut self.carnivore_death_causes, &delta.carnivore_death_causes),
+            (&mut self.omnivore_death_causes, &delta.omnivore_death_causes),
+            (&mut self.scavenger_death_causes, &delta.scavenger_death_causes),
+        ];
+        for (total, more) in causes {
+            for (cause, count) in more {
+                *total.entry(cause.clone()).or_default() += count;
+            }
+        }
+        let tallies = [
+            (&mut self.plant_births, &delta.plant_births),
+            (&mut self.plant_deaths, &delta.plant_deaths),
+            (&mut self.herbivore_lifespans, &delta.herbivore_lifespans),
+            (&mut self.carnivore_lifespans, &delta.carnivore_lifespans),
+            (&mut self.omnivore_lifespans, &delta.omnivore_lifespans),
+            (&mut self.scavenger_lifespans, &delta.scavenger_lifespans),
+        ];
+        for (total, more) in tallies {
+            if total.len() < more.len() {
+                total.resize(more.len(), 0);
+            }
+            for (entry, count) in total.iter_mut().zip(more) {
+                *entry += count;
+            }
+        }
+        if delta.longest_lived.is_some() {
+            self.longest_lived = delta.longest_lived.clone();
+        }
+    }
+
+    pub fn delta_since(&self, previous: &SimulationStats, plant_species: usize) -> IterationDelta {
+        let diff = |now: Vec<usize>, before: Vec<usize>| now.into_iter().zip(before).map(|(now, before)| now.saturating_sub(before)).collect();
+        IterationDelta {
+            births: diff(self.births(plant_species), previous.births(plant_species)),
+            deaths: diff(self.deaths(plant_species), previous.deaths(plant_species)),
+        }
+    }
+}
+
+// Births and deaths between two stats snapshots for each living species,
+// in `Population::living` order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IterationDelta {
+    pub births: Vec<usize>,
+    pub deaths: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    pub fn opposite(self) -> Edge {
+        match self {
+            Edge::Left => Edge::Right,
+            Edge::Right => Edge::Left,
+            Edge::Top => Edge::Bottom,
+            Edge::Bottom => Edge::Top,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Population {
+    /// Plants of each species, by index in `plant_species`.
+    pub plants: Vec<usize>,
+    pub herbivores: usize,
+    pub carnivores: usize,
+    pub omnivores: usize,
+    pub scavengers: usize,
+    pub waters: usize,
+    pub trees: usize,
+}
+
+impl Population {
+    // Counts of each plant species and then each animal species, in chart
+    // order.
+    pub fn living(&self) -> Vec<usize> {
+        self.plants.iter().copied().chain([self.herbivores, self.carnivores, self.omnivores, self.scavengers]).collect()
+    }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize)]
+pub struct LongestLived {
+    pub id: u32,
+    pub agent_type: AgentType,
+    pub age: usize,
+    pub parent_id: Option<u32>,
+    pub generation: u32,
+    pub alive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub fn growth_multiplier(self) -> f32 {
+        match self {
+            Season::Spring => 1.0,
+            Season::Summer => 1.25,
+            Season::Autumn => 0.75,
+            Season::Winter => 0.25,
+        }
+    }
+
+    pub fn water_multiplier(self) -> f32 {
+        match self {
+            Season::Spring => 1.0,
+            Season::Summer => 0.5,
+            Season::Autumn => 1.5,
+            Season::Winter => 0.5,
+        }
+    }
+
+    pub fn reproduction_multiplier(self) -> f32 {
+        match self {
+            Season::Spring => 1.0,
+            Season::Summer => 1.0,
+            Season::Autumn => 0.75,
+            Season::Winter => 0.25,
+        }
+    }
+}
+
+impl fmt::Display for Season {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Season::Spring => "Spring",
+            Season::Summer => "Summer",
+            Season::Autumn => "Autumn",
+            Season::Winter => "Winter",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Weather {
+    #[default]
+    Clear,
+    Rain,
+    Drought,
+}
+
+impl Weather {
+    pub fn water_spawn_multiplier(self) -> f32 {
+        match self {
+            Weather::Clear => 1.0,
+            Weather::Rain => 3.0,
+            Weather::Drought => 0.0,
+        }
+    }
+
+    pub fn growth_multiplier(self) -> f32 {
+        match self {
+            Weather::Clear => 1.0,
+            Weather::Rain => 1.5,
+            Weather::Drought => 0.5,
+        }
+    }
+
+    // Water ages this many iterations per step, so droughts dry lakes up
+    // faster.
+    pub fn evaporation_rate(self) -> usize {
+        match self {
+            Weather::Drought => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for Weather {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Weather::Clear => "Clear",
+            Weather::Rain => "Rain",
+            Weather::Drought => "Drought",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Json(serde_json::Error),
+    OutOfBounds { id: u32, x: usize, y: usize },
+    UnknownPlantSpecies { id: u32, species: usize },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Json(err) => write!(f, "invalid snapshot: {}", err),
+            SnapshotError::OutOfBounds { id, x, y } => write!(f, "agent #{} at ({}, {}) is outside the grid", id, x, y),
+            SnapshotError::UnknownPlantSpecies { id, species } => write!(f, "plant #{} has species {} but the config has no such species", id, species),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Ecosystem {
     pub width: usize,
     pub height: usize,
@@ -33,60 +394,160 @@ pub struct Ecosystem {
     pub omnivores: Vec<Agent>,
     pub waters: Vec<Agent>,
     pub trees: Vec<Agent>,
+    #[serde(default)]
+    pub corpses: Vec<Agent>,
+    #[serde(default)]
+    pub rocks: Vec<Agent>,
+    #[serde(default)]
+    pub scavengers: Vec<Agent>,
+    #[serde(default)]
+    elevation: Vec<u8>,
     pub config: SimulationConfig,
     pub next_agent_id: u32,
     pub iteration_count: usize,
+    #[serde(default)]
+    weather: Weather,
+    #[serde(default)]
+    weather_remaining: usize,
+    #[serde(skip)]
+    pub occupancy: Occupancy,
+    #[serde(skip)]
+    plant_index: CellIndex,
+    #[serde(skip)]
+    herbivore_index: CellIndex,
+    #[serde(skip)]
+    omnivore_index: CellIndex,
+    rng: ChaCha12Rng,
 }
 
 impl Ecosystem {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::new_custom(SimulationConfig {
+            grid_width: width,
+            grid_height: height,
+            ..SimulationConfig::default()
+        })
+    }
+
+    /// Ecosystem on the minimal config with no agents at all, to be filled
+    /// in with `add_agent`.
+    pub fn new_empty(width: usize, height: usize) -> Self {
+        Self::new_custom(SimulationConfig {
+            plant_species: vec![PlantSpecies::light(0), PlantSpecies::dark(0)],
+            initial_herbivores: 0,
+            initial_carnivores: 0,
+            initial_omnivores: 0,
+            ..SimulationConfig::minimal(width, height)
+        })
+    }
+
     pub fn new_custom(config: SimulationConfig) -> Self {
         let width = config.grid_width;
         let height = config.grid_height;
-        let mut rng = rand::thread_rng();
+        let mut rng = ChaCha12Rng::seed_from_u64(config.seed);
         let mut plants = Vec::new();
         let mut herbivores = Vec::new();
         let mut carnivores = Vec::new();
         let mut omnivores = Vec::new();
-        let waters = Vec::new();
-        let trees = Vec::new();
+        let mut scavengers = Vec::new();
+        let mut waters = Vec::new();
+        let mut trees = Vec::new();
+        let mut corpses = Vec::new();
+        let mut rocks = Vec::new();
         let mut next_agent_id: u32 = 0;
 
-        for _ in 0..config.initial_light_plants {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            plants.push(Agent::new(next_agent_id, AgentType::LightPlant, x, y, 0));
-            next_agent_id += 1;
-        }
+        let layout = config.from_map_file.as_ref().and_then(|path| match map::load_map(path) {
+            Ok(cells) => Some(cells),
+            Err(err) => {
+                eprintln!("{}: {}, falling back to random placement", path.display(), err);
+                None
+            }
+        });
 
-        for _ in 0..config.initial_dark_plants {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            plants.push(Agent::new(next_agent_id, AgentType::DarkPlant, x, y, 0));
-            next_agent_id += 1;
-        }
+        if let Some(cells) = layout {
+            for (x, y, agent_type, species) in cells {
+                let unknown_species = agent_type == AgentType::Plant && species >= config.plant_species.len();
+                if x >= width || y >= height || unknown_species {
+                    continue;
+                }
+                let id = next_agent_id;
+                match agent_type {
+                    AgentType::Plant => plants.push(Agent::new_plant(id, species, x, y)),
+                    AgentType::Herbivore => herbivores.push(Agent::new_animal(id, agent_type, x, y, config.herbivore_initial_energy, 0)),
+                    AgentType::Carnivore => carnivores.push(Agent::new_animal(id, agent_type, x, y, config.carnivore_initial_energy, 0)),
+                    AgentType::Omnivore => omnivores.push(Agent::new_animal(id, agent_type, x, y, config.omnivore_initial_energy, 0)),
+                    AgentType::Scavenger => scavengers.push(Agent::new_animal(id, agent_type, x, y, config.scavenger_initial_energy, 0)),
+                    AgentType::Water => waters.push(Agent::new_water(id, x, y, 0)),
+                    AgentType::Tree => trees.push(Agent::new_tree(id, x, y, 0)),
+                    AgentType::Corpse => corpses.push(Agent::new_corpse(id, x, y, 0)),
+                    AgentType::Rock => rocks.push(Agent::new_rock(id, x, y)),
+                }
+                next_agent_id += 1;
+            }
+        } else {
+            let distribution = &config.initial_distribution;
+            let centers: Vec<(usize, usize)> = match distribution {
+                InitialDistribution::Clustered { clusters, .. } => (0..(*clusters).max(1))
+                    .map(|_| (rng.gen_range(0..width), rng.gen_range(0..height)))
+                    .collect(),
+                _ => Vec::new(),
+            };
 
-        for _ in 0..config.initial_herbivores {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            herbivores.push(Agent::new(next_agent_id, AgentType::Herbivore, x, y, config.herbivore_initial_energy));
-            next_agent_id += 1;
-        }
+            for (index, species) in config.plant_species.iter().enumerate() {
+                // Only the first two species get a quadrant of their own.
+                let group = if index < 2 { index } else { 4 };
+                for _ in 0..species.initial_count {
+                    let (x, y) = Self::initial_cell(&mut rng, distribution, &centers, group, width, height);
+                    plants.push(Agent::new_plant(next_agent_id, index, x, y));
+                    next_agent_id += 1;
+                }
+            }
 
-        for _ in 0..config.initial_carnivores {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            carnivores.push(Agent::new(next_agent_id, AgentType::Carnivore, x, y, config.carnivore_initial_energy));
-            next_agent_id += 1;
-        }
+            // Animals never share a cell, so each one is placed on a free cell
+            // and any that don't fit on the grid are dropped.
+            let mut animal_cells = vec![false; width * height];
+            for _ in 0..config.initial_herbivores {
+                let Some((x, y)) = Self::initial_free_cell(&mut rng, distribution, &centers, 2, &mut animal_cells, width, height) else { break };
+                herbivores.push(Agent::new_animal(next_agent_id, AgentType::Herbivore, x, y, config.herbivore_initial_energy, 0));
+                next_agent_id += 1;
+            }
 
-        for _ in 0..config.initial_omnivores {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            omnivores.push(Agent::new(next_agent_id, AgentType::Omnivore, x, y, config.omnivore_initial_energy));
-            next_agent_id += 1;
+            for _ in 0..config.initial_carnivores {
+                let Some((x, y)) = Self::initial_free_cell(&mut rng, distribution, &centers, 3, &mut animal_cells, width, height) else { break };
+                carnivores.push(Agent::new_animal(next_agent_id, AgentType::Carnivore, x, y, config.carnivore_initial_energy, 0));
+                next_agent_id += 1;
+            }
+
+            for _ in 0..config.initial_omnivores {
+                let Some((x, y)) = Self::initial_free_cell(&mut rng, distribution, &centers, 4, &mut animal_cells, width, height) else { break };
+                omnivores.push(Agent::new_animal(next_agent_id, AgentType::Omnivore, x, y, config.omnivore_initial_energy, 0));
+                next_agent_id += 1;
+            }
+
+            for _ in 0..config.initial_scavengers {
+                let Some((x, y)) = Self::initial_free_cell(&mut rng, distribution, &centers, 5, &mut animal_cells, width, height) else { break };
+                scavengers.push(Agent::new_animal(next_agent_id, AgentType::Scavenger, x, y, config.scavenger_initial_energy, 0));
+                next_agent_id += 1;
+            }
+
+            for _ in 0..config.rock_count {
+                let Some((x, y)) = Self::random_free_cell(&mut rng, &mut animal_cells, width) else { break };
+                rocks.push(Agent::new_rock(next_agent_id, x, y));
+                next_agent_id += 1;
+            }
+            if !rocks.is_empty() {
+                let rock_cells: std::collections::HashSet<(usize, usize)> = rocks.iter().map(|r| (r.x, r.y)).collect();
+                plants.retain(|p| !rock_cells.contains(&(p.x, p.y)));
+            }
         }
 
-        Ecosystem {
+        let elevation = if config.elevation_scale > 0 {
+            Self::value_noise(&mut rng, width, height, config.elevation_scale)
+        } else {
+            Vec::new()
+        };
+
+        let mut ecosystem = Ecosystem {
             width,
             height,
             plants,
@@ -95,40 +556,964 @@ impl Ecosystem {
             omnivores,
             waters,
             trees,
+            corpses,
+            rocks,
+            scavengers,
+            elevation,
             config,
             next_agent_id,
             iteration_count: 0,
+            weather: Weather::Clear,
+            weather_remaining: 0,
+            occupancy: Occupancy::default(),
+            plant_index: CellIndex::default(),
+            herbivore_index: CellIndex::default(),
+            omnivore_index: CellIndex::default(),
+            rng,
+        };
+        ecosystem.rebuild_occupancy();
+        ecosystem
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("ecosystem snapshots always serialize")
+    }
+
+    pub fn from_json(s: &str) -> Result<Ecosystem, SnapshotError> {
+        let mut ecosystem: Ecosystem = serde_json::from_str(s).map_err(SnapshotError::Json)?;
+        let agents = ecosystem.plants.iter()
+            .chain(&ecosystem.herbivores)
+            .chain(&ecosystem.carnivores)
+            .chain(&ecosystem.omnivores)
+            .chain(&ecosystem.waters)
+            .chain(&ecosystem.trees)
+            .chain(&ecosystem.corpses)
+            .chain(&ecosystem.rocks)
+            .chain(&ecosystem.scavengers);
+        if let Some(agent) = agents.into_iter().find(|a| a.x >= ecosystem.width || a.y >= ecosystem.height) {
+            return Err(SnapshotError::OutOfBounds { id: agent.id, x: agent.x, y: agent.y });
+        }
+        if let Some(plant) = ecosystem.plants.iter().find(|p| p.species >= ecosystem.config.plant_species.len()) {
+            return Err(SnapshotError::UnknownPlantSpecies { id: plant.id, species: plant.species });
+        }
+        ecosystem.rebuild_occupancy();
+        Ok(ecosystem)
+    }
+
+    fn agents_of(&self, agent_type: &AgentType) -> &[Agent] {
+        match agent_type {
+            AgentType::Plant => &self.plants,
+            AgentType::Herbivore => &self.herbivores,
+            AgentType::Carnivore => &self.carnivores,
+            AgentType::Omnivore => &self.omnivores,
+            AgentType::Scavenger => &self.scavengers,
+            AgentType::Water => &self.waters,
+            AgentType::Tree => &self.trees,
+            AgentType::Corpse => &self.corpses,
+            AgentType::Rock => &self.rocks,
+        }
+    }
+
+    pub fn count_of(&self, agent_type: &AgentType) -> usize {
+        self.agents_of(agent_type).iter().filter(|a| a.agent_type == *agent_type).count()
+    }
+
+    /// Age of the oldest agent of a type, None when there are none. Waters
+    /// age faster during droughts, so their age is the one evaporation uses.
+    pub fn oldest_age(&self, agent_type: &AgentType) -> Option<usize> {
+        self.agents_of(agent_type)
+            .iter()
+            .filter(|a| a.agent_type == *agent_type)
+            .filter_map(|a| a.birth_iteration)
+            .map(|birth| self.iteration_count.saturating_sub(birth))
+            .max()
+    }
+
+    // Only the types the occupancy grid has at the cell are searched.
+    fn agents_of_type_at<'a>(&'a self, agent_type: &'a AgentType, x: usize, y: usize) -> impl Iterator<Item = &'a Agent> {
+        let agents = if self.occupancy.has(agent_type, x, y) { self.agents_of(agent_type) } else { &[] };
+        agents.iter().filter(move |a| a.x == x && a.y == y && a.agent_type == *agent_type)
+    }
+
+    /// Every agent on a cell, in `CELL_PRECEDENCE` order.
+    pub fn agents_at(&self, x: usize, y: usize) -> Vec<&Agent> {
+        CELL_PRECEDENCE.iter().flat_map(|agent_type| self.agents_of_type_at(agent_type, x, y)).collect()
+    }
+
+    /// The agent whose colour is shown for a cell, if any.
+    pub fn agent_at(&self, x: usize, y: usize) -> Option<&Agent> {
+        CELL_PRECEDENCE.iter().find_map(|agent_type| self.agents_of_type_at(agent_type, x, y).next())
+    }
+
+    // Prints the grid to the terminal, two characters per cell, using ANSI
+    // 256-colour backgrounds for the agent `agent_at` picks, as the window
+    // renderer does.
+    pub fn draw(&self) {
+        let mut out = String::with_capacity(self.config.grid_width * self.config.grid_height * 12);
+        for y in 0..self.config.grid_height {
+            for x in 0..self.config.grid_width {
+                let color = match self.agent_at(x, y).map(|agent| (&agent.agent_type, agent.species)) {
+                    Some((AgentType::Rock, _)) => 238,
+                    Some((AgentType::Tree, _)) => 94,
+                    Some((AgentType::Water, _)) => 27,
+                    Some((AgentType::Carnivore, _)) => 196,
+                    Some((AgentType::Herbivore, _)) => 213,
+                    Some((AgentType::Omnivore, _)) => 208,
+                    Some((AgentType::Scavenger, _)) => 93,
+                    Some((AgentType::Corpse, _)) => 88,
+                    // Light green for the first species, darker greens for the others.
+                    Some((AgentType::Plant, species)) => [46, 28, 22, 34][species % 4],
+                    None if self.is_night() => 240,
+                    None => 250,
+                };
+                out.push_str(&format!("\x1b[48;5;{}m  ", color));
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        print!("{}", out);
+    }
+
+    pub fn elevation_at(&self, x: usize, y: usize) -> Option<u8> {
+        self.elevation.get(y * self.width + x).copied()
+    }
+
+    // Smoothly interpolated random values on a lattice with `scale` cells
+    // between points, one byte per grid cell.
+    fn value_noise(rng: &mut impl Rng, width: usize, height: usize, scale: usize) -> Vec<u8> {
+        let lattice_w = width / scale + 2;
+        let lattice_h = height / scale + 2;
+        let lattice: Vec<f32> = (0..lattice_w * lattice_h).map(|_| rng.gen()).collect();
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let mut elevation = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (gx, gy) = (x / scale, y / scale);
+                let tx = smooth((x % scale) as f32 / scale as f32);
+                let ty = smooth((y % scale) as f32 / scale as f32);
+                let at = |lx: usize, ly: usize| lattice[ly * lattice_w + lx];
+                let top = at(gx, gy) + (at(gx + 1, gy) - at(gx, gy)) * tx;
+                let bottom = at(gx, gy + 1) + (at(gx + 1, gy + 1) - at(gx, gy + 1)) * tx;
+                elevation.push(((top + (bottom - top) * ty) * 255.0).round() as u8);
+            }
+        }
+        elevation
+    }
+
+    // With a plant elevation preference, growth onto a cell only goes ahead
+    // with a probability that is 1 on the preferred extreme of the terrain.
+    fn elevation_allows_growth(&mut self, x: usize, y: usize) -> bool {
+        let preference = self.config.plant_elevation_preference.clamp(-1.0, 1.0);
+        if preference == 0.0 {
+            return true;
+        }
+        let Some(elevation) = self.elevation_at(x, y) else { return true };
+        let height = elevation as f32 / 255.0;
+        let fit = if preference > 0.0 { height } else { 1.0 - height };
+        self.rng.gen::<f32>() < 1.0 - preference.abs() * (1.0 - fit)
+    }
+
+    fn edge_cell(&self, edge: Edge, along: usize) -> (usize, usize) {
+        match edge {
+            Edge::Left => (0, along.min(self.height - 1)),
+            Edge::Right => (self.width - 1, along.min(self.height - 1)),
+            Edge::Top => (along.min(self.width - 1), 0),
+            Edge::Bottom => (along.min(self.width - 1), self.height - 1),
+        }
+    }
+
+    // Removes the live animals standing on `edge`, each with probability
+    // `rate`, so they can move to a neighbouring simulation.
+    pub fn emigrate(&mut self, edge: Edge, rate: f32) -> Vec<Agent> {
+        let (width, height) = (self.width, self.height);
+        let on_edge = |a: &Agent| match edge {
+            Edge::Left => a.x == 0,
+            Edge::Right => a.x == width - 1,
+            Edge::Top => a.y == 0,
+            Edge::Bottom => a.y == height - 1,
+        };
+        let mut emigrants = Vec::new();
+        for agents in [&mut self.herbivores, &mut self.carnivores, &mut self.omnivores, &mut self.scavengers] {
+            let mut index = 0;
+            while index < agents.len() {
+                let agent = &agents[index];
+                if !agent.pending_death && on_edge(agent) && self.rng.gen::<f32>() < rate {
+                    let emigrant = agents.remove(index);
+                    self.occupancy.remove(&emigrant);
+                    emigrants.push(emigrant);
+                } else {
+                    index += 1;
+                }
+            }
+        }
+        emigrants
+    }
+
+    // Places an animal arriving across `edge` at offset `along` on it, with
+    // a fresh id. The animal is handed back if the cell is taken.
+    pub fn immigrate(&mut self, mut agent: Agent, edge: Edge, along: usize) -> Result<(), Agent> {
+        let (x, y) = self.edge_cell(edge, along);
+        if self.occupancy.has_animal(x, y) || self.occupancy.has_terrain(x, y) {
+            return Err(agent);
+        }
+        agent.id = self.next_agent_id;
+        self.next_agent_id += 1;
+        agent.x = x;
+        agent.y = y;
+        self.readmit(agent);
+        Ok(())
+    }
+
+    // Puts an animal back unchanged, e.g. an emigrant that found no room.
+    pub fn readmit(&mut self, agent: Agent) {
+        let agents = match agent.agent_type {
+            AgentType::Herbivore => &mut self.herbivores,
+            AgentType::Carnivore => &mut self.carnivores,
+            AgentType::Omnivore => &mut self.omnivores,
+            AgentType::Scavenger => &mut self.scavengers,
+            _ => return,
+        };
+        self.occupancy.add(&agent);
+        agents.push(agent);
+    }
+
+    // Places any agent on its cell as given, e.g. to set up a scene by
+    // hand. Later agents get ids after the agent's own.
+    pub fn add_agent(&mut self, agent: Agent) {
+        let agents = match agent.agent_type {
+            AgentType::Plant => &mut self.plants,
+            AgentType::Herbivore => &mut self.herbivores,
+            AgentType::Carnivore => &mut self.carnivores,
+            AgentType::Omnivore => &mut self.omnivores,
+            AgentType::Scavenger => &mut self.scavengers,
+            AgentType::Water => &mut self.waters,
+            AgentType::Tree => &mut self.trees,
+            AgentType::Corpse => &mut self.corpses,
+            AgentType::Rock => &mut self.rocks,
+        };
+        self.next_agent_id = self.next_agent_id.max(agent.id + 1);
+        self.occupancy.add(&agent);
+        agents.push(agent);
+    }
+
+    pub fn population(&self) -> Population {
+        let mut plants = vec![0; self.config.plant_species.len()];
+        for plant in &self.plants {
+            plants[plant.species] += 1;
+        }
+        Population {
+            plants,
+            herbivores: self.herbivores.len(),
+            carnivores: self.carnivores.len(),
+            omnivores: self.omnivores.len(),
+            scavengers: self.scavengers.len(),
+            waters: self.waters.len(),
+            trees: self.trees.len(),
+        }
+    }
+
+    // Mean energy of the living animals of one species, or None when there
+    // are none (or for a type that doesn't use energy).
+    pub fn average_energy(&self, agent_type: &AgentType) -> Option<f32> {
+        let agents = match agent_type {
+            AgentType::Herbivore => &self.herbivores,
+            AgentType::Carnivore => &self.carnivores,
+            AgentType::Omnivore => &self.omnivores,
+            AgentType::Scavenger => &self.scavengers,
+            _ => return None,
+        };
+        let (total, count) = agents
+            .iter()
+            .filter(|a| !a.pending_death)
+            .fold((0i64, 0usize), |(total, count), a| (total + a.energy as i64, count + 1));
+        (count > 0).then(|| total as f32 / count as f32)
+    }
+
+    // Shannon index over the living species, from 0 (a single species) to
+    // the log of their number (an even split). An empty world scores 0.
+    pub fn shannon_diversity(&self) -> f64 {
+        let counts = self.population().living();
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total as f64;
+                p * p.recip().ln()
+            })
+            .sum()
+    }
+
+    pub fn is_night(&self) -> bool {
+        let day_length = self.config.day_length;
+        day_length > 0 && self.iteration_count % day_length >= day_length / 2
+    }
+
+    // Spring's multipliers are all 1.0, so with seasons disabled the
+    // configured rates apply unchanged.
+    pub fn current_season(&self) -> Season {
+        if self.config.season_length == 0 {
+            return Season::Spring;
+        }
+        match (self.iteration_count / self.config.season_length) % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn current_weather(&self) -> Weather {
+        self.weather
+    }
+
+    // Sets the weather for `duration` iterations, after which it clears.
+    pub fn force_weather(&mut self, weather: Weather, duration: usize) {
+        self.weather = weather;
+        self.weather_remaining = duration;
+    }
+
+    // Counts down the current rain or drought; once the weather is clear,
+    // a new event may start. Draws from the RNG only when weather is on.
+    fn update_weather(&mut self, stats: &mut SimulationStats) {
+        if self.weather_remaining > 0 {
+            self.weather_remaining -= 1;
+            if self.weather_remaining == 0 {
+                self.weather = Weather::Clear;
+            }
+            return;
+        }
+        self.weather = Weather::Clear;
+        if self.config.weather_event_chance > 0.0 && self.rng.gen::<f32>() < self.config.weather_event_chance {
+            let weather = if self.rng.gen::<bool>() { Weather::Rain } else { Weather::Drought };
+            match weather {
+                Weather::Rain => stats.rain_events += 1,
+                _ => stats.drought_events += 1,
+            }
+            self.force_weather(weather, self.config.weather_duration);
+        }
+    }
+
+    fn plant_growth_rate(&self) -> f32 {
+        let rate = self.config.plant_growth_rate * self.current_season().growth_multiplier() * self.weather.growth_multiplier();
+        if self.is_night() {
+            rate * self.config.night_growth_factor
+        } else {
+            rate
+        }
+    }
+
+    pub fn rebuild_occupancy(&mut self) {
+        self.occupancy = Occupancy::from_agents(
+            self.width,
+            self.height,
+            self.config.plant_species.len(),
+            self.plants.iter()
+                .chain(&self.herbivores)
+                .chain(&self.carnivores)
+                .chain(&self.omnivores)
+                .chain(&self.waters)
+                .chain(&self.trees)
+                .chain(&self.corpses)
+                .chain(&self.rocks)
+                .chain(&self.scavengers),
+        );
+    }
+
+    fn find_at(agents: &[Agent], occupancy: &Occupancy, index: &mut CellIndex, types: &[AgentType], x: usize, y: usize) -> Option<usize> {
+        if types.iter().any(|t| occupancy.has(t, x, y)) {
+            index.find(agents, x, y)
+        } else {
+            None
+        }
+    }
+
+    fn clear_cell(agents: &mut Vec<Agent>, occupancy: &mut Occupancy, types: &[AgentType], x: usize, y: usize) -> usize {
+        if !types.iter().any(|t| occupancy.has(t, x, y)) {
+            return 0;
+        }
+        let before = agents.len();
+        agents.retain(|a| {
+            let hit = a.x == x && a.y == y && types.contains(&a.agent_type);
+            if hit {
+                occupancy.remove(a);
+            }
+            !hit
+        });
+        before - agents.len()
+    }
+
+    // Removes the plants on a cell whose species `doomed` picks.
+    fn clear_plants(plants: &mut Vec<Agent>, occupancy: &mut Occupancy, species: &[PlantSpecies], doomed: impl Fn(&PlantSpecies) -> bool, x: usize, y: usize, stats: &mut SimulationStats) {
+        if !occupancy.has_plant(x, y) {
+            return;
+        }
+        plants.retain(|p| {
+            let hit = p.x == x && p.y == y && doomed(&species[p.species]);
+            if hit {
+                occupancy.remove(p);
+                stats.plants_died(p.species, 1);
+            }
+            !hit
+        });
+    }
+
+    // The plant species with the highest positive `affinity`, and that
+    // affinity; the first one wins ties.
+    fn favoured_species(&self, affinity: impl Fn(&PlantSpecies) -> f32) -> Option<(usize, f32)> {
+        self.config.plant_species.iter().map(affinity).enumerate().filter(|&(_, a)| a > 0.0).fold(None, |best, (species, a)| match best {
+            Some((_, best_a)) if best_a >= a => best,
+            _ => Some((species, a)),
+        })
+    }
+
+    fn offset_cell(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        let nx = x as isize + dx;
+        let ny = y as isize + dy;
+        if self.config.wrap_edges {
+            Some((nx.rem_euclid(self.width as isize) as usize, ny.rem_euclid(self.height as isize) as usize))
+        } else if nx < 0 || ny < 0 || nx >= self.width as isize || ny >= self.height as isize {
+            None
+        } else {
+            Some((nx as usize, ny as usize))
         }
     }
 
-    fn random_adjacent_aux(rng: &mut impl Rng, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
-        let dx: i32 = rng.gen_range(-1..=1);
-        let dy: i32 = rng.gen_range(-1..=1);
-        let new_x = if dx < 0 { x.saturating_sub(dx.abs() as usize) } else { std::cmp::min(x + dx as usize, width - 1) };
-        let new_y = if dy < 0 { y.saturating_sub(dy.abs() as usize) } else { std::cmp::min(y + dy as usize, height - 1) };
+    fn axis_distance(&self, a: usize, b: usize, size: usize) -> usize {
+        let distance = a.abs_diff(b);
+        if self.config.wrap_edges { distance.min(size - distance) } else { distance }
+    }
+
+    fn axis_step(&self, from: usize, to: usize, size: usize) -> isize {
+        let delta = to as isize - from as isize;
+        if self.config.wrap_edges && delta.unsigned_abs() * 2 > size { -delta.signum() } else { delta.signum() }
+    }
+
+    fn random_adjacent_aux(rng: &mut impl Rng, x: usize, y: usize, width: usize, height: usize, wrap: bool, neighborhood: &MovementNeighborhood) -> (usize, usize) {
+        let (dx, dy): (i32, i32) = match neighborhood {
+            MovementNeighborhood::Moore => (rng.gen_range(-1..=1), rng.gen_range(-1..=1)),
+            // Staying put stays one of the choices, as it is for Moore.
+            MovementNeighborhood::VonNeumann => [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)][rng.gen_range(0..5)],
+        };
+        if wrap {
+            return (
+                (x as isize + dx as isize).rem_euclid(width as isize) as usize,
+                (y as isize + dy as isize).rem_euclid(height as isize) as usize,
+            );
+        }
+        let new_x = if dx < 0 { x.saturating_sub(dx.unsigned_abs() as usize) } else { std::cmp::min(x + dx as usize, width - 1) };
+        let new_y = if dy < 0 { y.saturating_sub(dy.unsigned_abs() as usize) } else { std::cmp::min(y + dy as usize, height - 1) };
         (new_x, new_y)
     }
 
-    fn maybe_spawn_water(&mut self, stats: &mut SimulationStats) {
-        let mut rng = rand::thread_rng();
-        if rng.gen::<f32>() < self.config.water_spawn_chance {
-            let x = rng.gen_range(1..(self.width - 1));
-            let y = rng.gen_range(1..(self.height - 1));
+    // A random one of the up to 8 neighbouring cells that holds no animal
+    // or terrain, so offspring aren't born into water, trees or another
+    // animal. None when every neighbour is taken.
+    fn free_adjacent_cell(&mut self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let mut free = Vec::with_capacity(8);
+        for dy in -1..=1 {
             for dx in -1..=1 {
-                for dy in -1..=1 {
-                    let wx = (x as i32 + dx) as usize;
-                    let wy = (y as i32 + dy) as usize;
-                    let removed_light = self.plants.iter().filter(|p| p.x == wx && p.y == wy && p.agent_type == AgentType::LightPlant).count();
-                    let removed_dark = self.plants.iter().filter(|p| p.x == wx && p.y == wy && p.agent_type == AgentType::DarkPlant).count();
-                    stats.light_plant_deaths += removed_light;
-                    stats.dark_plant_deaths += removed_dark;
-                    self.plants.retain(|p| !(p.x == wx && p.y == wy));
-                    self.herbivores.retain(|h| !(h.x == wx && h.y == wy));
-                    self.carnivores.retain(|c| !(c.x == wx && c.y == wy));
-                    self.omnivores.retain(|o| !(o.x == wx && o.y == wy));
-                    self.trees.retain(|t| !(t.x == wx && t.y == wy));
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let Some((cx, cy)) = self.offset_cell(x, y, dx, dy) else { continue };
+                if (cx, cy) != (x, y) && !self.occupancy.has_animal(cx, cy) && !self.occupancy.has_terrain(cx, cy) && !free.contains(&(cx, cy)) {
+                    free.push((cx, cy));
+                }
+            }
+        }
+        if free.is_empty() {
+            None
+        } else {
+            Some(free[self.rng.gen_range(0..free.len())])
+        }
+    }
+
+    // Scans rings of growing Chebyshev distance, row by row, so the first
+    // food cell found is the nearest one with deterministic tie-breaking.
+    fn nearest_food(&self, x: usize, y: usize, radius: usize, is_food: impl Fn(&Occupancy, usize, usize) -> bool) -> Option<(usize, usize)> {
+        for distance in 0..=radius as isize {
+            for dy in -distance..=distance {
+                for dx in -distance..=distance {
+                    if dx.abs() != distance && dy.abs() != distance {
+                        continue;
+                    }
+                    let Some((cx, cy)) = self.offset_cell(x, y, dx, dy) else { continue };
+                    if is_food(&self.occupancy, cx, cy) {
+                        return Some((cx, cy));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // Picks the free neighbouring cell farthest (Chebyshev) from the closest
+    // adjacent predator, or None when no predator is adjacent.
+    fn flee_target(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let occupancy = &self.occupancy;
+        let mut neighbours = Vec::with_capacity(8);
+        for dy in -1..=1isize {
+            for dx in -1..=1isize {
+                if (dx == 0 && dy == 0) || (self.config.movement_neighborhood == MovementNeighborhood::VonNeumann && dx != 0 && dy != 0) {
+                    continue;
+                }
+                if let Some(cell) = self.offset_cell(x, y, dx, dy) {
+                    neighbours.push(cell);
+                }
+            }
+        }
+        let predators: Vec<(usize, usize)> = neighbours
+            .iter()
+            .copied()
+            .filter(|&(nx, ny)| occupancy.has(&AgentType::Carnivore, nx, ny) || occupancy.has(&AgentType::Omnivore, nx, ny))
+            .collect();
+        if predators.is_empty() {
+            return None;
+        }
+        let mut best: Option<((usize, usize), usize)> = None;
+        for (nx, ny) in neighbours {
+            if occupancy.has_terrain(nx, ny) || occupancy.has_animal(nx, ny) {
+                continue;
+            }
+            let distance = predators
+                .iter()
+                .map(|&(px, py)| self.axis_distance(px, nx, self.width).max(self.axis_distance(py, ny, self.height)))
+                .min()
+                .unwrap_or(0);
+            if best.is_none_or(|(_, best_distance)| distance > best_distance) {
+                best = Some(((nx, ny), distance));
+            }
+        }
+        best.map(|(cell, _)| cell)
+    }
+
+    // Marks an animal dead of `cause` and counts it: in its species' deaths,
+    // under that cause, and by its age at `iteration` in its species'
+    // lifespan histogram. Every animal death goes through here so the
+    // totals and the breakdowns agree.
+    fn record_death(agent: &mut Agent, cause: &str, iteration: usize, stats: &mut SimulationStats) {
+        agent.pending_death = true;
+        agent.death_cause = Some(cause.to_string());
+        let (deaths, causes, lifespans) = match agent.agent_type {
+            AgentType::Herbivore => (&mut stats.herbivore_deaths, &mut stats.herbivore_death_causes, &mut stats.herbivore_lifespans),
+            AgentType::Carnivore => (&mut stats.carnivore_deaths, &mut stats.carnivore_death_causes, &mut stats.carnivore_lifespans),
+            AgentType::Omnivore => (&mut stats.omnivore_deaths, &mut stats.omnivore_death_causes, &mut stats.omnivore_lifespans),
+            AgentType::Scavenger => (&mut stats.scavenger_deaths, &mut stats.scavenger_death_causes, &mut stats.scavenger_lifespans),
+            _ => return,
+        };
+        *deaths += 1;
+        *causes.entry(cause.to_string()).or_default() += 1;
+        if let Some(birth) = agent.birth_iteration {
+            let bucket = iteration.saturating_sub(birth) / LIFESPAN_BUCKET;
+            if lifespans.len() <= bucket {
+                lifespans.resize(bucket + 1, 0);
+            }
+            lifespans[bucket] += 1;
+        }
+    }
+
+    // Removes the animals on a cell that water or a tree just took over.
+    fn clear_animals(&mut self, x: usize, y: usize, stats: &mut SimulationStats) {
+        if !self.occupancy.has_animal(x, y) {
+            return;
+        }
+        let iteration = self.iteration_count;
+        let occupancy = &mut self.occupancy;
+        for agents in [&mut self.herbivores, &mut self.carnivores, &mut self.omnivores, &mut self.scavengers] {
+            agents.retain_mut(|a| {
+                let hit = a.x == x && a.y == y;
+                if hit {
+                    occupancy.remove(a);
+                    Self::record_death(a, "Overridden by Water/Tree", iteration, stats);
+                }
+                !hit
+            });
+        }
+    }
+
+    // Energy an animal with `energy` hands to an offspring, or None when it
+    // can't reproduce: `cost` when set, otherwise half its energy. Parent
+    // and offspring must both stay above the starvation threshold, or the
+    // split would just produce two starving animals.
+    fn offspring_energy(energy: i32, threshold: i32, cost: i32, starvation_threshold: i32) -> Option<i32> {
+        let offspring = if cost > 0 { cost } else { energy / 2 };
+        (energy >= threshold + cost && offspring > starvation_threshold && energy - offspring > starvation_threshold).then_some(offspring)
+    }
+
+    fn step_toward(&self, x: usize, y: usize, target_x: usize, target_y: usize) -> (usize, usize) {
+        let mut dx = self.axis_step(x, target_x, self.width);
+        let mut dy = self.axis_step(y, target_y, self.height);
+        // Without diagonal steps, close the larger gap first.
+        if self.config.movement_neighborhood == MovementNeighborhood::VonNeumann && dx != 0 && dy != 0 {
+            if self.axis_distance(x, target_x, self.width) >= self.axis_distance(y, target_y, self.height) {
+                dy = 0;
+            } else {
+                dx = 0;
+            }
+        }
+        self.offset_cell(x, y, dx, dy).unwrap_or((x, y))
+    }
+
+    // Draws a starting cell for a species, indexed in `Population::living`
+    // order, according to the configured distribution.
+    fn initial_cell(rng: &mut impl Rng, distribution: &InitialDistribution, centers: &[(usize, usize)], species: usize, width: usize, height: usize) -> (usize, usize) {
+        match distribution {
+            InitialDistribution::Uniform => (rng.gen_range(0..width), rng.gen_range(0..height)),
+            InitialDistribution::Clustered { spread, .. } => {
+                let (cx, cy) = centers[rng.gen_range(0..centers.len())];
+                let mut gaussian = || {
+                    let u1: f32 = 1.0 - rng.gen::<f32>();
+                    let u2: f32 = rng.gen();
+                    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos() * *spread as f32
+                };
+                let x = (cx as f32 + gaussian()).round().clamp(0.0, (width - 1) as f32) as usize;
+                let y = (cy as f32 + gaussian()).round().clamp(0.0, (height - 1) as f32) as usize;
+                (x, y)
+            }
+            InitialDistribution::Quadrants => {
+                let (half_w, half_h) = (width.div_ceil(2), height.div_ceil(2));
+                let (x0, y0, w, h) = match species {
+                    0 => (0, 0, half_w, half_h),
+                    1 => (width - half_w, 0, half_w, half_h),
+                    2 => (0, height - half_h, half_w, half_h),
+                    3 => (width - half_w, height - half_h, half_w, half_h),
+                    _ => (width / 4, height / 4, half_w, half_h),
+                };
+                (x0 + rng.gen_range(0..w), y0 + rng.gen_range(0..h))
+            }
+        }
+    }
+
+    // Animals can't share a cell, so cells already taken are redrawn. After
+    // a few misses it falls back to any free cell on the grid.
+    fn initial_free_cell(rng: &mut impl Rng, distribution: &InitialDistribution, centers: &[(usize, usize)], species: usize, taken: &mut [bool], width: usize, height: usize) -> Option<(usize, usize)> {
+        if *distribution == InitialDistribution::Uniform {
+            return Self::random_free_cell(rng, taken, width);
+        }
+        for _ in 0..64 {
+            let (x, y) = Self::initial_cell(rng, distribution, centers, species, width, height);
+            if !taken[y * width + x] {
+                taken[y * width + x] = true;
+                return Some((x, y));
+            }
+        }
+        Self::random_free_cell(rng, taken, width)
+    }
+
+    fn random_free_cell(rng: &mut impl Rng, taken: &mut [bool], width: usize) -> Option<(usize, usize)> {
+        if taken.iter().all(|&t| t) {
+            return None;
+        }
+        loop {
+            let index = rng.gen_range(0..taken.len());
+            if !taken[index] {
+                taken[index] = true;
+                return Some((index % width, index / width));
+            }
+        }
+    }
+
+    // A live animal never shares its cell with another one, except that
+    // predators may step onto their prey to eat it.
+    fn is_blocked(&self, animal: &Agent, x: usize, y: usize) -> bool {
+        if (animal.x, animal.y) == (x, y) {
+            return false;
+        }
+        let cells = &self.occupancy;
+        if cells.has(&AgentType::Rock, x, y) {
+            return true;
+        }
+        match animal.agent_type {
+            AgentType::Carnivore => cells.has(&AgentType::Carnivore, x, y) || cells.has(&AgentType::Scavenger, x, y),
+            AgentType::Omnivore => cells.has(&AgentType::Carnivore, x, y) || cells.has(&AgentType::Omnivore, x, y) || cells.has(&AgentType::Scavenger, x, y),
+            _ => cells.has_animal(x, y),
+        }
+    }
+
+    // Moving costs `move_energy_cost`, on top of the metabolic loss every
+    // animal pays, but only when the animal actually changes cells.
+    fn try_move(&mut self, animal: &mut Agent, x: usize, y: usize) {
+        if (x, y) != (animal.x, animal.y) && !self.is_blocked(animal, x, y) {
+            animal.energy -= Self::scaled_loss(self.config.move_energy_cost as f32, self.config.time_scale, animal);
+            self.occupancy.relocate(animal, x, y);
+            animal.x = x;
+            animal.y = y;
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn assert_one_animal_per_cell(&self) {
+        let mut seen = vec![false; self.width * self.height];
+        for animal in self.herbivores.iter().chain(&self.carnivores).chain(&self.omnivores).chain(&self.scavengers) {
+            if animal.pending_death {
+                continue;
+            }
+            let cell = &mut seen[animal.y * self.width + animal.x];
+            assert!(!*cell, "two animals share cell ({}, {}) at iteration {}", animal.x, animal.y, self.iteration_count);
+            *cell = true;
+        }
+    }
+
+    // A per-step energy loss scaled by `time_scale`. Whatever doesn't add
+    // up to a whole unit is kept as the agent's debt and taken once it
+    // does, so a loss of 1 at scale 0.25 costs 1 energy every 4 steps.
+    fn scaled_loss(loss: f32, time_scale: f32, agent: &mut Agent) -> i32 {
+        if time_scale == 1.0 && loss.fract() == 0.0 {
+            return loss as i32;
+        }
+        let exact = loss * time_scale + agent.energy_debt;
+        let whole = exact.floor();
+        agent.energy_debt = exact - whole;
+        whole as i32
+    }
+
+    fn overfed_decay(energy: i32, threshold: i32, rate: f32) -> i32 {
+        if energy > threshold {
+            ((energy - threshold) as f32 * rate).ceil() as i32
+        } else {
+            0
+        }
+    }
+
+    // Offspring a species may still have this step before its live count
+    // reaches `cap`. Counting from the start of the step means deaths
+    // during the step only make room from the next one.
+    fn birth_room(agents: &[Agent], cap: Option<usize>) -> usize {
+        let Some(cap) = cap else { return usize::MAX };
+        cap.saturating_sub(agents.iter().filter(|a| !a.pending_death).count())
+    }
+
+    fn reached_max_age(agent: &Agent, iteration: usize, max_age: usize) -> bool {
+        max_age > 0 && !agent.pending_death && agent.birth_iteration.is_some_and(|birth| iteration.saturating_sub(birth) >= max_age)
+    }
+
+    fn update_longest_lived(&self, stats: &mut SimulationStats) {
+        let mut record = stats.longest_lived.take();
+        if let Some(holder) = record.as_mut() {
+            holder.alive = false;
+        }
+        let animals = self.herbivores.iter().chain(self.carnivores.iter()).chain(self.omnivores.iter()).chain(self.scavengers.iter());
+        for animal in animals.filter(|a| !a.pending_death) {
+            let Some(birth) = animal.birth_iteration else { continue };
+            let age = self.iteration_count - birth;
+            match record.as_mut() {
+                Some(holder) if holder.id == animal.id => {
+                    holder.age = age;
+                    holder.alive = true;
+                }
+                Some(holder) if holder.age >= age => {}
+                _ => {
+                    record = Some(LongestLived {
+                        id: animal.id,
+                        agent_type: animal.agent_type.clone(),
+                        age,
+                        parent_id: animal.parent_id,
+                        generation: animal.generation,
+                        alive: true,
+                    });
+                }
+            }
+        }
+        stats.longest_lived = record;
+    }
+
+    fn is_lethal_cell(&self, x: usize, y: usize) -> bool {
+        (self.config.water_is_lethal && self.occupancy.has(&AgentType::Water, x, y))
+            || (self.config.tree_is_lethal && self.occupancy.has(&AgentType::Tree, x, y))
+    }
+
+    fn drink(&self, animal: &mut Agent) {
+        if self.config.water_is_lethal || !self.occupancy.has(&AgentType::Water, animal.x, animal.y) {
+            return;
+        }
+        if animal.energy < self.config.water_drink_cap {
+            animal.energy = (animal.energy + self.config.water_drink_gain).min(self.config.water_drink_cap);
+            self.cap_energy(animal);
+        }
+    }
+
+    fn max_energy(&self, agent_type: &AgentType) -> i32 {
+        match agent_type {
+            AgentType::Herbivore => self.config.herbivore_max_energy,
+            AgentType::Carnivore => self.config.carnivore_max_energy,
+            AgentType::Omnivore => self.config.omnivore_max_energy,
+            AgentType::Scavenger => self.config.scavenger_max_energy,
+            _ => i32::MAX,
+        }
+    }
+
+    // Drops whatever an animal gained beyond its species' energy cap.
+    fn cap_energy(&self, animal: &mut Agent) {
+        animal.energy = animal.energy.min(self.max_energy(&animal.agent_type));
+    }
+
+    // A cell holds at most one corpse; another death there makes the
+    // existing one fresh again instead.
+    fn leave_corpse(&mut self, x: usize, y: usize) {
+        if self.occupancy.has_terrain(x, y) {
+            return;
+        }
+        if self.occupancy.has(&AgentType::Corpse, x, y) {
+            if let Some(corpse) = self.corpses.iter_mut().find(|c| c.x == x && c.y == y) {
+                corpse.birth_iteration = Some(self.iteration_count);
+            }
+            return;
+        }
+        let corpse = Agent::new_corpse(self.next_agent_id, x, y, self.iteration_count);
+        self.next_agent_id += 1;
+        self.occupancy.add(&corpse);
+        self.corpses.push(corpse);
+    }
+
+    fn decay_corpses(&mut self, stats: &mut SimulationStats) {
+        let current_it = self.iteration_count;
+        let decay_time = self.config.corpse_decay_time;
+        let (decayed, remaining): (Vec<Agent>, Vec<Agent>) = std::mem::take(&mut self.corpses)
+            .into_iter()
+            .partition(|c| c.birth_iteration.is_some_and(|birth| current_it - birth >= decay_time));
+        self.corpses = remaining;
+        for corpse in decayed {
+            self.occupancy.remove(&corpse);
+            if self.config.plant_species.is_empty() || self.occupancy.has_plant(corpse.x, corpse.y) || self.occupancy.has_terrain(corpse.x, corpse.y) {
+                continue;
+            }
+            let plant = Agent::new_plant(self.next_agent_id, 0, corpse.x, corpse.y);
+            self.next_agent_id += 1;
+            self.occupancy.add(&plant);
+            self.plants.push(plant);
+            stats.plant_born(0);
+        }
+    }
+
+    // Each existing plant may seed one free 4-neighbour with its own species.
+    // New plants are collected and applied by the caller, so growth never
+    // cascades within a step.
+    fn spread_plants_locally(&mut self, stats: &mut SimulationStats) -> Vec<Agent> {
+        let growth_rate = self.plant_growth_rate();
+        let mut new_plants: Vec<Agent> = Vec::new();
+        for index in 0..self.plants.len() {
+            let plant = &self.plants[index];
+            if self.rng.gen::<f32>() >= growth_rate * self.config.plant_species[plant.species].growth_mult {
+                continue;
+            }
+            let free: Vec<(usize, usize)> = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                .into_iter()
+                .filter_map(|(dx, dy)| self.offset_cell(plant.x, plant.y, dx, dy))
+                .filter(|&(nx, ny)| !self.occupancy.has_plant(nx, ny) && !self.occupancy.has_terrain(nx, ny))
+                .filter(|&(nx, ny)| !new_plants.iter().any(|p| p.x == nx && p.y == ny))
+                .collect();
+            if free.is_empty() {
+                continue;
+            }
+            let species = plant.species;
+            let (nx, ny) = free[self.rng.gen_range(0..free.len())];
+            if !self.elevation_allows_growth(nx, ny) {
+                continue;
+            }
+            stats.plant_born(species);
+            new_plants.push(Agent::new_plant(self.next_agent_id, species, nx, ny));
+            self.next_agent_id += 1;
+        }
+        new_plants
+    }
+
+    // A plant picks a random plant of its own species within the pollination
+    // radius and seeds a free cell in the rectangle spanning the two, grown
+    // by one cell so patches expand. Without a partner it can't reproduce.
+    fn pollinate_plants(&mut self, stats: &mut SimulationStats) -> Vec<Agent> {
+        let growth_rate = self.plant_growth_rate();
+        let radius = self.config.pollination_radius as isize;
+        let mut new_plants: Vec<Agent> = Vec::new();
+        for index in 0..self.plants.len() {
+            let plant = &self.plants[index];
+            if self.rng.gen::<f32>() >= growth_rate * self.config.plant_species[plant.species].growth_mult {
+                continue;
+            }
+            let partners: Vec<(isize, isize)> = (-radius..=radius)
+                .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+                .filter(|&offset| offset != (0, 0))
+                .filter(|&(dx, dy)| {
+                    self.offset_cell(plant.x, plant.y, dx, dy)
+                        .is_some_and(|(nx, ny)| (nx, ny) != (plant.x, plant.y) && self.occupancy.has_plant_species(plant.species, nx, ny))
+                })
+                .collect();
+            if partners.is_empty() {
+                continue;
+            }
+            let (px, py) = partners[self.rng.gen_range(0..partners.len())];
+            let free: Vec<(usize, usize)> = (py.min(0) - 1..=py.max(0) + 1)
+                .flat_map(|dy| (px.min(0) - 1..=px.max(0) + 1).map(move |dx| (dx, dy)))
+                .filter_map(|(dx, dy)| self.offset_cell(plant.x, plant.y, dx, dy))
+                .filter(|&(nx, ny)| !self.occupancy.has_plant(nx, ny) && !self.occupancy.has_terrain(nx, ny))
+                .filter(|&(nx, ny)| !new_plants.iter().any(|p| p.x == nx && p.y == ny))
+                .collect();
+            if free.is_empty() {
+                continue;
+            }
+            let species = plant.species;
+            let (nx, ny) = free[self.rng.gen_range(0..free.len())];
+            if !self.elevation_allows_growth(nx, ny) {
+                continue;
+            }
+            stats.plant_born(species);
+            new_plants.push(Agent::new_plant(self.next_agent_id, species, nx, ny));
+            self.next_agent_id += 1;
+        }
+        new_plants
+    }
+
+    // Side of the block a lake or tree patch covers, clipped to the grid so
+    // a block never leaves it or, with wrapping, overlaps itself.
+    fn block_size(&self, size: usize) -> usize {
+        size.clamp(1, self.width.min(self.height))
+    }
+
+    // Centre of a new lake; without wrapping, its whole `size` block must
+    // fit inside the grid.
+    fn random_water_site(&mut self, size: usize) -> (usize, usize) {
+        if self.config.wrap_edges {
+            (self.rng.gen_range(0..self.width), self.rng.gen_range(0..self.height))
+        } else {
+            let half = (size - 1) / 2;
+            let (after_x, after_y) = (self.width - (size - 1 - half), self.height - (size - 1 - half));
+            (self.rng.gen_range(half..after_x), self.rng.gen_range(half..after_y))
+        }
+    }
+
+    fn maybe_spawn_water(&mut self, stats: &mut SimulationStats) {
+        let chance = self.config.water_spawn_chance * self.current_season().water_multiplier() * self.weather.water_spawn_multiplier();
+        if self.rng.gen::<f32>() < chance {
+            let size = self.block_size(self.config.water_block_size);
+            let mut site = self.random_water_site(size);
+            if !self.elevation.is_empty() {
+                // Lakes form in the lowest of a few candidate sites.
+                for _ in 1..WATER_SITE_CANDIDATES {
+                    let candidate = self.random_water_site(size);
+                    if self.elevation_at(candidate.0, candidate.1) < self.elevation_at(site.0, site.1) {
+                        site = candidate;
+                    }
+                }
+            }
+            let (x, y) = site;
+            let half = ((size - 1) / 2) as isize;
+            for dx in -half..(size as isize - half) {
+                for dy in -half..(size as isize - half) {
+                    let Some((wx, wy)) = self.offset_cell(x, y, dx, dy) else { continue };
+                    if self.occupancy.has(&AgentType::Rock, wx, wy) {
+                        continue;
+                    }
+                    Self::clear_plants(&mut self.plants, &mut self.occupancy, &self.config.plant_species, |_| true, wx, wy, stats);
+                    if self.config.water_is_lethal {
+                        self.clear_animals(wx, wy, stats);
+                    }
+                    Self::clear_cell(&mut self.trees, &mut self.occupancy, &[AgentType::Tree], wx, wy);
+                    Self::clear_cell(&mut self.corpses, &mut self.occupancy, &[AgentType::Corpse], wx, wy);
                     let water = Agent::new_water(self.next_agent_id, wx, wy, self.iteration_count);
                     self.next_agent_id += 1;
+                    self.occupancy.add(&water);
                     self.waters.push(water);
                     stats.water_births += 1;
                 }
@@ -138,355 +1523,1493 @@ impl Ecosystem {
 
     fn evaporate_water(&mut self, stats: &mut SimulationStats) {
         let current_it = self.iteration_count;
+        let lifespan = self.config.water_lifespan;
+        let evaporation = self.weather.evaporation_rate();
+        let occupancy = &mut self.occupancy;
         let before = self.waters.len();
+        if evaporation > 1 {
+            // Moving the birth back ages the lake by the extra iterations.
+            for w in self.waters.iter_mut() {
+                w.birth_iteration = w.birth_iteration.map(|birth| birth.saturating_sub(evaporation - 1));
+            }
+        }
         self.waters.retain(|w| {
-            if let Some(birth) = w.birth_iteration {
-                (current_it - birth) < self.config.water_lifespan
+            let alive = if let Some(birth) = w.birth_iteration {
+                (current_it - birth) < lifespan
             } else {
                 true
+            };
+            if !alive {
+                occupancy.remove(w);
             }
+            alive
         });
         let after = self.waters.len();
         stats.water_deaths += before - after;
     }
 
-    fn handle_water_influence(&mut self, stats: &mut SimulationStats) {
-        let mut rng = rand::thread_rng();
-        for w in &self.waters {
-            let w_x = w.x as i32;
-            let w_y = w.y as i32;
-            for dx in -5..=5 {
-                for dy in -5..=5 {
-                    let nx = w_x + dx;
-                    let ny = w_y + dy;
-                    if nx < 0 || ny < 0 || nx >= self.width as i32 || ny >= self.height as i32 {
+    // Near water, species with a negative water affinity die and the one
+    // with the highest positive affinity sprouts.
+    fn handle_water_influence(&mut self, stats: &mut SimulationStats) {
+        let radius = self.config.water_influence_radius as isize;
+        let favoured = self.favoured_species(|species| species.water_affinity);
+        for w in &self.waters {
+            for dx in -radius..=radius {
+                for dy in -radius..=radius {
+                    let Some((ux, uy)) = self.offset_cell(w.x, w.y, dx, dy) else { continue };
+                    Self::clear_plants(&mut self.plants, &mut self.occupancy, &self.config.plant_species, |species| species.water_affinity < 0.0, ux, uy, stats);
+                    let Some((species, affinity)) = favoured else { continue };
+                    if self.rng.gen::<f32>() < (self.config.plant_growth_rate * self.config.water_plant_growth_mult * affinity) {
+                        let no_plant = !self.occupancy.has_plant(ux, uy);
+                        let no_water = !self.occupancy.has(&AgentType::Water, ux, uy);
+                        let no_rock = !self.occupancy.has(&AgentType::Rock, ux, uy);
+                        if no_plant && no_water && no_rock {
+                            let plant = Agent::new_plant(self.next_agent_id, species, ux, uy);
+                            self.next_agent_id += 1;
+                            self.occupancy.add(&plant);
+                            self.plants.push(plant);
+                            stats.plant_born(species);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn maybe_spawn_tree(&mut self, stats: &mut SimulationStats) {
+        if self.rng.gen::<f32>() < self.config.tree_spawn_chance {
+            let size = self.block_size(self.config.tree_block_size);
+            let (x, y) = if self.config.wrap_edges {
+                (self.rng.gen_range(0..self.width), self.rng.gen_range(0..self.height))
+            } else {
+                (self.rng.gen_range(0..(self.width - size + 1)), self.rng.gen_range(0..(self.height - size + 1)))
+            };
+            for dx in 0..size as isize {
+                for dy in 0..size as isize {
+                    let Some((tx, ty)) = self.offset_cell(x, y, dx, dy) else { continue };
+                    if self.occupancy.has(&AgentType::Rock, tx, ty) {
+                        continue;
+                    }
+                    Self::clear_plants(&mut self.plants, &mut self.occupancy, &self.config.plant_species, |_| true, tx, ty, stats);
+                    if self.config.tree_is_lethal {
+                        self.clear_animals(tx, ty, stats);
+                    }
+                    Self::clear_cell(&mut self.waters, &mut self.occupancy, &[AgentType::Water], tx, ty);
+                    Self::clear_cell(&mut self.corpses, &mut self.occupancy, &[AgentType::Corpse], tx, ty);
+                    let tree = Agent::new_tree(self.next_agent_id, tx, ty, self.iteration_count);
+                    self.next_agent_id += 1;
+                    self.occupancy.add(&tree);
+                    self.trees.push(tree);
+                    stats.tree_births += 1;
+                }
+            }
+        }
+    }
+
+    fn evaporate_trees(&mut self, stats: &mut SimulationStats) {
+        let current_it = self.iteration_count;
+        let lifespan = self.config.tree_lifespan;
+        let occupancy = &mut self.occupancy;
+        let before = self.trees.len();
+        self.trees.retain(|t| {
+            let alive = if let Some(birth) = t.birth_iteration {
+                (current_it - birth) < lifespan
+            } else {
+                true
+            };
+            if !alive {
+                occupancy.remove(t);
+            }
+            alive
+        });
+        let after = self.trees.len();
+        stats.tree_deaths += before - after;
+    }
+
+    // The same as `handle_water_influence`, by tree affinity.
+    fn handle_tree_influence(&mut self, stats: &mut SimulationStats) {
+        let radius = self.config.tree_influence_radius as isize;
+        let favoured = self.favoured_species(|species| species.tree_affinity);
+        for t in &self.trees {
+            for dx in -radius..=radius {
+                for dy in -radius..=radius {
+                    let Some((ux, uy)) = self.offset_cell(t.x, t.y, dx, dy) else { continue };
+                    Self::clear_plants(&mut self.plants, &mut self.occupancy, &self.config.plant_species, |species| species.tree_affinity < 0.0, ux, uy, stats);
+                    let Some((species, affinity)) = favoured else { continue };
+                    if self.rng.gen::<f32>() < self.config.tree_plant_chance * affinity {
+                        let no_plant = !self.occupancy.has_plant(ux, uy);
+                        let no_water = !self.occupancy.has(&AgentType::Water, ux, uy);
+                        let no_tree = !self.occupancy.has(&AgentType::Tree, ux, uy);
+                        let no_rock = !self.occupancy.has(&AgentType::Rock, ux, uy);
+                        if no_plant && no_water && no_tree && no_rock {
+                            let plant = Agent::new_plant(self.next_agent_id, species, ux, uy);
+                            self.next_agent_id += 1;
+                            self.occupancy.add(&plant);
+                            self.plants.push(plant);
+                            stats.plant_born(species);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn animal_vecs(&mut self) -> [&mut Vec<Agent>; 4] {
+        [&mut self.herbivores, &mut self.carnivores, &mut self.omnivores, &mut self.scavengers]
+    }
+
+    // Takes the animals that died this step but are still in their vector,
+    // such as eaten prey, off the grid and leaves their corpses, so the
+    // vectors and population counts only hold live animals between steps.
+    fn remove_dead(&mut self) {
+        let mut dead = Vec::new();
+        for agents in self.animal_vecs() {
+            agents.retain(|a| {
+                if a.pending_death {
+                    dead.push(a.clone());
+                }
+                !a.pending_death
+            });
+        }
+        for agent in dead {
+            self.occupancy.remove(&agent);
+            self.leave_corpse(agent.x, agent.y);
+        }
+    }
+
+    // Infects new animals, then ages each existing infection: the animal
+    // pays `disease_energy_loss`, may recover into temporary immunity, and
+    // dies once the infection reaches `disease_duration`. Animals infected
+    // this step only start spreading and ageing the disease next step.
+    fn progress_disease(&mut self, stats: &mut SimulationStats) {
+        let active = self.animal_vecs().iter().any(|agents| agents.iter().any(|a| a.infected || a.immunity > 0));
+        if !active && self.config.disease_spawn_chance <= 0.0 {
+            return;
+        }
+
+        // Which live animal, if any, stands on each cell: (species, index).
+        let width = self.width;
+        let mut cells: Vec<Option<(usize, usize)>> = vec![None; width * self.height];
+        for (species, agents) in self.animal_vecs().into_iter().enumerate() {
+            for (index, agent) in agents.iter().enumerate().filter(|(_, a)| !a.pending_death) {
+                cells[agent.y * width + agent.x] = Some((species, index));
+            }
+        }
+
+        let mut newly_infected = Vec::new();
+        if self.config.disease_spawn_chance > 0.0 && self.rng.gen::<f32>() < self.config.disease_spawn_chance {
+            let animals: Vec<(usize, usize)> = cells.iter().flatten().copied().collect();
+            if !animals.is_empty() {
+                newly_infected.push(animals[self.rng.gen_range(0..animals.len())]);
+            }
+        }
+        let spreaders: Vec<(usize, usize)> = self.animal_vecs()
+            .iter()
+            .flat_map(|agents| agents.iter().filter(|a| a.infected && !a.pending_death).map(|a| (a.x, a.y)))
+            .collect();
+        for (x, y) in spreaders {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if dx == 0 && dy == 0 {
                         continue;
                     }
-
-                    let ux = nx as usize;
-                    let uy = ny as usize;
-                    let before = self.plants.len();
-                    self.plants.retain(|p| !(p.x == ux && p.y == uy && p.agent_type == AgentType::DarkPlant));
-                    let after = self.plants.len();
-                    let removed = before - after;
-                    stats.dark_plant_deaths += removed;
-                    if rng.gen::<f32>() < (self.config.plant_growth_rate * 3.0) {
-                        let no_plant = !self.plants.iter().any(|p| p.x == ux && p.y == uy);
-                        let no_water = !self.waters.iter().any(|wa| wa.x == ux && wa.y == uy);
-                        if no_plant && no_water {
-                            let new_l = Agent::new(self.next_agent_id, AgentType::LightPlant, ux, uy, 0);
-                            self.next_agent_id += 1;
-                            self.plants.push(new_l);
-                            stats.light_plant_births += 1;
+                    let Some((nx, ny)) = self.offset_cell(x, y, dx, dy) else { continue };
+                    if let Some(target) = cells[ny * width + nx] {
+                        if self.rng.gen::<f32>() < self.config.transmission_rate {
+                            newly_infected.push(target);
                         }
                     }
                 }
             }
         }
-    }
 
-    fn maybe_spawn_tree(&mut self, stats: &mut SimulationStats) {
-        let mut rng = rand::thread_rng();
-        if rng.gen::<f32>() < self.config.tree_spawn_chance {
-            let x = rng.gen_range(0..(self.width - 1));
-            let y = rng.gen_range(0..(self.height - 1));
-            for dx in 0..2 {
-                for dy in 0..2 {
-                    let tx = x + dx;
-                    let ty = y + dy;
-                    let removed_light = self.plants.iter().filter(|p| p.x == tx && p.y == ty && p.agent_type == AgentType::LightPlant).count();
-                    let removed_dark = self.plants.iter().filter(|p| p.x == tx && p.y == ty && p.agent_type == AgentType::DarkPlant).count();
-                    stats.light_plant_deaths += removed_light;
-                    stats.dark_plant_deaths += removed_dark;
-                    self.plants.retain(|p| !(p.x == tx && p.y == ty));
-                    self.herbivores.retain(|h| !(h.x == tx && h.y == ty));
-                    self.carnivores.retain(|c| !(c.x == tx && c.y == ty));
-                    self.omnivores.retain(|o| !(o.x == tx && o.y == ty));
-                    self.waters.retain(|w| !(w.x == tx && w.y == ty));
-                    let tree = Agent::new_tree(self.next_agent_id, tx, ty, self.iteration_count);
-                    self.next_agent_id += 1;
-                    self.trees.push(tree);
-                    stats.tree_births += 1;
+        let config = &self.config;
+        let mut dead = Vec::new();
+        for agents in [&mut self.herbivores, &mut self.carnivores, &mut self.omnivores, &mut self.scavengers] {
+            for agent in agents.iter_mut().filter(|a| !a.pending_death) {
+                if agent.infected {
+                    agent.infection_age += 1;
+                    agent.energy -= Self::scaled_loss(config.disease_energy_loss as f32, config.time_scale, agent);
+                    if self.rng.gen::<f32>() < config.recovery_chance {
+                        agent.infected = false;
+                        agent.infection_age = 0;
+                        agent.immunity = config.immunity_duration;
+                        stats.recoveries += 1;
+                    } else if agent.infection_age >= config.disease_duration {
+                        Self::record_death(agent, "Disease", self.iteration_count, stats);
+                        dead.push(agent.clone());
+                    }
+                } else {
+                    agent.immunity = agent.immunity.saturating_sub(1);
                 }
             }
         }
+        for (species, index) in newly_infected {
+            let agent = &mut self.animal_vecs()[species][index];
+            if agent.is_susceptible() {
+                agent.infected = true;
+                agent.infection_age = 0;
+                stats.infections += 1;
+            }
+        }
+
+        for agents in self.animal_vecs() {
+            agents.retain(|a| !dead.iter().any(|d: &Agent| d.id == a.id));
+        }
+        for agent in dead {
+            stats.disease_deaths += 1;
+            self.occupancy.remove(&agent);
+            self.leave_corpse(agent.x, agent.y);
+        }
     }
 
-    fn evaporate_trees(&mut self, stats: &mut SimulationStats) {
-        let current_it = self.iteration_count;
-        let before = self.trees.len();
-        self.trees.retain(|t| {
-            if let Some(birth) = t.birth_iteration {
-                (current_it - birth) < self.config.tree_lifespan
+    // Scavengers head for corpses they can see and eat the one on their
+    // cell; with no corpses around they wander and eventually starve.
+    fn step_scavengers(&mut self, stats: &mut SimulationStats, reproduction_multiplier: f32) {
+        let mut current_scavengers = std::mem::take(&mut self.scavengers);
+        if self.config.stable_processing {
+            current_scavengers.sort_by_key(|a| a.id);
+        }
+        let mut updated_scavengers = Vec::new();
+        let mut new_scavengers = Vec::new();
+
+        for mut scavenger in current_scavengers {
+            let food = if self.config.scavenger_vision_radius > 0 {
+                self.nearest_food(scavenger.x, scavenger.y, self.config.scavenger_vision_radius, |cells, x, y| cells.has(&AgentType::Corpse, x, y))
             } else {
-                true
+                None
+            };
+            if let Some((fx, fy)) = food {
+                let (nx, ny) = self.step_toward(scavenger.x, scavenger.y, fx, fy);
+                self.try_move(&mut scavenger, nx, ny);
+            } else if self.rng.gen::<f32>() < scavenger.genome.move_chance {
+                let (nx, ny) = Self::random_adjacent_aux(&mut self.rng, scavenger.x, scavenger.y, self.width, self.height, self.config.wrap_edges, &self.config.movement_neighborhood);
+                self.try_move(&mut scavenger, nx, ny);
             }
-        });
-        let after = self.trees.len();
-        stats.tree_deaths += before - after;
-    }
 
-    fn handle_tree_influence(&mut self, stats: &mut SimulationStats) {
-        let mut rng = rand::thread_rng();
-        for t in &self.trees {
-            let t_x = t.x as i32;
-            let t_y = t.y as i32;
-            for dx in -5..=5 {
-                for dy in -5..=5 {
-                    let nx = t_x + dx;
-                    let ny = t_y + dy;
-                    if nx < 0 || ny < 0 || nx >= self.width as i32 || ny >= self.height as i32 {
-                        continue;
-                    }
+            scavenger.energy -= Self::scaled_loss(scavenger.genome.energy_loss(self.config.scavenger_energy_loss), self.config.time_scale, &mut scavenger);
+            self.drink(&mut scavenger);
+            if self.is_lethal_cell(scavenger.x, scavenger.y) {
+                scavenger.energy = 0;
+                Self::record_death(&mut scavenger, "Overridden by Water/Tree", self.iteration_count, stats);
+            } else if self.occupancy.has(&AgentType::Corpse, scavenger.x, scavenger.y) {
+                if let Some(index) = self.corpses.iter().position(|c| c.x == scavenger.x && c.y == scavenger.y) {
+                    let corpse = self.corpses.swap_remove(index);
+                    self.occupancy.remove(&corpse);
+                    scavenger.energy += self.config.scavenger_energy_gain;
+                    self.cap_energy(&mut scavenger);
+                    stats.scavenger_consumptions += 1;
+                }
+            }
 
-                    let ux = nx as usize;
-                    let uy = ny as usize;
-                    let before = self.plants.len();
-                    self.plants.retain(|p| !(p.x == ux && p.y == uy && p.agent_type == AgentType::LightPlant));
-                    let after = self.plants.len();
-                    let removed = before - after;
-                    stats.light_plant_deaths += removed;
-                    if rng.gen::<f32>() < 0.5 {
-                        let no_plant = !self.plants.iter().any(|p| p.x == ux && p.y == uy);
-                        let no_water = !self.waters.iter().any(|w| w.x == ux && w.y == uy);
-                        let no_tree = !self.trees.iter().any(|tt| tt.x == ux && tt.y == uy);
-                        if no_plant && no_water && no_tree {
-                            let dplant = Agent::new(self.next_agent_id, AgentType::DarkPlant, ux, uy, 0);
-                            self.next_agent_id += 1;
-                            self.plants.push(dplant);
-                            stats.dark_plant_births += 1;
-                        }
-                    }
+            let offspring_energy = Self::offspring_energy(scavenger.energy, self.config.scavenger_reproduction_threshold, self.config.scavenger_reproduction_cost, self.config.scavenger_starvation_threshold)
+                .filter(|_| self.rng.gen::<f32>() < self.config.scavenger_reproduction_rate * reproduction_multiplier);
+            if let Some(offspring_energy) = offspring_energy {
+                if let Some((ox, oy)) = self.free_adjacent_cell(scavenger.x, scavenger.y) {
+                    scavenger.energy -= offspring_energy;
+                    let genome = scavenger.genome.mutate(&mut self.rng, self.config.mutation_std);
+                    let offspring = Agent::new_offspring(self.next_agent_id, &scavenger, ox, oy, offspring_energy, self.iteration_count, genome);
+                    self.occupancy.add(&offspring);
+                    new_scavengers.push(offspring);
+                    self.next_agent_id += 1;
+                    stats.scavenger_births += 1;
                 }
             }
+
+            if scavenger.energy <= self.config.scavenger_starvation_threshold && !scavenger.pending_death {
+                Self::record_death(&mut scavenger, "Lack of Energy", self.iteration_count, stats);
+            }
+            if scavenger.pending_death {
+                self.occupancy.remove(&scavenger);
+                self.leave_corpse(scavenger.x, scavenger.y);
+            } else {
+                updated_scavengers.push(scavenger);
+            }
+        }
+        updated_scavengers.extend(new_scavengers);
+        self.scavengers = updated_scavengers;
+    }
+
+    // Cells holding an infected live animal, row by row.
+    pub fn infected_cells(&self) -> Vec<bool> {
+        let mut cells = vec![false; self.width * self.height];
+        for agent in self.herbivores.iter().chain(&self.carnivores).chain(&self.omnivores).chain(&self.scavengers) {
+            if agent.infected && !agent.pending_death {
+                cells[agent.y * self.width + agent.x] = true;
+            }
         }
+        cells
     }
 
     pub fn step(&mut self, stats: &mut SimulationStats) {
+        self.step_delta(Some(stats));
+    }
+
+    /// Advances one iteration and returns the stats of that iteration alone,
+    /// also adding them to `total` when one is given. The returned
+    /// longest-lived record carries on from `total`'s.
+    pub fn step_delta(&mut self, total: Option<&mut SimulationStats>) -> SimulationStats {
+        let mut delta = SimulationStats { longest_lived: total.as_ref().and_then(|t| t.longest_lived.clone()), ..Default::default() };
+        self.step_into(&mut delta);
+        if let Some(total) = total {
+            total.accumulate(&delta);
+        }
+        delta
+    }
+
+    fn step_into(&mut self, stats: &mut SimulationStats) {
         self.iteration_count += 1;
-        self.maybe_spawn_water(stats);
-        self.evaporate_water(stats);
-        self.handle_water_influence(stats);
-        self.maybe_spawn_tree(stats);
-        self.evaporate_trees(stats);
-        self.handle_tree_influence(stats);
-
-        let mut rng = rand::thread_rng();
-        let plants_snapshot = self.plants.clone();
+        self.update_weather(stats);
+        // With nothing to spawn or age, the terrain phases are skipped
+        // outright so they don't draw from the RNG.
+        if self.config.water_spawn_chance > 0.0 || !self.waters.is_empty() {
+            self.maybe_spawn_water(stats);
+            self.evaporate_water(stats);
+            self.handle_water_influence(stats);
+        }
+        if self.config.tree_spawn_chance > 0.0 || !self.trees.is_empty() {
+            self.maybe_spawn_tree(stats);
+            self.evaporate_trees(stats);
+            self.handle_tree_influence(stats);
+        }
+        self.decay_corpses(stats);
+
+        self.plant_index.rebuild(self.width, self.height, &self.plants);
         let mut new_plants = Vec::new();
 
-        for _ in &plants_snapshot {
-            if rng.gen::<f32>() < self.config.plant_growth_rate {
-                let nx = rng.gen_range(0..self.width);
-                let ny = rng.gen_range(0..self.height);
-                if self.waters.iter().any(|w| w.x == nx && w.y == ny) || self.trees.iter().any(|t| t.x == nx && t.y == ny) {
-                    continue;
-                }
-                if let Some(existing_index) = self.plants.iter().position(|x| x.x == nx && x.y == ny) {
-                    let new_type = match self.plants[existing_index].agent_type {
-                        AgentType::LightPlant => AgentType::DarkPlant,
-                        AgentType::DarkPlant => AgentType::LightPlant,
-                        _ => continue,
-                    };
-                    let old_id = self.plants[existing_index].id;
-                    self.plants[existing_index] = Agent::new(old_id, new_type, nx, ny, 0);
-                } else {
-                    if rng.gen::<f32>() < 0.5 {
-                        new_plants.push(Agent::new(self.next_agent_id, AgentType::LightPlant, nx, ny, 0));
-                        self.next_agent_id += 1;
-                        stats.light_plant_births += 1;
-                    } else {
-                        new_plants.push(Agent::new(self.next_agent_id, AgentType::DarkPlant, nx, ny, 0));
-                        self.next_agent_id += 1;
-                        stats.dark_plant_births += 1;
+        if self.config.plant_pollination {
+            new_plants = self.pollinate_plants(stats);
+        } else if self.config.plant_spread_local {
+            new_plants = self.spread_plants_locally(stats);
+        } else {
+            // Each plant may seed a random free cell with its own species,
+            // so species grow independently at their own rates.
+            let growth_rate = self.plant_growth_rate();
+            for index in 0..self.plants.len() {
+                let species = self.plants[index].species;
+                if self.rng.gen::<f32>() < growth_rate * self.config.plant_species[species].growth_mult {
+                    let nx = self.rng.gen_range(0..self.width);
+                    let ny = self.rng.gen_range(0..self.height);
+                    if self.occupancy.has_terrain(nx, ny) || self.occupancy.has_plant(nx, ny) || !self.elevation_allows_growth(nx, ny) {
+                        continue;
+                    }
+                    if new_plants.iter().any(|p| p.x == nx && p.y == ny) {
+                        continue;
                     }
+                    new_plants.push(Agent::new_plant(self.next_agent_id, species, nx, ny));
+                    self.next_agent_id += 1;
+                    stats.plant_born(species);
                 }
             }
         }
+        for plant in &new_plants {
+            self.occupancy.add(plant);
+        }
+        let first_new_plant = self.plants.len();
         self.plants.extend(new_plants);
+        for index in first_new_plant..self.plants.len() {
+            self.plant_index.pushed(&self.plants, index);
+        }
 
-        let current_herbivores = std::mem::take(&mut self.herbivores);
+        let reproduction_multiplier = self.current_season().reproduction_multiplier();
+        let mut current_herbivores = std::mem::take(&mut self.herbivores);
+        if self.config.stable_processing {
+            current_herbivores.sort_by_key(|a| a.id);
+        }
+        let herbivore_room = Self::birth_room(&current_herbivores, self.config.herbivore_max_population);
         let mut updated_herbivores = Vec::new();
         let mut new_herbivores = Vec::new();
 
         for mut herbivore in current_herbivores {
-            if rng.gen::<f32>() < 0.8 {
-                let (nx, ny) = Self::random_adjacent_aux(&mut rng, herbivore.x, herbivore.y, self.width, self.height);
-                herbivore.x = nx;
-                herbivore.y = ny;
+            if Self::reached_max_age(&herbivore, self.iteration_count, self.config.herbivore_max_age) {
+                Self::record_death(&mut herbivore, "Old Age", self.iteration_count, stats);
+                self.occupancy.remove(&herbivore);
+                self.leave_corpse(herbivore.x, herbivore.y);
+                continue;
+            }
+
+            let flee = if self.config.herbivore_flee_chance > 0.0 {
+                self.flee_target(herbivore.x, herbivore.y)
+                    .filter(|_| self.rng.gen::<f32>() < self.config.herbivore_flee_chance)
+            } else {
+                None
+            };
+            let food = if flee.is_none() && self.config.herbivore_vision_radius > 0 {
+                self.nearest_food(herbivore.x, herbivore.y, self.config.herbivore_vision_radius, |cells, x, y| cells.has_plant(x, y))
+            } else {
+                None
+            };
+            let destination = flee.or_else(|| food.map(|(fx, fy)| self.step_toward(herbivore.x, herbivore.y, fx, fy)));
+            if let Some((nx, ny)) = destination {
+                self.try_move(&mut herbivore, nx, ny);
+            } else if self.rng.gen::<f32>() < herbivore.genome.move_chance {
+                let (nx, ny) = Self::random_adjacent_aux(&mut self.rng, herbivore.x, herbivore.y, self.width, self.height, self.config.wrap_edges, &self.config.movement_neighborhood);
+                self.try_move(&mut herbivore, nx, ny);
             }
 
-            herbivore.energy -= self.config.herbivore_energy_loss;
-            if self.waters.iter().any(|w| w.x == herbivore.x && w.y == herbivore.y) || self.trees.iter().any(|t| t.x == herbivore.x && t.y == herbivore.y) {
+            herbivore.energy -= Self::scaled_loss(herbivore.genome.energy_loss(self.config.herbivore_energy_loss), self.config.time_scale, &mut herbivore);
+            self.drink(&mut herbivore);
+            if self.is_lethal_cell(herbivore.x, herbivore.y) {
                 herbivore.energy = 0;
-                herbivore.pending_death = true;
-                herbivore.death_cause = Some("Overridden by Water/Tree".to_string());
-            } else if let Some(index) = self.plants.iter().position(|p| p.x == herbivore.x && p.y == herbivore.y) {
-                let eaten_plant_type = self.plants[index].agent_type.clone();
-                self.plants.swap_remove(index);
-                if eaten_plant_type == AgentType::LightPlant {
-                    stats.light_plant_deaths += 1;
-                } else {
-                    stats.dark_plant_deaths += 1;
-                }
-                herbivore.energy += self.config.herbivore_energy_gain;
+                Self::record_death(&mut herbivore, "Overridden by Water/Tree", self.iteration_count, stats);
+            } else if let Some(index) = Self::find_at(&self.plants, &self.occupancy, &mut self.plant_index, &[AgentType::Plant], herbivore.x, herbivore.y) {
+                let eaten_plant = self.plants.swap_remove(index);
+                self.plant_index.swap_removed(&self.plants, &eaten_plant, index);
+                self.occupancy.remove(&eaten_plant);
+                stats.plants_died(eaten_plant.species, 1);
+                herbivore.energy += self.config.plant_species[eaten_plant.species].herbivore_energy_gain.unwrap_or(self.config.herbivore_energy_gain);
+                self.cap_energy(&mut herbivore);
                 stats.herbivore_consumptions += 1;
             }
 
-            if herbivore.energy >= self.config.herbivore_reproduction_threshold {
-                let (ox, oy) = Self::random_adjacent_aux(&mut rng, herbivore.x, herbivore.y, self.width, self.height);
-                let offspring_energy = herbivore.energy / 2;
-                herbivore.energy -= offspring_energy;
-                new_herbivores.push(Agent::new(self.next_agent_id, AgentType::Herbivore, ox, oy, offspring_energy));
-                self.next_agent_id += 1;
-                stats.herbivore_births += 1;
+            let offspring_energy = Self::offspring_energy(herbivore.energy, self.config.herbivore_reproduction_threshold, self.config.herbivore_reproduction_cost, self.config.herbivore_starvation_threshold)
+                .filter(|_| self.rng.gen::<f32>() < self.config.herbivore_reproduction_rate * reproduction_multiplier && new_herbivores.len() < herbivore_room);
+            if let Some(offspring_energy) = offspring_energy {
+                if let Some((ox, oy)) = self.free_adjacent_cell(herbivore.x, herbivore.y) {
+                    herbivore.energy -= offspring_energy;
+                    let genome = herbivore.genome.mutate(&mut self.rng, self.config.mutation_std);
+                    let offspring = Agent::new_offspring(self.next_agent_id, &herbivore, ox, oy, offspring_energy, self.iteration_count, genome);
+                    self.occupancy.add(&offspring);
+                    new_herbivores.push(offspring);
+                    self.next_agent_id += 1;
+                    stats.herbivore_births += 1;
+                }
             }
 
-            if herbivore.energy <= 0 {
-                if !herbivore.pending_death {
-                    herbivore.pending_death = true;
-                    herbivore.death_cause = Some("Lack of Energy".to_string());
-                    stats.herbivore_deaths += 1;
-                }
+            if herbivore.energy <= self.config.herbivore_starvation_threshold && !herbivore.pending_death {
+                Self::record_death(&mut herbivore, "Lack of Energy", self.iteration_count, stats);
+            }
+            if herbivore.pending_death {
+                self.occupancy.remove(&herbivore);
+                self.leave_corpse(herbivore.x, herbivore.y);
                 continue;
             } else {
-                herbivore.pending_death = false;
-                herbivore.death_cause = None;
                 updated_herbivores.push(herbivore);
             }
         }
         updated_herbivores.extend(new_herbivores);
         self.herbivores = updated_herbivores;
+        self.herbivore_index.rebuild(self.width, self.height, &self.herbivores);
 
-        let current_carnivores = std::mem::take(&mut self.carnivores);
+        self.omnivore_index.rebuild(self.width, self.height, &self.omnivores);
+        let mut current_carnivores = std::mem::take(&mut self.carnivores);
+        if self.config.stable_processing {
+            current_carnivores.sort_by_key(|a| a.id);
+        }
+        let carnivore_move_factor = if self.is_night() { self.config.night_carnivore_move_factor } else { 1.0 };
+        let carnivore_room = Self::birth_room(&current_carnivores, self.config.carnivore_max_population);
         let mut updated_carnivores = Vec::new();
         let mut new_carnivores = Vec::new();
 
         for mut carnivore in current_carnivores {
-            if rng.gen::<f32>() < 0.8 {
-                let (nx, ny) = Self::random_adjacent_aux(&mut rng, carnivore.x, carnivore.y, self.width, self.height);
-                carnivore.x = nx;
-                carnivore.y = ny;
+            if Self::reached_max_age(&carnivore, self.iteration_count, self.config.carnivore_max_age) {
+                Self::record_death(&mut carnivore, "Old Age", self.iteration_count, stats);
+                self.occupancy.remove(&carnivore);
+                self.leave_corpse(carnivore.x, carnivore.y);
+                continue;
+            }
+
+            let food = if self.config.carnivore_vision_radius > 0 {
+                self.nearest_food(carnivore.x, carnivore.y, self.config.carnivore_vision_radius, |cells, x, y| cells.has(&AgentType::Herbivore, x, y))
+            } else {
+                None
+            };
+            if let Some((fx, fy)) = food {
+                let (nx, ny) = self.step_toward(carnivore.x, carnivore.y, fx, fy);
+                self.try_move(&mut carnivore, nx, ny);
+            } else if self.rng.gen::<f32>() < carnivore_move_factor * carnivore.genome.move_chance {
+                let (nx, ny) = Self::random_adjacent_aux(&mut self.rng, carnivore.x, carnivore.y, self.width, self.height, self.config.wrap_edges, &self.config.movement_neighborhood);
+                self.try_move(&mut carnivore, nx, ny);
             }
 
-            carnivore.energy -= self.config.carnivore_energy_loss;
-            if self.waters.iter().any(|w| w.x == carnivore.x && w.y == carnivore.y) || self.trees.iter().any(|t| t.x == carnivore.x && t.y == carnivore.y) {
+            carnivore.energy -= Self::scaled_loss(carnivore.genome.energy_loss(self.config.carnivore_energy_loss), self.config.time_scale, &mut carnivore);
+            carnivore.energy -= Self::scaled_loss(Self::overfed_decay(carnivore.energy, self.config.carnivore_overfed_threshold, self.config.overfed_decay_rate) as f32, self.config.time_scale, &mut carnivore);
+            self.drink(&mut carnivore);
+            if self.is_lethal_cell(carnivore.x, carnivore.y) {
                 carnivore.energy = 0;
-                carnivore.pending_death = true;
-                carnivore.death_cause = Some("Overridden by Water/Tree".to_string());
-            } else if let Some(index) = self.herbivores.iter().position(|h| h.x == carnivore.x && h.y == carnivore.y) {
+                Self::record_death(&mut carnivore, "Overridden by Water/Tree", self.iteration_count, stats);
+            } else if let Some(index) = Self::find_at(&self.herbivores, &self.occupancy, &mut self.herbivore_index, &[AgentType::Herbivore], carnivore.x, carnivore.y) {
                 let mut prey = self.herbivores.swap_remove(index);
+                self.herbivore_index.swap_removed(&self.herbivores, &prey, index);
                 prey.energy = 0;
-                prey.pending_death = true;
-                prey.death_cause = Some("Eaten by Carnivore".to_string());
+                Self::record_death(&mut prey, "Eaten by Carnivore", self.iteration_count, stats);
                 self.herbivores.push(prey);
+                self.herbivore_index.pushed(&self.herbivores, self.herbivores.len() - 1);
                 carnivore.energy += self.config.carnivore_energy_gain;
+                self.cap_energy(&mut carnivore);
                 stats.carnivore_consumptions += 1;
-                stats.herbivore_deaths += 1;
+            } else if let Some(index) = Self::find_at(&self.omnivores, &self.occupancy, &mut self.omnivore_index, &[AgentType::Omnivore], carnivore.x, carnivore.y) {
+                let mut prey = self.omnivores.swap_remove(index);
+                self.omnivore_index.swap_removed(&self.omnivores, &prey, index);
+                Self::record_death(&mut prey, "Eaten by Carnivore", self.iteration_count, stats);
+                self.occupancy.remove(&prey);
+                self.leave_corpse(prey.x, prey.y);
+                carnivore.energy += self.config.carnivore_energy_gain;
+                self.cap_energy(&mut carnivore);
+                stats.carnivore_consumptions_omnivores += 1;
             }
 
-            if carnivore.energy >= self.config.carnivore_reproduction_threshold {
-                let (ox, oy) = Self::random_adjacent_aux(&mut rng, carnivore.x, carnivore.y, self.width, self.height);
-                let offspring_energy = carnivore.energy / 2;
-                carnivore.energy -= offspring_energy;
-                new_carnivores.push(Agent::new(self.next_agent_id, AgentType::Carnivore, ox, oy, offspring_energy));
-                self.next_agent_id += 1;
-                stats.carnivore_births += 1;
+            let offspring_energy = Self::offspring_energy(carnivore.energy, self.config.carnivore_reproduction_threshold, self.config.carnivore_reproduction_cost, self.config.carnivore_starvation_threshold)
+                .filter(|_| self.rng.gen::<f32>() < self.config.carnivore_reproduction_rate * reproduction_multiplier && new_carnivores.len() < carnivore_room);
+            if let Some(offspring_energy) = offspring_energy {
+                if let Some((ox, oy)) = self.free_adjacent_cell(carnivore.x, carnivore.y) {
+                    carnivore.energy -= offspring_energy;
+                    let genome = carnivore.genome.mutate(&mut self.rng, self.config.mutation_std);
+                    let offspring = Agent::new_offspring(self.next_agent_id, &carnivore, ox, oy, offspring_energy, self.iteration_count, genome);
+                    self.occupancy.add(&offspring);
+                    new_carnivores.push(offspring);
+                    self.next_agent_id += 1;
+                    stats.carnivore_births += 1;
+                }
             }
 
-            if carnivore.energy <= 0 {
-                if !carnivore.pending_death {
-                    carnivore.pending_death = true;
-                    carnivore.death_cause = Some("Lack of Energy".to_string());
-                    stats.carnivore_deaths += 1;
-                }
+            if carnivore.energy <= self.config.carnivore_starvation_threshold && !carnivore.pending_death {
+                Self::record_death(&mut carnivore, "Lack of Energy", self.iteration_count, stats);
+            }
+            if carnivore.pending_death {
+                self.occupancy.remove(&carnivore);
+                self.leave_corpse(carnivore.x, carnivore.y);
                 continue;
             } else {
-                carnivore.pending_death = false;
-                carnivore.death_cause = None;
                 updated_carnivores.push(carnivore);
             }
         }
         updated_carnivores.extend(new_carnivores);
         self.carnivores = updated_carnivores;
 
-        let current_omnivores = std::mem::take(&mut self.omnivores);
+        let mut current_omnivores = std::mem::take(&mut self.omnivores);
+        if self.config.stable_processing {
+            current_omnivores.sort_by_key(|a| a.id);
+        }
+        let omnivore_room = Self::birth_room(&current_omnivores, self.config.omnivore_max_population);
         let mut updated_omnivores = Vec::new();
         let mut new_omnivores = Vec::new();
 
         for mut omnivore in current_omnivores {
-            if rng.gen::<f32>() < 0.8 {
-                let (nx, ny) = Self::random_adjacent_aux(&mut rng, omnivore.x, omnivore.y, self.width, self.height);
-                omnivore.x = nx;
-                omnivore.y = ny;
+            if Self::reached_max_age(&omnivore, self.iteration_count, self.config.omnivore_max_age) {
+                Self::record_death(&mut omnivore, "Old Age", self.iteration_count, stats);
+                self.occupancy.remove(&omnivore);
+                self.leave_corpse(omnivore.x, omnivore.y);
+                continue;
+            }
+
+            let from = (omnivore.x, omnivore.y);
+            let food = if self.config.omnivore_vision_radius > 0 {
+                self.nearest_food(omnivore.x, omnivore.y, self.config.omnivore_vision_radius, |cells, x, y| cells.has(&AgentType::Herbivore, x, y) || cells.has_plant(x, y))
+            } else {
+                None
+            };
+            if let Some((fx, fy)) = food {
+                let (nx, ny) = self.step_toward(omnivore.x, omnivore.y, fx, fy);
+                self.try_move(&mut omnivore, nx, ny);
+            } else if self.rng.gen::<f32>() < omnivore.genome.move_chance {
+                let (nx, ny) = Self::random_adjacent_aux(&mut self.rng, omnivore.x, omnivore.y, self.width, self.height, self.config.wrap_edges, &self.config.movement_neighborhood);
+                self.try_move(&mut omnivore, nx, ny);
             }
 
-            omnivore.energy -= self.config.omnivore_energy_loss;
-            if self.waters.iter().any(|w| w.x == omnivore.x && w.y == omnivore.y) || self.trees.iter().any(|t| t.x == omnivore.x && t.y == omnivore.y) {
+            omnivore.energy -= Self::scaled_loss(omnivore.genome.energy_loss(self.config.omnivore_energy_loss), self.config.time_scale, &mut omnivore);
+            omnivore.energy -= Self::scaled_loss(Self::overfed_decay(omnivore.energy, self.config.omnivore_overfed_threshold, self.config.overfed_decay_rate) as f32, self.config.time_scale, &mut omnivore);
+            self.drink(&mut omnivore);
+            if self.is_lethal_cell(omnivore.x, omnivore.y) {
                 omnivore.energy = 0;
-                omnivore.pending_death = true;
-                omnivore.death_cause = Some("Overridden by Water/Tree".to_string());
+                Self::record_death(&mut omnivore, "Overridden by Water/Tree", self.iteration_count, stats);
             } else {
-                if let Some(index) = self.herbivores.iter().position(|h| h.x == omnivore.x && h.y == omnivore.y) {
+                let prey = Self::find_at(&self.herbivores, &self.occupancy, &mut self.herbivore_index, &[AgentType::Herbivore], omnivore.x, omnivore.y);
+                let plant = Self::find_at(&self.plants, &self.occupancy, &mut self.plant_index, &[AgentType::Plant], omnivore.x, omnivore.y);
+                let preference = self.config.omnivore_meat_preference;
+                let eats_meat = prey.is_some() && (plant.is_none() || preference >= 1.0 || self.rng.gen::<f32>() < preference);
+                if let Some(index) = prey.filter(|_| eats_meat) {
                     let mut prey = self.herbivores.swap_remove(index);
+                    self.herbivore_index.swap_removed(&self.herbivores, &prey, index);
                     prey.energy = 0;
-                    prey.pending_death = true;
-                    prey.death_cause = Some("Eaten by Omnivore".to_string());
+                    Self::record_death(&mut prey, "Eaten by Omnivore", self.iteration_count, stats);
                     self.herbivores.push(prey);
+                    self.herbivore_index.pushed(&self.herbivores, self.herbivores.len() - 1);
                     omnivore.energy += self.config.omnivore_energy_gain_herbivores;
+                    self.cap_energy(&mut omnivore);
                     stats.omnivore_consumptions_herbivores += 1;
-                    stats.herbivore_deaths += 1;
-                } else if let Some(index) = self.plants.iter().position(|p| p.x == omnivore.x && p.y == omnivore.y) {
-                    let eaten_plant_type = self.plants[index].agent_type.clone();
-                    self.plants.swap_remove(index);
-                    if eaten_plant_type == AgentType::LightPlant {
-                        stats.light_plant_deaths += 1;
-                    } else {
-                        stats.dark_plant_deaths += 1;
-                    }
-                    omnivore.energy += self.config.omnivore_energy_gain_plants;
+                } else if let Some(index) = plant {
+                    let eaten_plant = self.plants.swap_remove(index);
+                    self.plant_index.swap_removed(&self.plants, &eaten_plant, index);
+                    self.occupancy.remove(&eaten_plant);
+                    stats.plants_died(eaten_plant.species, 1);
+                    omnivore.energy += self.config.plant_species[eaten_plant.species].omnivore_energy_gain.unwrap_or(self.config.omnivore_energy_gain_plants);
+                    self.cap_energy(&mut omnivore);
                     stats.omnivore_consumptions_plants += 1;
+                    // It stepped onto the herbivore but chose the plant, so
+                    // it goes back to the cell it came from.
+                    if prey.is_some() {
+                        self.occupancy.relocate(&omnivore, from.0, from.1);
+                        (omnivore.x, omnivore.y) = from;
+                    }
                 }
             }
 
-            if omnivore.energy >= self.config.omnivore_reproduction_threshold {
-                let (ox, oy) = Self::random_adjacent_aux(&mut rng, omnivore.x, omnivore.y, self.width, self.height);
-                let offspring_energy = omnivore.energy / 2;
-                omnivore.energy -= offspring_energy;
-                new_omnivores.push(Agent::new(self.next_agent_id, AgentType::Omnivore, ox, oy, offspring_energy));
-                self.next_agent_id += 1;
-                stats.omnivore_births += 1;
+            let offspring_energy = Self::offspring_energy(omnivore.energy, self.config.omnivore_reproduction_threshold, self.config.omnivore_reproduction_cost, self.config.omnivore_starvation_threshold)
+                .filter(|_| self.rng.gen::<f32>() < self.config.omnivore_reproduction_rate * reproduction_multiplier && new_omnivores.len() < omnivore_room);
+            if let Some(offspring_energy) = offspring_energy {
+                if let Some((ox, oy)) = self.free_adjacent_cell(omnivore.x, omnivore.y) {
+                    omnivore.energy -= offspring_energy;
+                    let genome = omnivore.genome.mutate(&mut self.rng, self.config.mutation_std);
+                    let offspring = Agent::new_offspring(self.next_agent_id, &omnivore, ox, oy, offspring_energy, self.iteration_count, genome);
+                    self.occupancy.add(&offspring);
+                    new_omnivores.push(offspring);
+                    self.next_agent_id += 1;
+                    stats.omnivore_births += 1;
+                }
             }
 
-            if omnivore.energy <= 0 {
-                if !omnivore.pending_death {
-                    omnivore.pending_death = true;
-                    omnivore.death_cause = Some("Lack of Energy".to_string());
-                    stats.omnivore_deaths += 1;
-                }
+            if omnivore.energy <= self.config.omnivore_starvation_threshold && !omnivore.pending_death {
+                Self::record_death(&mut omnivore, "Lack of Energy", self.iteration_count, stats);
+            }
+            if omnivore.pending_death {
+                self.occupancy.remove(&omnivore);
+                self.leave_corpse(omnivore.x, omnivore.y);
                 continue;
             } else {
-                omnivore.pending_death = false;
-                omnivore.death_cause = None;
                 updated_omnivores.push(omnivore);
             }
         }
         updated_omnivores.extend(new_omnivores);
         self.omnivores = updated_omnivores;
 
-        let mut trees_died_count = 0;
-        self.trees.retain(|t| {
-            if let Some(birth) = t.birth_iteration {
-                if (self.iteration_count - birth) >= self.config.tree_lifespan {
-                    trees_died_count += 1;
-                    false
-                } else {
-                    true
+        self.step_scavengers(stats, reproduction_multiplier);
+        self.remove_dead();
+
+        self.progress_disease(stats);
+        self.update_longest_lived(stats);
+        #[cfg(debug_assertions)]
+        self.assert_one_animal_per_cell();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An empty grid where nothing grows or breeds unless a test turns it
+    // on, so a scene only changes through the rule under test.
+    fn scene(width: usize, height: usize) -> Ecosystem {
+        let mut ecosystem = Ecosystem::new_empty(width, height);
+        let config = &mut ecosystem.config;
+        config.plant_growth_rate = 0.0;
+        config.herbivore_reproduction_rate = 0.0;
+        config.carnivore_reproduction_rate = 0.0;
+        config.omnivore_reproduction_rate = 0.0;
+        config.scavenger_reproduction_rate = 0.0;
+        ecosystem
+    }
+
+    // Adds an animal born now that never wanders off on its own, and
+    // returns its id.
+    fn place(ecosystem: &mut Ecosystem, agent_type: AgentType, x: usize, y: usize, energy: i32) -> u32 {
+        let id = ecosystem.next_agent_id;
+        let mut animal = Agent::new_animal(id, agent_type, x, y, energy, ecosystem.iteration_count);
+        animal.genome.move_chance = 0.0;
+        ecosystem.add_agent(animal);
+        id
+    }
+
+    fn run(ecosystem: &mut Ecosystem, steps: usize) -> SimulationStats {
+        let mut stats = SimulationStats::default();
+        for _ in 0..steps {
+            ecosystem.step(&mut stats);
+        }
+        stats
+    }
+
+    #[test]
+    fn same_seed_runs_are_identical() {
+        let config = SimulationConfig { seed: 42, grid_width: 30, grid_height: 30, ..SimulationConfig::default() };
+        let mut first = Ecosystem::new_custom(config.clone());
+        let mut second = Ecosystem::new_custom(config);
+        let (mut first_stats, mut second_stats) = (SimulationStats::default(), SimulationStats::default());
+        for _ in 0..100 {
+            first.step(&mut first_stats);
+            second.step(&mut second_stats);
+        }
+        assert_eq!(first.to_json(), second.to_json());
+    }
+
+    #[test]
+    fn occupancy_matches_rebuilding_from_scratch() {
+        let config = SimulationConfig {
+            seed: 5,
+            grid_width: 200,
+            grid_height: 200,
+            plant_species: vec![PlantSpecies::light(4000), PlantSpecies::dark(2000)],
+            initial_herbivores: 3000,
+            initial_carnivores: 600,
+            initial_omnivores: 600,
+            water_spawn_chance: 0.2,
+            tree_spawn_chance: 0.2,
+            ..SimulationConfig::default()
+        };
+        let mut incremental = Ecosystem::new_custom(config);
+        let mut naive = incremental.clone();
+        let (mut incremental_stats, mut naive_stats) = (SimulationStats::default(), SimulationStats::default());
+        for _ in 0..10 {
+            incremental.step(&mut incremental_stats);
+            naive.rebuild_occupancy();
+            naive.step(&mut naive_stats);
+            let mut rebuilt = incremental.clone();
+            rebuilt.rebuild_occupancy();
+            assert!(incremental.occupancy == rebuilt.occupancy, "occupancy drifted at iteration {}", incremental.iteration_count);
+            assert_eq!(incremental.population(), naive.population());
+        }
+        assert_eq!(incremental.to_json(), naive.to_json());
+    }
+
+    #[test]
+    fn herbivores_die_of_old_age() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.herbivore_max_age = 5;
+        place(&mut ecosystem, AgentType::Herbivore, 2, 2, 100);
+        let mut stats = run(&mut ecosystem, 4);
+        assert_eq!(ecosystem.herbivores.len(), 1);
+        ecosystem.step(&mut stats);
+        assert!(ecosystem.herbivores.is_empty());
+        assert_eq!(stats.herbivore_deaths, 1);
+        assert_eq!(stats.herbivore_death_causes.get("Old Age"), Some(&1));
+    }
+
+    #[test]
+    fn hungry_herbivores_head_for_plants() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.herbivore_vision_radius = 3;
+        ecosystem.add_agent(Agent::new_plant(100, 0, 2, 2));
+        place(&mut ecosystem, AgentType::Herbivore, 0, 2, 10);
+        let mut stats = run(&mut ecosystem, 1);
+        assert_eq!((ecosystem.herbivores[0].x, ecosystem.herbivores[0].y), (1, 2));
+        ecosystem.step(&mut stats);
+        assert_eq!((ecosystem.herbivores[0].x, ecosystem.herbivores[0].y), (2, 2));
+        assert_eq!(stats.herbivore_consumptions, 1);
+    }
+
+    #[test]
+    fn herbivores_flee_to_the_free_cell_farthest_from_a_predator() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.herbivore_flee_chance = 1.0;
+        ecosystem.add_agent(Agent::new_water(100, 2, 1, 0));
+        ecosystem.add_agent(Agent::new_tree(101, 2, 3, 0));
+        place(&mut ecosystem, AgentType::Carnivore, 0, 2, 50);
+        place(&mut ecosystem, AgentType::Herbivore, 1, 2, 10);
+        run(&mut ecosystem, 1);
+        assert_eq!((ecosystem.herbivores[0].x, ecosystem.herbivores[0].y), (2, 2));
+    }
+
+    #[test]
+    fn deaths_on_one_cell_share_a_corpse() {
+        let mut ecosystem = scene(3, 3);
+        place(&mut ecosystem, AgentType::Herbivore, 1, 1, 1);
+        let mut stats = run(&mut ecosystem, 1);
+        assert_eq!(ecosystem.corpses.len(), 1);
+        place(&mut ecosystem, AgentType::Herbivore, 1, 1, 1);
+        ecosystem.step(&mut stats);
+        assert_eq!(stats.herbivore_deaths, 2);
+        assert_eq!(ecosystem.corpses.len(), 1);
+        assert_eq!(ecosystem.corpses[0].birth_iteration, Some(2));
+        assert_eq!(ecosystem.occupancy.count(&AgentType::Corpse, 1, 1), 1);
+    }
+
+    #[test]
+    fn animals_drink_from_water_unless_it_is_lethal() {
+        let mut ecosystem = scene(3, 3);
+        ecosystem.add_agent(Agent::new_water(100, 1, 1, 0));
+        place(&mut ecosystem, AgentType::Herbivore, 1, 1, 5);
+        run(&mut ecosystem, 1);
+        let gain = ecosystem.config.water_drink_gain - ecosystem.config.herbivore_energy_loss;
+        assert_eq!(ecosystem.herbivores[0].energy, 5 + gain);
+
+        let mut ecosystem = scene(3, 3);
+        ecosystem.config.water_is_lethal = true;
+        ecosystem.add_agent(Agent::new_water(100, 1, 1, 0));
+        place(&mut ecosystem, AgentType::Herbivore, 1, 1, 5);
+        let stats = run(&mut ecosystem, 1);
+        assert!(ecosystem.herbivores.is_empty());
+        assert_eq!(stats.herbivore_death_causes.get("Overridden by Water/Tree"), Some(&1));
+    }
+
+    #[test]
+    fn wrapped_edges_join_opposite_sides() {
+        let mut ecosystem = scene(5, 5);
+        assert_eq!(ecosystem.offset_cell(0, 2, -1, 0), None);
+        ecosystem.config.wrap_edges = true;
+        assert_eq!(ecosystem.offset_cell(0, 2, -1, 0), Some((4, 2)));
+
+        // The plant is one step away across the edge, not four.
+        ecosystem.config.herbivore_vision_radius = 1;
+        ecosystem.add_agent(Agent::new_plant(100, 0, 4, 2));
+        place(&mut ecosystem, AgentType::Herbivore, 0, 2, 10);
+        run(&mut ecosystem, 1);
+        assert_eq!((ecosystem.herbivores[0].x, ecosystem.herbivores[0].y), (4, 2));
+    }
+
+    #[test]
+    fn local_spread_grows_a_contiguous_patch() {
+        let mut ecosystem = scene(9, 9);
+        ecosystem.config.plant_spread_local = true;
+        ecosystem.config.plant_growth_rate = 1.0;
+        ecosystem.add_agent(Agent::new_plant(0, 0, 4, 4));
+        run(&mut ecosystem, 3);
+        assert!(ecosystem.plants.len() > 1);
+
+        let mut patch: Vec<(usize, usize)> = vec![(4, 4)];
+        let mut frontier = patch.clone();
+        while let Some((x, y)) = frontier.pop() {
+            for plant in &ecosystem.plants {
+                if x.abs_diff(plant.x) + y.abs_diff(plant.y) == 1 && !patch.contains(&(plant.x, plant.y)) {
+                    patch.push((plant.x, plant.y));
+                    frontier.push((plant.x, plant.y));
                 }
+            }
+        }
+        assert_eq!(patch.len(), ecosystem.plants.len());
+    }
+
+    #[test]
+    fn fewer_plants_grow_at_night() {
+        let mut ecosystem = scene(100, 100);
+        ecosystem.config.plant_growth_rate = 0.2;
+        ecosystem.config.day_length = 10;
+        ecosystem.config.night_growth_factor = 0.25;
+        for i in 0..20 {
+            ecosystem.add_agent(Agent::new_plant(i, 0, i as usize * 5, 50));
+        }
+        let (mut day_births, mut night_births) = (0, 0);
+        for _ in 0..40 {
+            let births = ecosystem.step_delta(None).plant_births_of(0);
+            if ecosystem.is_night() {
+                night_births += births;
             } else {
-                true
+                day_births += births;
             }
-        });
-        stats.tree_deaths += trees_died_count;
+        }
+        assert!(night_births < day_births, "{} births at night, {} by day", night_births, day_births);
+    }
+
+    #[test]
+    fn fewer_plants_grow_in_winter_than_in_summer() {
+        let mut summer = scene(100, 100);
+        summer.config.plant_growth_rate = 0.2;
+        summer.config.season_length = 1000;
+        for i in 0..100 {
+            summer.add_agent(Agent::new_plant(i, 0, i as usize, 50));
+        }
+        let mut winter = summer.clone();
+        summer.iteration_count = 1000;
+        winter.iteration_count = 3000;
+        let summer_births = run(&mut summer, 20).plant_births_of(0);
+        let winter_births = run(&mut winter, 20).plant_births_of(0);
+        assert_eq!((summer.current_season(), winter.current_season()), (Season::Summer, Season::Winter));
+        assert!(winter_births < summer_births, "{} births in winter, {} in summer", winter_births, summer_births);
+    }
+
+    #[test]
+    fn carnivores_eat_omnivores() {
+        let mut ecosystem = scene(3, 3);
+        place(&mut ecosystem, AgentType::Omnivore, 1, 1, 50);
+        place(&mut ecosystem, AgentType::Carnivore, 1, 1, 50);
+        let stats = run(&mut ecosystem, 1);
+        assert!(ecosystem.omnivores.is_empty());
+        assert_eq!(stats.carnivore_consumptions_omnivores, 1);
+        assert_eq!(stats.omnivore_deaths, 1);
+        let config = &ecosystem.config;
+        assert_eq!(ecosystem.carnivores[0].energy, 50 - config.carnivore_energy_loss + config.carnivore_energy_gain);
+    }
+
+    #[test]
+    fn herbivores_starve_at_their_threshold() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.herbivore_starvation_threshold = 5;
+        let starving = place(&mut ecosystem, AgentType::Herbivore, 0, 0, 5);
+        let fed = place(&mut ecosystem, AgentType::Herbivore, 4, 4, 7);
+        let stats = run(&mut ecosystem, 1);
+        assert!(ecosystem.herbivores.iter().all(|h| h.id != starving));
+        assert!(ecosystem.herbivores.iter().any(|h| h.id == fed && h.energy == 6));
+        assert_eq!(stats.herbivore_death_causes.get("Lack of Energy"), Some(&1));
+    }
+
+    #[test]
+    fn stable_processing_ignores_vector_order() {
+        let config = SimulationConfig { seed: 11, grid_width: 30, grid_height: 30, stable_processing: true, ..SimulationConfig::default() };
+        let mut ecosystem = Ecosystem::new_custom(config);
+        run(&mut ecosystem, 5);
+        let snapshot = ecosystem.to_json();
+        let mut first = Ecosystem::from_json(&snapshot).unwrap();
+        let mut second = Ecosystem::from_json(&snapshot).unwrap();
+        second.herbivores.reverse();
+        second.carnivores.reverse();
+        second.omnivores.reverse();
+        let first_stats = run(&mut first, 50);
+        let second_stats = run(&mut second, 50);
+        assert_eq!(first.to_json(), second.to_json());
+        assert_eq!(first_stats.births(2), second_stats.births(2));
+        assert_eq!(first_stats.deaths(2), second_stats.deaths(2));
+    }
+
+    #[test]
+    fn herbivores_heading_for_one_cell_do_not_share_it() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.herbivore_vision_radius = 3;
+        ecosystem.add_agent(Agent::new_plant(100, 0, 2, 2));
+        place(&mut ecosystem, AgentType::Herbivore, 0, 2, 10);
+        place(&mut ecosystem, AgentType::Herbivore, 4, 2, 10);
+        let stats = run(&mut ecosystem, 2);
+        let cells: Vec<(usize, usize)> = ecosystem.herbivores.iter().map(|herbivore| (herbivore.x, herbivore.y)).collect();
+        assert_eq!(cells, vec![(2, 2), (3, 2)]);
+        assert_eq!(stats.herbivore_consumptions, 1);
+    }
+
+    #[test]
+    fn expired_trees_die_once() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.tree_lifespan = 3;
+        ecosystem.add_agent(Agent::new_tree(100, 1, 1, 0));
+        ecosystem.add_agent(Agent::new_tree(101, 2, 1, 0));
+        let stats = run(&mut ecosystem, 10);
+        assert!(ecosystem.trees.is_empty());
+        assert_eq!(stats.tree_deaths, 2);
+    }
+
+    #[test]
+    fn shannon_diversity_spans_one_species_to_an_even_split() {
+        let mut ecosystem = scene(10, 10);
+        assert_eq!(ecosystem.shannon_diversity(), 0.0);
+        for x in 0..3 {
+            place(&mut ecosystem, AgentType::Herbivore, x, 0, 10);
+        }
+        assert_eq!(ecosystem.shannon_diversity(), 0.0);
+        for x in 0..3 {
+            for species in 0..ecosystem.config.plant_species.len() {
+                ecosystem.add_agent(Agent::new_plant(1000 + (x * 10 + species) as u32, species, x, 2 + species));
+            }
+            for (y, agent_type) in [(6, AgentType::Carnivore), (7, AgentType::Omnivore), (8, AgentType::Scavenger)] {
+                place(&mut ecosystem, agent_type, x, y, 10);
+            }
+        }
+        let species = ecosystem.population().living().len() as f64;
+        assert!((ecosystem.shannon_diversity() - species.ln()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn clustered_starts_are_closer_together_than_uniform_ones() {
+        let average_distance = |initial_distribution| {
+            let config = SimulationConfig { seed: 7, grid_width: 60, grid_height: 60, initial_distribution, ..SimulationConfig::default() };
+            let ecosystem = Ecosystem::new_custom(config);
+            let cells: Vec<(f64, f64)> = ecosystem.herbivores.iter().map(|a| (a.x as f64, a.y as f64)).collect();
+            let mut total = 0.0;
+            let mut pairs = 0;
+            for (i, a) in cells.iter().enumerate() {
+                for b in &cells[i + 1..] {
+                    total += ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+                    pairs += 1;
+                }
+            }
+            total / pairs as f64
+        };
+        let uniform = average_distance(InitialDistribution::Uniform);
+        let clustered = average_distance(InitialDistribution::Clustered { clusters: 2, spread: 3 });
+        assert!(clustered < uniform, "clustered {} vs uniform {}", clustered, uniform);
+    }
+
+    #[test]
+    fn animals_cannot_move_onto_rocks() {
+        let mut ecosystem = scene(3, 3);
+        let ring = [(0, 0), (1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for (id, (x, y)) in (100..).zip(ring) {
+            ecosystem.add_agent(Agent::new_rock(id, x, y));
+        }
+        let herbivore = place(&mut ecosystem, AgentType::Herbivore, 1, 1, 100);
+        ecosystem.herbivores[0].genome.move_chance = 1.0;
+        run(&mut ecosystem, 20);
+        assert_eq!(ecosystem.herbivores[0].id, herbivore);
+        assert_eq!((ecosystem.herbivores[0].x, ecosystem.herbivores[0].y), (1, 1));
+        assert_eq!(ecosystem.rocks.len(), 8);
+    }
+
+    #[test]
+    fn water_prefers_low_ground() {
+        let mut ecosystem = scene(20, 20);
+        ecosystem.config.water_spawn_chance = 1.0;
+        ecosystem.config.water_block_size = 1;
+        ecosystem.config.water_lifespan = 1000;
+        // The left half is a valley, the right half a plateau.
+        ecosystem.elevation = (0..400).map(|i| if i % 20 < 10 { 0 } else { 255 }).collect();
+        run(&mut ecosystem, 200);
+        let low = ecosystem.waters.iter().filter(|water| water.x < 10).count();
+        let high = ecosystem.waters.len() - low;
+        assert!(low > 4 * high, "{} low vs {} high", low, high);
+    }
+
+    #[test]
+    fn tiny_grids_spawn_terrain_without_panicking() {
+        for (grid_width, grid_height) in [(2, 2), (1, 5), (5, 1), (1, 1)] {
+            for wrap_edges in [false, true] {
+                let config = SimulationConfig {
+                    grid_width,
+                    grid_height,
+                    wrap_edges,
+                    water_spawn_chance: 0.5,
+                    tree_spawn_chance: 0.5,
+                    water_lifespan: 3,
+                    tree_lifespan: 3,
+                    ..SimulationConfig::default()
+                };
+                let mut ecosystem = Ecosystem::new_custom(config);
+                run(&mut ecosystem, 200);
+            }
+        }
+    }
+
+    #[test]
+    fn influence_radius_one_reaches_only_adjacent_cells() {
+        let mut ecosystem = scene(9, 9);
+        ecosystem.config.water_influence_radius = 1;
+        ecosystem.config.plant_species[0].water_affinity = -1.0;
+        ecosystem.add_agent(Agent::new_water(1000, 4, 4, 0));
+        for (id, (x, y)) in (0..).zip((0..81).map(|i| (i % 9, i / 9))).filter(|&(_, cell)| cell != (4, 4)) {
+            ecosystem.add_agent(Agent::new_plant(id, 0, x, y));
+        }
+        run(&mut ecosystem, 1);
+        for x in 0..9usize {
+            for y in 0..9usize {
+                let adjacent = x.abs_diff(4) <= 1 && y.abs_diff(4) <= 1;
+                assert_eq!(ecosystem.occupancy.has_plant(x, y), !adjacent, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn herbivores_never_outgrow_their_cap() {
+        let config = SimulationConfig {
+            seed: 3,
+            grid_width: 40,
+            grid_height: 40,
+            initial_herbivores: 20,
+            initial_carnivores: 0,
+            initial_omnivores: 0,
+            herbivore_reproduction_rate: 1.0,
+            herbivore_max_population: Some(50),
+            ..SimulationConfig::default()
+        };
+        let mut ecosystem = Ecosystem::new_custom(config);
+        let mut stats = SimulationStats::default();
+        let mut peak = 0;
+        for _ in 0..300 {
+            ecosystem.step(&mut stats);
+            peak = peak.max(ecosystem.population().herbivores);
+        }
+        assert_eq!(peak, 50);
+    }
+
+    #[test]
+    fn plant_species_give_their_own_energy() {
+        let energy_after_eating = |agent_type: AgentType, species: usize| {
+            let mut ecosystem = scene(5, 5);
+            ecosystem.config.herbivore_vision_radius = 3;
+            ecosystem.config.omnivore_vision_radius = 3;
+            for (plant, gain) in ecosystem.config.plant_species.iter_mut().zip([3, 9]) {
+                plant.herbivore_energy_gain = Some(gain);
+                plant.omnivore_energy_gain = Some(gain + 1);
+            }
+            ecosystem.add_agent(Agent::new_plant(100, species, 1, 2));
+            place(&mut ecosystem, agent_type.clone(), 0, 2, 10);
+            let stats = run(&mut ecosystem, 1);
+            assert_eq!(stats.plant_deaths_of(species), 1);
+            ecosystem.agents_of(&agent_type)[0].energy
+        };
+        assert_eq!(energy_after_eating(AgentType::Herbivore, 1) - energy_after_eating(AgentType::Herbivore, 0), 6);
+        assert_eq!(energy_after_eating(AgentType::Omnivore, 1) - energy_after_eating(AgentType::Omnivore, 0), 6);
+    }
+
+    #[test]
+    fn only_moving_costs_extra_energy() {
+        let after_one_step = |move_chance: f32| {
+            let mut ecosystem = scene(5, 5);
+            ecosystem.config.move_energy_cost = 3;
+            place(&mut ecosystem, AgentType::Herbivore, 2, 2, 50);
+            ecosystem.herbivores[0].genome.move_chance = move_chance;
+            run(&mut ecosystem, 1);
+            let herbivore = &ecosystem.herbivores[0];
+            ((herbivore.x, herbivore.y) != (2, 2), herbivore.energy)
+        };
+        let loss = SimulationConfig::default().herbivore_energy_loss;
+        assert_eq!(after_one_step(0.0), (false, 50 - loss));
+        assert_eq!(after_one_step(1.0), (true, 50 - loss - 3));
+    }
+
+    #[test]
+    fn terrain_free_steps_leave_the_rng_alone() {
+        let mut ecosystem = scene(10, 10);
+        let rng = ecosystem.rng.clone();
+        run(&mut ecosystem, 20);
+        assert_eq!(ecosystem.rng, rng);
+        ecosystem.config.water_spawn_chance = 0.5;
+        run(&mut ecosystem, 1);
+        assert_ne!(ecosystem.rng, rng);
+    }
+
+    #[test]
+    fn rain_brings_more_water_than_clear_weather() {
+        let water_births = |weather: Weather| {
+            let mut ecosystem = scene(20, 20);
+            ecosystem.config.water_spawn_chance = 0.1;
+            ecosystem.config.water_block_size = 1;
+            ecosystem.force_weather(weather, 1000);
+            run(&mut ecosystem, 200).water_births
+        };
+        let (rain, clear) = (water_births(Weather::Rain), water_births(Weather::Clear));
+        assert!(rain > clear, "rain {} vs clear {}", rain, clear);
+    }
+
+    #[test]
+    fn well_fed_carnivores_stay_at_their_energy_cap() {
+        let mut ecosystem = scene(3, 3);
+        ecosystem.config.carnivore_max_energy = 20;
+        place(&mut ecosystem, AgentType::Carnivore, 1, 1, 15);
+        let mut stats = SimulationStats::default();
+        for meals in 1..=10 {
+            place(&mut ecosystem, AgentType::Herbivore, 1, 1, 10);
+            ecosystem.step(&mut stats);
+            assert_eq!(stats.carnivore_consumptions, meals);
+            assert!(ecosystem.carnivores[0].energy <= 20);
+        }
+    }
+
+    #[test]
+    fn animals_hemmed_in_by_water_have_no_offspring() {
+        let births_in = |waters: usize| {
+            let mut ecosystem = scene(3, 3);
+            ecosystem.config.water_is_lethal = false;
+            ecosystem.config.herbivore_reproduction_rate = 1.0;
+            let ring = [(0, 0), (1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2), (2, 2)];
+            for (id, (x, y)) in (100..).zip(ring).take(waters) {
+                ecosystem.add_agent(Agent::new_water(id, x, y, 0));
+            }
+            place(&mut ecosystem, AgentType::Herbivore, 1, 1, 40);
+            let stats = run(&mut ecosystem, 1);
+            assert_eq!(ecosystem.herbivores.len(), 1 + stats.herbivore_births);
+            stats.herbivore_births
+        };
+        assert_eq!(births_in(8), 0);
+        assert_eq!(births_in(7), 1);
+    }
+
+    #[test]
+    fn omnivores_without_a_taste_for_meat_eat_the_plant() {
+        let mut ecosystem = scene(3, 3);
+        ecosystem.config.omnivore_meat_preference = 0.0;
+        ecosystem.config.omnivore_vision_radius = 1;
+        // The herbivore eats one of the plants before the omnivore's turn.
+        ecosystem.add_agent(Agent::new_plant(100, 0, 1, 1));
+        ecosystem.add_agent(Agent::new_plant(101, 1, 1, 1));
+        place(&mut ecosystem, AgentType::Herbivore, 1, 1, 10);
+        place(&mut ecosystem, AgentType::Omnivore, 0, 1, 10);
+        let stats = run(&mut ecosystem, 1);
+        assert_eq!(stats.herbivore_consumptions, 1);
+        assert_eq!(stats.omnivore_consumptions_plants, 1);
+        assert_eq!(stats.omnivore_consumptions_herbivores, 0);
+        assert_eq!(ecosystem.herbivores.len(), 1);
+        assert_eq!((ecosystem.omnivores[0].x, ecosystem.omnivores[0].y), (0, 1));
+    }
+
+    #[test]
+    fn agent_at_follows_the_cell_precedence() {
+        let mut ecosystem = scene(3, 3);
+        assert!(ecosystem.agent_at(0, 0).is_none());
+        assert!(ecosystem.agents_at(0, 0).is_empty());
+        ecosystem.add_agent(Agent::new_plant(100, 1, 2, 2));
+        assert_eq!(ecosystem.agent_at(2, 2).map(|agent| agent.id), Some(100));
+        assert_eq!(ecosystem.agents_at(2, 2).len(), 1);
+        ecosystem.add_agent(Agent::new_plant(101, 0, 1, 1));
+        ecosystem.add_agent(Agent::new_corpse(102, 1, 1, 0));
+        let herbivore = place(&mut ecosystem, AgentType::Herbivore, 1, 1, 10);
+        assert_eq!(ecosystem.agent_at(1, 1).map(|agent| agent.id), Some(herbivore));
+        let types: Vec<AgentType> = ecosystem.agents_at(1, 1).into_iter().map(|agent| agent.agent_type.clone()).collect();
+        assert_eq!(types, vec![AgentType::Herbivore, AgentType::Corpse, AgentType::Plant]);
+    }
+
+    #[test]
+    fn pollinated_plants_need_a_partner_nearby() {
+        let births_from = |cells: &[(usize, usize)]| {
+            let mut ecosystem = scene(9, 9);
+            ecosystem.config.plant_growth_rate = 1.0;
+            ecosystem.config.plant_pollination = true;
+            ecosystem.config.pollination_radius = 2;
+            for (id, &(x, y)) in (100..).zip(cells) {
+                ecosystem.add_agent(Agent::new_plant(id, 0, x, y));
+            }
+            run(&mut ecosystem, 20).plant_births_of(0)
+        };
+        assert_eq!(births_from(&[(4, 4)]), 0);
+        assert!(births_from(&[(4, 4), (6, 4)]) > 0);
+    }
+
+    #[test]
+    fn scavengers_eat_the_corpse_they_stand_on() {
+        let mut ecosystem = scene(3, 3);
+        ecosystem.add_agent(Agent::new_corpse(100, 1, 1, 0));
+        place(&mut ecosystem, AgentType::Scavenger, 1, 1, 10);
+        let stats = run(&mut ecosystem, 1);
+        assert!(ecosystem.corpses.is_empty());
+        assert_eq!(stats.scavenger_consumptions, 1);
+        let config = &ecosystem.config;
+        assert_eq!(ecosystem.scavengers[0].energy, 10 - config.scavenger_energy_loss + config.scavenger_energy_gain);
+    }
+
+    #[test]
+    fn von_neumann_moves_are_never_diagonal() {
+        let mut rng = ChaCha12Rng::seed_from_u64(5);
+        for wrap in [false, true] {
+            for (x, y) in [(0, 0), (2, 2), (4, 4), (0, 4)] {
+                for _ in 0..200 {
+                    let (nx, ny) = Ecosystem::random_adjacent_aux(&mut rng, x, y, 5, 5, wrap, &MovementNeighborhood::VonNeumann);
+                    assert!(nx == x || ny == y, "({}, {}) -> ({}, {})", x, y, nx, ny);
+                }
+            }
+        }
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.movement_neighborhood = MovementNeighborhood::VonNeumann;
+        assert_eq!(ecosystem.step_toward(1, 1, 3, 2), (2, 1));
+    }
+
+    #[test]
+    fn reproduction_needs_the_threshold_plus_the_cost() {
+        assert_eq!(Ecosystem::offspring_energy(19, 15, 5, 0), None);
+        assert_eq!(Ecosystem::offspring_energy(20, 15, 5, 0), Some(5));
+        // Without a cost the energy is halved from the threshold on.
+        assert_eq!(Ecosystem::offspring_energy(14, 15, 0, 0), None);
+        assert_eq!(Ecosystem::offspring_energy(15, 15, 0, 0), Some(7));
+        // Neither parent nor offspring may be left starving.
+        assert_eq!(Ecosystem::offspring_energy(20, 15, 5, 5), None);
+        assert_eq!(Ecosystem::offspring_energy(30, 10, 20, 9), Some(20));
+        assert_eq!(Ecosystem::offspring_energy(30, 10, 20, 10), None);
+    }
+
+    #[test]
+    fn test_worlds_start_as_configured() {
+        let minimal = Ecosystem::new_custom(SimulationConfig::minimal(10, 10));
+        assert_eq!(minimal.population().living(), vec![4, 2, 2, 1, 1, 0]);
+        assert!(minimal.waters.is_empty() && minimal.trees.is_empty());
+        assert_eq!(minimal.to_json(), Ecosystem::new_custom(SimulationConfig::minimal(10, 10)).to_json());
+
+        let mut empty = Ecosystem::new_empty(10, 10);
+        assert!(empty.population().living().iter().all(|&count| count == 0));
+        empty.add_agent(Agent::new_rock(41, 3, 4));
+        assert!(empty.occupancy.has(&AgentType::Rock, 3, 4));
+        assert_eq!(empty.next_agent_id, 42);
+    }
+
+    #[test]
+    fn drowning_and_predation_are_distinct_causes() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.water_is_lethal = true;
+        ecosystem.add_agent(Agent::new_water(100, 0, 0, 0));
+        place(&mut ecosystem, AgentType::Herbivore, 0, 0, 10);
+        place(&mut ecosystem, AgentType::Herbivore, 3, 3, 10);
+        place(&mut ecosystem, AgentType::Carnivore, 3, 3, 50);
+        let stats = run(&mut ecosystem, 1);
+        assert_eq!(stats.herbivore_deaths, 2);
+        assert_eq!(stats.herbivore_death_causes.get("Overridden by Water/Tree"), Some(&1));
+        assert_eq!(stats.herbivore_death_causes.get("Eaten by Carnivore"), Some(&1));
+    }
+
+    #[test]
+    fn death_totals_match_their_causes_and_lifespans() {
+        let config = SimulationConfig {
+            seed: 9,
+            grid_width: 30,
+            grid_height: 30,
+            initial_scavengers: 10,
+            water_spawn_chance: 0.05,
+            tree_spawn_chance: 0.05,
+            disease_spawn_chance: 0.05,
+            ..SimulationConfig::default()
+        };
+        let mut ecosystem = Ecosystem::new_custom(config);
+        let stats = run(&mut ecosystem, 150);
+        let species = [
+            (stats.herbivore_deaths, &stats.herbivore_death_causes, &stats.herbivore_lifespans),
+            (stats.carnivore_deaths, &stats.carnivore_death_causes, &stats.carnivore_lifespans),
+            (stats.omnivore_deaths, &stats.omnivore_death_causes, &stats.omnivore_lifespans),
+            (stats.scavenger_deaths, &stats.scavenger_death_causes, &stats.scavenger_lifespans),
+        ];
+        for (deaths, causes, lifespans) in species {
+            assert_eq!(causes.values().sum::<usize>(), deaths);
+            assert_eq!(lifespans.iter().sum::<usize>(), deaths);
+        }
+        assert!(stats.herbivore_death_causes.contains_key("Overridden by Water/Tree"));
+        assert!(stats.disease_deaths > 0);
+    }
+
+    #[test]
+    fn lifespans_land_in_their_age_bucket() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.herbivore_max_age = 25;
+        ecosystem.config.water_is_lethal = true;
+        place(&mut ecosystem, AgentType::Herbivore, 0, 0, 100);
+        let stats = run(&mut ecosystem, 30);
+        assert_eq!(stats.herbivore_lifespans, vec![0, 0, 1]);
+
+        // A drowned animal is bucketed by age like any other.
+        place(&mut ecosystem, AgentType::Herbivore, 3, 3, 100);
+        run(&mut ecosystem, 12);
+        ecosystem.add_agent(Agent::new_water(100, 3, 3, ecosystem.iteration_count));
+        let stats = run(&mut ecosystem, 1);
+        assert_eq!(stats.herbivore_death_causes.get("Overridden by Water/Tree"), Some(&1));
+        assert_eq!(stats.herbivore_lifespans, vec![0, 1]);
+    }
+
+    #[test]
+    fn unit_time_scale_keeps_whole_energy_losses() {
+        let energy_after = |time_scale: f32, steps: usize| {
+            let mut ecosystem = scene(3, 3);
+            ecosystem.config.time_scale = time_scale;
+            place(&mut ecosystem, AgentType::Herbivore, 1, 1, 20);
+            run(&mut ecosystem, steps);
+            (ecosystem.herbivores[0].energy, ecosystem.herbivores[0].energy_debt)
+        };
+        assert_eq!(energy_after(1.0, 5), (15, 0.0));
+        assert_eq!(energy_after(0.25, 8), (18, 0.0));
+        assert_eq!(energy_after(0.25, 9), (18, 0.25));
+    }
+
+    #[test]
+    fn eaten_herbivores_are_gone_after_the_step() {
+        let mut ecosystem = scene(5, 5);
+        place(&mut ecosystem, AgentType::Herbivore, 2, 2, 10);
+        place(&mut ecosystem, AgentType::Carnivore, 2, 2, 50);
+        let stats = run(&mut ecosystem, 1);
+        assert_eq!(stats.carnivore_consumptions, 1);
+        assert!(ecosystem.herbivores.is_empty());
+        assert_eq!(ecosystem.population().herbivores, 0);
+        assert!(!ecosystem.occupancy.has(&AgentType::Herbivore, 2, 2));
+        assert!(ecosystem.occupancy.has(&AgentType::Carnivore, 2, 2));
+    }
+
+    #[test]
+    fn block_size_one_spawns_a_single_cell() {
+        for wrap_edges in [false, true] {
+            let mut ecosystem = scene(10, 10);
+            ecosystem.config.wrap_edges = wrap_edges;
+            ecosystem.config.water_spawn_chance = 1.0;
+            ecosystem.config.water_block_size = 1;
+            ecosystem.config.tree_spawn_chance = 1.0;
+            ecosystem.config.tree_block_size = 1;
+            let stats = run(&mut ecosystem, 1);
+            assert_eq!((stats.water_births, stats.tree_births), (1, 1));
+        }
+    }
+
+    // Adds every number in `delta` to the one at the same place in `total`,
+    // padding lists and maps as needed.
+    fn add_json(total: &mut serde_json::Value, delta: &serde_json::Value) {
+        use serde_json::Value;
+        match (total, delta) {
+            (Value::Number(sum), Value::Number(value)) => *sum = (sum.as_u64().unwrap() + value.as_u64().unwrap()).into(),
+            (Value::Array(sums), Value::Array(values)) => {
+                if sums.len() < values.len() {
+                    sums.resize(values.len(), 0.into());
+                }
+                for (sum, value) in sums.iter_mut().zip(values) {
+                    add_json(sum, value);
+                }
+            },
+            (Value::Object(sums), Value::Object(values)) => {
+                for (key, value) in values {
+                    add_json(sums.entry(key.clone()).or_insert(0.into()), value);
+                }
+            },
+            (total, delta) => *total = delta.clone(),
+        }
+    }
+
+    #[test]
+    fn step_deltas_add_up_to_the_running_total() {
+        let config = SimulationConfig { seed: 11, grid_width: 30, grid_height: 30, water_spawn_chance: 0.05, tree_spawn_chance: 0.05, ..SimulationConfig::default() };
+        let mut totalled = Ecosystem::new_custom(config.clone());
+        let mut stepped = Ecosystem::new_custom(config);
+        let mut total = SimulationStats::default();
+        let mut sum = serde_json::Value::Object(Default::default());
+        for _ in 0..60 {
+            totalled.step(&mut total);
+            add_json(&mut sum, &serde_json::to_value(stepped.step_delta(None)).unwrap());
+        }
+        // The longest-lived record isn't a count; it carries over instead.
+        let mut total = serde_json::to_value(&total).unwrap();
+        total.as_object_mut().unwrap().remove("longest_lived");
+        sum.as_object_mut().unwrap().remove("longest_lived");
+        assert_eq!(sum, total);
+    }
+
+    #[test]
+    fn plant_species_grow_at_their_own_rates() {
+        let species = |name: &str, growth_mult| PlantSpecies { name: name.to_string(), initial_count: 10, growth_mult, ..PlantSpecies::default() };
+        let config = SimulationConfig {
+            seed: 4,
+            plant_species: vec![species("Dormant", 0.0), species("Steady", 1.0), species("Rampant", 2.0)],
+            plant_growth_rate: 0.05,
+            initial_herbivores: 0,
+            initial_carnivores: 0,
+            initial_omnivores: 0,
+            ..SimulationConfig::minimal(40, 40)
+        };
+        let mut ecosystem = Ecosystem::new_custom(config);
+        let stats = run(&mut ecosystem, 30);
+        assert_eq!(stats.plant_births_of(0), 0);
+        assert_eq!(ecosystem.population().plants[0], 10);
+        assert!(stats.plant_births_of(1) > 0);
+        assert!(stats.plant_births_of(2) > stats.plant_births_of(1));
+    }
+
+    #[test]
+    fn efficient_genomes_lose_less_energy() {
+        let mut ecosystem = scene(3, 3);
+        place(&mut ecosystem, AgentType::Herbivore, 1, 1, 20);
+        ecosystem.herbivores[0].genome.energy_loss_mult = 0.5;
+        run(&mut ecosystem, 8);
+        assert_eq!(ecosystem.herbivores[0].energy, 20 - 8 * ecosystem.config.herbivore_energy_loss / 2);
+    }
+
+    #[test]
+    fn a_cap_of_zero_stops_breeding() {
+        let mut ecosystem = scene(5, 5);
+        ecosystem.config.herbivore_reproduction_rate = 1.0;
+        ecosystem.config.herbivore_max_population = Some(0);
+        place(&mut ecosystem, AgentType::Herbivore, 2, 2, 40);
+        assert_eq!(run(&mut ecosystem, 5).herbivore_births, 0);
+        ecosystem.config.herbivore_max_population = None;
+        assert!(run(&mut ecosystem, 1).herbivore_births > 0);
+    }
+
+    #[test]
+    fn map_files_keep_other_cells_without_plant_species() {
+        let path = std::env::temp_dir().join(format!("ecosim-map-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "HL\nWR\n").unwrap();
+        let config = SimulationConfig { plant_species: Vec::new(), from_map_file: Some(path.clone()), ..SimulationConfig::minimal(4, 4) };
+        let ecosystem = Ecosystem::new_custom(config);
+        std::fs::remove_file(&path).unwrap();
+        assert!(ecosystem.plants.is_empty());
+        assert_eq!((ecosystem.herbivores.len(), ecosystem.waters.len(), ecosystem.rocks.len()), (1, 1, 1));
+    }
+
+    #[test]
+    fn legacy_snapshots_keep_dark_plants_as_the_second_species() {
+        let mut ecosystem = scene(4, 4);
+        ecosystem.plants.push(Agent::new(1, AgentType::Plant, 0, 0, 5));
+        ecosystem.plants.push(Agent::new(2, AgentType::Plant, 1, 0, 5));
+        let mut snapshot = serde_json::to_value(&ecosystem).unwrap();
+        for (plant, legacy_type) in snapshot["plants"].as_array_mut().unwrap().iter_mut().zip(["LightPlant", "DarkPlant"]) {
+            let plant = plant.as_object_mut().unwrap();
+            plant.remove("species");
+            plant.insert("agent_type".to_string(), legacy_type.into());
+        }
+        let loaded = Ecosystem::from_json(&snapshot.to_string()).unwrap();
+        let species: Vec<(u32, usize)> = loaded.plants.iter().map(|p| (p.id, p.species)).collect();
+        assert_eq!(species, vec![(1, 0), (2, 1)]);
+        assert!(loaded.plants.iter().all(|p| p.agent_type == AgentType::Plant));
+        let reloaded = Ecosystem::from_json(&loaded.to_json()).unwrap();
+        assert_eq!(serde_json::to_value(&reloaded).unwrap(), serde_json::to_value(&loaded).unwrap());
     }
 }
@@ -0,0 +1,147 @@
+use crate::config::{AgentType, SimulationConfig};
+use crate::ecosystem::{Ecosystem, SimulationStats};
+use std::fmt;
+use std::str::FromStr;
+
+pub fn run_headless(config: SimulationConfig, steps: usize) -> Vec<SimulationStats> {
+    let mut ecosystem = Ecosystem::new_custom(config);
+    let mut stats = SimulationStats::default();
+    let mut series = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        ecosystem.step(&mut stats);
+        series.push(stats.clone());
+    }
+    series
+}
+
+/// Condition `run_until` steps an ecosystem until. Parses from text such as
+/// "iteration 500", "carnivores < 5" or "omnivores extinct".
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunUntil {
+    Iteration(usize),
+    SpeciesBelow(AgentType, usize),
+    Extinct(AgentType),
+}
+
+// Plants count across all species.
+const SPECIES_NAMES: [(AgentType, &str); 5] = [
+    (AgentType::Plant, "plants"),
+    (AgentType::Herbivore, "herbivores"),
+    (AgentType::Carnivore, "carnivores"),
+    (AgentType::Omnivore, "omnivores"),
+    (AgentType::Scavenger, "scavengers"),
+];
+
+fn species_name(species: &AgentType) -> &'static str {
+    SPECIES_NAMES.iter().find(|(s, _)| s == species).map_or("agents", |(_, name)| name)
+}
+
+impl RunUntil {
+    pub fn is_met(&self, ecosystem: &Ecosystem) -> bool {
+        match self {
+            RunUntil::Iteration(iteration) => ecosystem.iteration_count >= *iteration,
+            RunUntil::SpeciesBelow(species, count) => ecosystem.count_of(species) < *count,
+            RunUntil::Extinct(species) => ecosystem.count_of(species) == 0,
+        }
+    }
+}
+
+impl fmt::Display for RunUntil {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunUntil::Iteration(iteration) => write!(f, "iteration {}", iteration),
+            RunUntil::SpeciesBelow(species, count) => write!(f, "{} < {}", species_name(species), count),
+            RunUntil::Extinct(species) => write!(f, "{} extinct", species_name(species)),
+        }
+    }
+}
+
+impl FromStr for RunUntil {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim().to_lowercase();
+        let number = |s: &str| s.trim().parse::<usize>().map_err(|_| format!("'{}' is not a count", s.trim()));
+        let species = |s: &str| {
+            // "carnivore" and "Carnivores" both name the carnivores.
+            let name = s.trim().trim_end_matches('s');
+            SPECIES_NAMES
+                .iter()
+                .find(|(_, n)| n.trim_end_matches('s') == name)
+                .map(|(species, _)| species.clone())
+                .ok_or_else(|| format!("unknown species '{}'", s.trim()))
+        };
+        if let Some(rest) = text.strip_prefix("iteration") {
+            Ok(RunUntil::Iteration(number(rest)?))
+        } else if let Some((name, count)) = text.split_once('<') {
+            Ok(RunUntil::SpeciesBelow(species(name)?, number(count)?))
+        } else if let Some(name) = text.strip_suffix("extinct") {
+            Ok(RunUntil::Extinct(species(name)?))
+        } else {
+            Err(format!("expected 'iteration N', '<species> < N' or '<species> extinct', got '{}'", text))
+        }
+    }
+}
+
+/// Steps `ecosystem` until `condition` holds, at most `max_steps` times, and
+/// returns how many steps ran.
+pub fn run_until(ecosystem: &mut Ecosystem, stats: &mut SimulationStats, condition: &RunUntil, max_steps: usize) -> usize {
+    let mut steps = 0;
+    while steps < max_steps && !condition.is_met(ecosystem) {
+        ecosystem.step(stats);
+        steps += 1;
+    }
+    steps
+}
+
+/// Config field varied by `sweep`. Integer fields take the rounded value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepField {
+    PlantGrowthRate,
+    HerbivoreEnergyGain,
+    CarnivoreEnergyGain,
+    OmnivoreEnergyGainPlants,
+    OmnivoreEnergyGainHerbivores,
+    HerbivoreReproductionRate,
+    CarnivoreReproductionRate,
+    OmnivoreReproductionRate,
+    WaterSpawnChance,
+    TreeSpawnChance,
+}
+
+impl SweepField {
+    pub fn apply(self, config: &mut SimulationConfig, value: f32) {
+        match self {
+            SweepField::PlantGrowthRate => config.plant_growth_rate = value,
+            SweepField::HerbivoreEnergyGain => config.herbivore_energy_gain = value.round() as i32,
+            SweepField::CarnivoreEnergyGain => config.carnivore_energy_gain = value.round() as i32,
+            SweepField::OmnivoreEnergyGainPlants => config.omnivore_energy_gain_plants = value.round() as i32,
+            SweepField::OmnivoreEnergyGainHerbivores => config.omnivore_energy_gain_herbivores = value.round() as i32,
+            SweepField::HerbivoreReproductionRate => config.herbivore_reproduction_rate = value,
+            SweepField::CarnivoreReproductionRate => config.carnivore_reproduction_rate = value,
+            SweepField::OmnivoreReproductionRate => config.omnivore_reproduction_rate = value,
+            SweepField::WaterSpawnChance => config.water_spawn_chance = value,
+            SweepField::TreeSpawnChance => config.tree_spawn_chance = value,
+        }
+    }
+}
+
+/// Runs one headless simulation per value and returns the final stats of
+/// each. Run `i` is seeded with `base.seed + i`, so sweeps are reproducible.
+pub fn sweep(base: SimulationConfig, field: SweepField, values: &[f32], steps: usize) -> Vec<(f32, SimulationStats)> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| {
+            let mut config = base.clone();
+            config.seed = base.seed.wrapping_add(index as u64);
+            field.apply(&mut config, value);
+            let mut ecosystem = Ecosystem::new_custom(config);
+            let mut stats = SimulationStats::default();
+            for _ in 0..steps {
+                ecosystem.step(&mut stats);
+            }
+            (value, stats)
+        })
+        .collect()
+}
@@ -0,0 +1,9 @@
+pub mod config;
+pub mod ecosystem;
+pub mod headless;
+pub mod map;
+pub mod occupancy;
+pub mod replay;
+
+pub use headless::{run_headless, run_until, sweep, RunUntil, SweepField};
+pub use replay::{replay, SessionRecorder};
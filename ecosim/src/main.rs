@@ -1,18 +1,42 @@
 use macroquad::prelude::*;
-use crate::config::{SimulationConfig, AgentType};
-use crate::ecosystem::{Ecosystem, SimulationStats};
-
-mod config;
-mod ecosystem;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use ecosim::config::{SimulationConfig, Agent, AgentType, PlantSpecies};
+use ecosim::ecosystem::{Ecosystem, Edge, IterationDelta, SimulationStats, CELL_PRECEDENCE, LIFESPAN_BUCKET};
+use ecosim::{RunUntil, SessionRecorder};
 
 const DARK_GREEN: Color = Color::new(0.0, 0.5, 0.0, 1.0);
+const YELLOW_GREEN: Color = Color::new(0.6, 0.8, 0.1, 1.0);
+const OLIVE: Color = Color::new(0.4, 0.45, 0.1, 1.0);
+const MAX_SPEED: usize = 50;
+const CHART_WINDOW: usize = 200;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
+const VISIBLE_MARGIN: f32 = 40.0;
+const SIMULATION_COUNTS: [usize; 6] = [1, 2, 3, 4, 6, 8];
+const GRID_STATS_HEIGHT: f32 = 80.0;
+const CONTROL_BAR_HEIGHT: f32 = 90.0;
+const SESSION_FILE: &str = "session.json";
+const GRID_LINE_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.25);
+const GRID_LABEL_EVERY: usize = 5;
+const ALERT_SECONDS: f64 = 5.0;
+const MIN_GLYPH_CELL: f32 = 6.0;
+const MINIMAP_WIDTH: f32 = 200.0;
+const MINIMAP_PANE_HEIGHT: f32 = 100.0;
+const MINIMAP_GAP: f32 = 4.0;
+
+const WINDOWED_FLAG: &str = "--windowed";
 
 fn window_conf() -> Conf {
+    let windowed = std::env::args().skip(1).any(|arg| arg == WINDOWED_FLAG);
     Conf {
         window_title: "Rust.eze".to_owned(),
-        window_width: 1920,
-        window_height: 1080,
-        fullscreen: true,
+        window_width: if windowed { 1280 } else { 1920 },
+        window_height: if windowed { 720 } else { 1080 },
+        fullscreen: !windowed,
+        window_resizable: true,
         ..Default::default()
     }
 }
@@ -22,54 +46,814 @@ enum AppState {
     ConfigMenu,
     Simulation,
     StatsScreen,
+    CompareScreen,
+    LifespanScreen,
+}
+
+// Colours of everything drawn for an agent type, so the grid, texts,
+// chart, trails and menu all follow the selected scheme.
+struct ColorScheme {
+    name: &'static str,
+    /// Plant species take these in turn, starting over after the last.
+    plants: [Color; 4],
+    herbivore: Color,
+    carnivore: Color,
+    omnivore: Color,
+    scavenger: Color,
+    water: Color,
+    tree: Color,
+    rock: Color,
+    corpse: Color,
+    infected: Color,
+    ground: Color,
+    night_ground: Color,
+}
+
+const COLOR_SCHEMES: [ColorScheme; 3] = [
+    ColorScheme {
+        name: "Default",
+        plants: [GREEN, DARK_GREEN, YELLOW_GREEN, OLIVE],
+        herbivore: PINK,
+        carnivore: RED,
+        omnivore: ORANGE,
+        scavenger: PURPLE,
+        water: BLUE,
+        tree: BROWN,
+        rock: DARKGRAY,
+        corpse: MAROON,
+        infected: Color::new(0.6, 1.0, 0.0, 1.0),
+        ground: LIGHTGRAY,
+        night_ground: GRAY,
+    },
+    // Okabe-Ito colours, which keep distinct hues under deuteranopia: the
+    // two plants differ in hue rather than only in lightness, and no two
+    // animals are both red-orange.
+    ColorScheme {
+        name: "Colorblind",
+        plants: [Color::new(0.0, 0.62, 0.45, 1.0), Color::new(0.0, 0.45, 0.7, 1.0), Color::new(0.9, 0.6, 0.0, 1.0), Color::new(0.1, 0.2, 0.4, 1.0)],
+        herbivore: Color::new(0.8, 0.47, 0.65, 1.0),
+        carnivore: Color::new(0.84, 0.37, 0.0, 1.0),
+        omnivore: Color::new(0.94, 0.89, 0.26, 1.0),
+        scavenger: BLACK,
+        water: Color::new(0.34, 0.71, 0.91, 1.0),
+        tree: Color::new(0.45, 0.3, 0.15, 1.0),
+        rock: DARKGRAY,
+        corpse: Color::new(0.3, 0.15, 0.15, 1.0),
+        infected: WHITE,
+        ground: LIGHTGRAY,
+        night_ground: GRAY,
+    },
+    ColorScheme {
+        name: "High Contrast",
+        plants: [Color::new(0.0, 1.0, 0.0, 1.0), Color::new(0.0, 0.45, 0.0, 1.0), Color::new(0.6, 1.0, 0.6, 1.0), Color::new(0.7, 0.85, 0.0, 1.0)],
+        herbivore: MAGENTA,
+        carnivore: Color::new(1.0, 0.0, 0.0, 1.0),
+        omnivore: Color::new(1.0, 1.0, 0.0, 1.0),
+        scavenger: Color::new(0.6, 0.4, 1.0, 1.0),
+        water: Color::new(0.0, 0.5, 1.0, 1.0),
+        tree: Color::new(0.6, 0.3, 0.0, 1.0),
+        rock: WHITE,
+        corpse: Color::new(0.5, 0.0, 0.0, 1.0),
+        infected: Color::new(0.0, 1.0, 1.0, 1.0),
+        ground: Color::new(0.12, 0.12, 0.12, 1.0),
+        night_ground: BLACK,
+    },
+];
+
+impl ColorScheme {
+    // Plants take the first species' colour; `of` tells species apart.
+    fn agent(&self, agent_type: &AgentType) -> Color {
+        match agent_type {
+            AgentType::Plant => self.plant(0),
+            AgentType::Herbivore => self.herbivore,
+            AgentType::Carnivore => self.carnivore,
+            AgentType::Omnivore => self.omnivore,
+            AgentType::Scavenger => self.scavenger,
+            AgentType::Water => self.water,
+            AgentType::Tree => self.tree,
+            AgentType::Rock => self.rock,
+            AgentType::Corpse => self.corpse,
+        }
+    }
+
+    fn plant(&self, species: usize) -> Color {
+        self.plants[species % self.plants.len()]
+    }
+
+    fn of(&self, agent: &Agent) -> Color {
+        match agent.agent_type {
+            AgentType::Plant => self.plant(agent.species),
+            _ => self.agent(&agent.agent_type),
+        }
+    }
+
+    // The living species, in `Population::living` order.
+    fn species(&self, plant_species: usize) -> Vec<Color> {
+        (0..plant_species).map(|species| self.plant(species)).chain([self.herbivore, self.carnivore, self.omnivore, self.scavenger]).collect()
+    }
+
+    fn tint(&self, tint: &Tint) -> Color {
+        match tint {
+            Tint::Plain(color) => *color,
+            Tint::Agent(agent_type) => self.agent(agent_type),
+            Tint::Plant(species) => self.plant(*species),
+            Tint::Infected => self.infected,
+        }
+    }
+}
+
+// Colour of a menu section or field, resolved against the current scheme.
+#[derive(Clone)]
+enum Tint {
+    Plain(Color),
+    Agent(AgentType),
+    Plant(usize),
+    Infected,
+}
+
+#[derive(PartialEq)]
+enum RenderMode {
+    Normal,
+    Heatmap,
+    Elevation,
 }
 
+const MAX_FIELD_VALUE: f64 = 1_000_000.0;
+
 struct ConfigField {
-    label: String,
+    section: &'static str,
+    label: &'static str,
     is_int: bool,
     input: String,
-    color: Color,
+    // Byte offset in `input` where typed characters go; inputs are ASCII.
+    cursor: usize,
+    tint: Tint,
+    min: f64,
+    max: f64,
+    apply: fn(&mut SimulationConfig, &str),
 }
 
 impl ConfigField {
     fn display_value(&self) -> String {
         self.input.clone()
     }
+
+    fn tinted(mut self, agent_type: AgentType) -> Self {
+        self.tint = Tint::Agent(agent_type);
+        self
+    }
+
+    fn tinted_plant(mut self, species: usize) -> Self {
+        self.tint = Tint::Plant(species);
+        self
+    }
+
+    fn range(mut self, min: f64, max: f64) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    // The input can be replaced as a whole (loads, pastes, undo), so the
+    // cursor is kept within it here rather than at every replacement.
+    fn cursor(&self) -> usize {
+        self.cursor.min(self.input.len())
+    }
+
+    fn push(&mut self, ch: char) {
+        // A minus sign only goes in front, and only where negatives are allowed.
+        let sign = ch == '-' && self.min < 0.0 && self.cursor() == 0 && !self.input.starts_with('-');
+        if (ch.is_ascii_digit() || sign) && self.input == "0" {
+            self.input.clear();
+        }
+        if ch.is_ascii_digit() || sign || (ch == '.' && !self.is_int && !self.input.contains('.')) {
+            let cursor = self.cursor();
+            self.input.insert(cursor, ch);
+            self.cursor = cursor + 1;
+        }
+        self.clamp(f64::MIN, self.max);
+    }
+
+    fn backspace(&mut self) {
+        let cursor = self.cursor();
+        if cursor > 0 {
+            self.input.remove(cursor - 1);
+            self.cursor = cursor - 1;
+        }
+    }
+
+    fn delete(&mut self) {
+        let cursor = self.cursor();
+        if cursor < self.input.len() {
+            self.input.remove(cursor);
+        }
+    }
+
+    fn move_cursor(&mut self, offset: isize) {
+        self.cursor = self.cursor().saturating_add_signed(offset).min(self.input.len());
+    }
+
+    // The minimum is only enforced when leaving a field, so values can be
+    // typed digit by digit.
+    fn commit(&mut self) {
+        self.clamp(self.min, self.max);
+        self.cursor = self.input.len();
+    }
+
+    fn clamp(&mut self, min: f64, max: f64) {
+        if let Ok(value) = self.input.parse::<f64>() {
+            if value > max || value < min {
+                let clamped = value.clamp(min, max);
+                self.input = if self.is_int { (clamped as u64).to_string() } else { clamped.to_string() };
+                self.cursor = self.input.len();
+            }
+        }
+    }
+}
+
+const CONFIG_UNDO_LIMIT: usize = 50;
+
+// Undo and redo for one simulation's fields. Each entry holds every field's
+// input, taken when the selected field is left or the fields are replaced
+// as a whole, so typing into one field undoes as a single edit.
+struct ConfigHistory {
+    saved: Vec<String>,
+    undo: VecDeque<Vec<String>>,
+    redo: Vec<Vec<String>>,
+}
+
+impl ConfigHistory {
+    fn new(fields: &[ConfigField]) -> Self {
+        Self { saved: Self::inputs(fields), undo: VecDeque::new(), redo: Vec::new() }
+    }
+
+    fn inputs(fields: &[ConfigField]) -> Vec<String> {
+        fields.iter().map(|field| field.input.clone()).collect()
+    }
+
+    fn checkpoint(&mut self, fields: &[ConfigField]) {
+        let inputs = Self::inputs(fields);
+        if inputs == self.saved {
+            return;
+        }
+        if self.undo.len() == CONFIG_UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(std::mem::replace(&mut self.saved, inputs));
+        self.redo.clear();
+    }
+
+    // Both return the first field the step changed, to select it.
+    fn undo(&mut self, fields: &mut [ConfigField]) -> Option<usize> {
+        self.checkpoint(fields);
+        let inputs = self.undo.pop_back()?;
+        self.redo.push(std::mem::replace(&mut self.saved, inputs));
+        Self::apply(&self.saved, fields)
+    }
+
+    fn redo(&mut self, fields: &mut [ConfigField]) -> Option<usize> {
+        self.checkpoint(fields);
+        let inputs = self.redo.pop()?;
+        self.undo.push_back(std::mem::replace(&mut self.saved, inputs));
+        Self::apply(&self.saved, fields)
+    }
+
+    fn apply(inputs: &[String], fields: &mut [ConfigField]) -> Option<usize> {
+        let changed = fields.iter().zip(inputs).position(|(field, input)| field.input != *input);
+        for (field, input) in fields.iter_mut().zip(inputs) {
+            field.input = input.clone();
+        }
+        changed
+    }
+}
+
+const CONFIG_SECTIONS: [(&str, Tint); 9] = [
+    ("Grid", Tint::Plain(WHITE)),
+    ("Plants", Tint::Agent(AgentType::Plant)),
+    ("Herbivores", Tint::Agent(AgentType::Herbivore)),
+    ("Carnivores", Tint::Agent(AgentType::Carnivore)),
+    ("Omnivores", Tint::Agent(AgentType::Omnivore)),
+    ("Scavengers", Tint::Agent(AgentType::Scavenger)),
+    ("Terrain", Tint::Agent(AgentType::Water)),
+    ("Disease", Tint::Infected),
+    ("General", Tint::Plain(LIGHTGRAY)),
+];
+
+fn section_tint(section: &str) -> Tint {
+    CONFIG_SECTIONS.iter().find(|(name, _)| *name == section).map_or(Tint::Plain(WHITE), |(_, tint)| tint.clone())
+}
+
+// Integer fields default to 0..=MAX_FIELD_VALUE and decimal fields to 0..=1.
+fn field(section: &'static str, label: &'static str, is_int: bool, value: impl ToString, apply: fn(&mut SimulationConfig, &str)) -> ConfigField {
+    let max = if is_int { MAX_FIELD_VALUE } else { 1.0 };
+    let input = value.to_string();
+    ConfigField { section, label, is_int, cursor: input.len(), input, tint: section_tint(section), min: 0.0, max, apply }
+}
+
+fn set<T: std::str::FromStr>(target: &mut T, input: &str) {
+    if let Ok(value) = input.parse() {
+        *target = value;
+    }
+}
+
+// Optional settings show as an empty field while unset.
+fn optional(value: Option<impl ToString>) -> String {
+    value.map_or(String::new(), |value| value.to_string())
+}
+
+// The menu edits the first two plant species; further ones come from
+// config files. A species the config lacks shows as an empty field.
+fn species_value(c: &SimulationConfig, species: usize, value: fn(&PlantSpecies) -> String) -> String {
+    c.plant_species.get(species).map_or(String::new(), value)
+}
+
+fn config_fields(c: &SimulationConfig) -> Vec<ConfigField> {
+    vec![
+        field("Grid", "Grid Width", true, c.grid_width, |c, s| set(&mut c.grid_width, s)).range(3.0, 1000.0),
+        field("Grid", "Grid Height", true, c.grid_height, |c, s| set(&mut c.grid_height, s)).range(3.0, 1000.0),
+        field("Plants", "Initial Plants (Species 1)", true, species_value(c, 0, |p| p.initial_count.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(0) { set(&mut p.initial_count, s) }),
+        field("Plants", "Initial Plants (Species 2)", true, species_value(c, 1, |p| p.initial_count.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(1) { set(&mut p.initial_count, s) }).tinted_plant(1),
+        field("Plants", "Growth Rate", false, c.plant_growth_rate, |c, s| set(&mut c.plant_growth_rate, s)),
+        field("Plants", "Growth Mult (Species 1)", false, species_value(c, 0, |p| p.growth_mult.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(0) { set(&mut p.growth_mult, s) }).range(0.0, 100.0),
+        field("Plants", "Growth Mult (Species 2)", false, species_value(c, 1, |p| p.growth_mult.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(1) { set(&mut p.growth_mult, s) }).tinted_plant(1).range(0.0, 100.0),
+        field("Plants", "Water Affinity (Species 1)", false, species_value(c, 0, |p| p.water_affinity.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(0) { set(&mut p.water_affinity, s) }).range(-1.0, 1.0),
+        field("Plants", "Water Affinity (Species 2)", false, species_value(c, 1, |p| p.water_affinity.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(1) { set(&mut p.water_affinity, s) }).tinted_plant(1).range(-1.0, 1.0),
+        field("Plants", "Tree Affinity (Species 1)", false, species_value(c, 0, |p| p.tree_affinity.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(0) { set(&mut p.tree_affinity, s) }).range(-1.0, 1.0),
+        field("Plants", "Tree Affinity (Species 2)", false, species_value(c, 1, |p| p.tree_affinity.to_string()), |c, s| if let Some(p) = c.plant_species.get_mut(1) { set(&mut p.tree_affinity, s) }).tinted_plant(1).range(-1.0, 1.0),
+        field("Plants", "Pollination Radius", true, c.pollination_radius, |c, s| set(&mut c.pollination_radius, s)).range(1.0, 100.0),
+        field("Plants", "Elevation Preference", false, c.plant_elevation_preference, |c, s| set(&mut c.plant_elevation_preference, s)).range(-1.0, 1.0),
+        field("Herbivores", "Initial Herbivores", true, c.initial_herbivores, |c, s| set(&mut c.initial_herbivores, s)),
+        field("Herbivores", "Initial Energy", true, c.herbivore_initial_energy, |c, s| set(&mut c.herbivore_initial_energy, s)),
+        field("Herbivores", "Energy Gain", true, c.herbivore_energy_gain, |c, s| set(&mut c.herbivore_energy_gain, s)),
+        field("Herbivores", "Energy Gain (Plant Species 1)", true, species_value(c, 0, |p| optional(p.herbivore_energy_gain)), |c, s| if let Some(p) = c.plant_species.get_mut(0) { p.herbivore_energy_gain = s.parse().ok() }),
+        field("Herbivores", "Energy Gain (Plant Species 2)", true, species_value(c, 1, |p| optional(p.herbivore_energy_gain)), |c, s| if let Some(p) = c.plant_species.get_mut(1) { p.herbivore_energy_gain = s.parse().ok() }),
+        field("Herbivores", "Energy Loss", true, c.herbivore_energy_loss, |c, s| set(&mut c.herbivore_energy_loss, s)),
+        field("Herbivores", "Reproduction Threshold", true, c.herbivore_reproduction_threshold, |c, s| set(&mut c.herbivore_reproduction_threshold, s)),
+        field("Herbivores", "Reproduction Rate", false, c.herbivore_reproduction_rate, |c, s| c.herbivore_reproduction_rate = parse_rate(s, c.herbivore_reproduction_rate)),
+        field("Herbivores", "Reproduction Cost", true, c.herbivore_reproduction_cost, |c, s| set(&mut c.herbivore_reproduction_cost, s)).range(0.0, MAX_FIELD_VALUE),
+        field("Herbivores", "Max Age", true, c.herbivore_max_age, |c, s| set(&mut c.herbivore_max_age, s)),
+        field("Herbivores", "Max Population", true, optional(c.herbivore_max_population), |c, s| c.herbivore_max_population = s.parse().ok()),
+        field("Herbivores", "Max Energy", true, c.herbivore_max_energy, |c, s| set(&mut c.herbivore_max_energy, s)),
+        field("Herbivores", "Starvation Threshold", true, c.herbivore_starvation_threshold, |c, s| set(&mut c.herbivore_starvation_threshold, s)),
+        field("Herbivores", "Vision Radius", true, c.herbivore_vision_radius, |c, s| set(&mut c.herbivore_vision_radius, s)).range(0.0, 100.0),
+        field("Herbivores", "Flee Chance", false, c.herbivore_flee_chance, |c, s| set(&mut c.herbivore_flee_chance, s)),
+        field("Carnivores", "Initial Carnivores", true, c.initial_carnivores, |c, s| set(&mut c.initial_carnivores, s)),
+        field("Carnivores", "Initial Energy", true, c.carnivore_initial_energy, |c, s| set(&mut c.carnivore_initial_energy, s)),
+        field("Carnivores", "Energy Gain", true, c.carnivore_energy_gain, |c, s| set(&mut c.carnivore_energy_gain, s)),
+        field("Carnivores", "Energy Loss", true, c.carnivore_energy_loss, |c, s| set(&mut c.carnivore_energy_loss, s)),
+        field("Carnivores", "Reproduction Threshold", true, c.carnivore_reproduction_threshold, |c, s| set(&mut c.carnivore_reproduction_threshold, s)),
+        field("Carnivores", "Reproduction Rate", false, c.carnivore_reproduction_rate, |c, s| c.carnivore_reproduction_rate = parse_rate(s, c.carnivore_reproduction_rate)),
+        field("Carnivores", "Reproduction Cost", true, c.carnivore_reproduction_cost, |c, s| set(&mut c.carnivore_reproduction_cost, s)).range(0.0, MAX_FIELD_VALUE),
+        field("Carnivores", "Max Age", true, c.carnivore_max_age, |c, s| set(&mut c.carnivore_max_age, s)),
+        field("Carnivores", "Max Population", true, optional(c.carnivore_max_population), |c, s| c.carnivore_max_population = s.parse().ok()),
+        field("Carnivores", "Max Energy", true, c.carnivore_max_energy, |c, s| set(&mut c.carnivore_max_energy, s)),
+        field("Carnivores", "Starvation Threshold", true, c.carnivore_starvation_threshold, |c, s| set(&mut c.carnivore_starvation_threshold, s)),
+        field("Carnivores", "Vision Radius", true, c.carnivore_vision_radius, |c, s| set(&mut c.carnivore_vision_radius, s)).range(0.0, 100.0),
+        field("Carnivores", "Overfed Threshold", true, c.carnivore_overfed_threshold, |c, s| set(&mut c.carnivore_overfed_threshold, s)),
+        field("Omnivores", "Initial Omnivores", true, c.initial_omnivores, |c, s| set(&mut c.initial_omnivores, s)),
+        field("Omnivores", "Initial Energy", true, c.omnivore_initial_energy, |c, s| set(&mut c.omnivore_initial_energy, s)),
+        field("Omnivores", "Energy Gain (Plants)", true, c.omnivore_energy_gain_plants, |c, s| set(&mut c.omnivore_energy_gain_plants, s)),
+        field("Omnivores", "Energy Gain (Plant Species 1)", true, species_value(c, 0, |p| optional(p.omnivore_energy_gain)), |c, s| if let Some(p) = c.plant_species.get_mut(0) { p.omnivore_energy_gain = s.parse().ok() }),
+        field("Omnivores", "Energy Gain (Plant Species 2)", true, species_value(c, 1, |p| optional(p.omnivore_energy_gain)), |c, s| if let Some(p) = c.plant_species.get_mut(1) { p.omnivore_energy_gain = s.parse().ok() }),
+        field("Omnivores", "Energy Gain (Herbivores)", true, c.omnivore_energy_gain_herbivores, |c, s| set(&mut c.omnivore_energy_gain_herbivores, s)),
+        field("Omnivores", "Meat Preference", false, c.omnivore_meat_preference, |c, s| set(&mut c.omnivore_meat_preference, s)),
+        field("Omnivores", "Energy Loss", true, c.omnivore_energy_loss, |c, s| set(&mut c.omnivore_energy_loss, s)),
+        field("Omnivores", "Reproduction Threshold", true, c.omnivore_reproduction_threshold, |c, s| set(&mut c.omnivore_reproduction_threshold, s)),
+        field("Omnivores", "Reproduction Rate", false, c.omnivore_reproduction_rate, |c, s| c.omnivore_reproduction_rate = parse_rate(s, c.omnivore_reproduction_rate)),
+        field("Omnivores", "Reproduction Cost", true, c.omnivore_reproduction_cost, |c, s| set(&mut c.omnivore_reproduction_cost, s)).range(0.0, MAX_FIELD_VALUE),
+        field("Omnivores", "Max Age", true, c.omnivore_max_age, |c, s| set(&mut c.omnivore_max_age, s)),
+        field("Omnivores", "Max Population", true, optional(c.omnivore_max_population), |c, s| c.omnivore_max_population = s.parse().ok()),
+        field("Omnivores", "Max Energy", true, c.omnivore_max_energy, |c, s| set(&mut c.omnivore_max_energy, s)),
+        field("Omnivores", "Starvation Threshold", true, c.omnivore_starvation_threshold, |c, s| set(&mut c.omnivore_starvation_threshold, s)),
+        field("Omnivores", "Vision Radius", true, c.omnivore_vision_radius, |c, s| set(&mut c.omnivore_vision_radius, s)).range(0.0, 100.0),
+        field("Omnivores", "Overfed Threshold", true, c.omnivore_overfed_threshold, |c, s| set(&mut c.omnivore_overfed_threshold, s)),
+        field("Scavengers", "Initial Scavengers", true, c.initial_scavengers, |c, s| set(&mut c.initial_scavengers, s)),
+        field("Scavengers", "Initial Energy", true, c.scavenger_initial_energy, |c, s| set(&mut c.scavenger_initial_energy, s)),
+        field("Scavengers", "Energy Gain (Corpses)", true, c.scavenger_energy_gain, |c, s| set(&mut c.scavenger_energy_gain, s)),
+        field("Scavengers", "Energy Loss", true, c.scavenger_energy_loss, |c, s| set(&mut c.scavenger_energy_loss, s)),
+        field("Scavengers", "Reproduction Threshold", true, c.scavenger_reproduction_threshold, |c, s| set(&mut c.scavenger_reproduction_threshold, s)),
+        field("Scavengers", "Reproduction Rate", false, c.scavenger_reproduction_rate, |c, s| c.scavenger_reproduction_rate = parse_rate(s, c.scavenger_reproduction_rate)),
+        field("Scavengers", "Reproduction Cost", true, c.scavenger_reproduction_cost, |c, s| set(&mut c.scavenger_reproduction_cost, s)).range(0.0, MAX_FIELD_VALUE),
+        field("Scavengers", "Max Energy", true, c.scavenger_max_energy, |c, s| set(&mut c.scavenger_max_energy, s)),
+        field("Scavengers", "Starvation Threshold", true, c.scavenger_starvation_threshold, |c, s| set(&mut c.scavenger_starvation_threshold, s)),
+        field("Scavengers", "Vision Radius", true, c.scavenger_vision_radius, |c, s| set(&mut c.scavenger_vision_radius, s)).range(0.0, 100.0),
+        field("Terrain", "Lakes Spawn Chance", false, c.water_spawn_chance, |c, s| set(&mut c.water_spawn_chance, s)),
+        field("Terrain", "Lake Lifespan", true, c.water_lifespan, |c, s| set(&mut c.water_lifespan, s)).range(1.0, MAX_FIELD_VALUE),
+        field("Terrain", "Lake Block Size", true, c.water_block_size, |c, s| set(&mut c.water_block_size, s)).range(1.0, 100.0),
+        field("Terrain", "Lake Influence Radius", true, c.water_influence_radius, |c, s| set(&mut c.water_influence_radius, s)).range(0.0, 100.0),
+        field("Terrain", "Lake Plant Growth Mult", false, c.water_plant_growth_mult, |c, s| set(&mut c.water_plant_growth_mult, s)).range(0.0, 100.0),
+        field("Terrain", "Water Drink Gain", true, c.water_drink_gain, |c, s| set(&mut c.water_drink_gain, s)),
+        field("Terrain", "Water Drink Cap", true, c.water_drink_cap, |c, s| set(&mut c.water_drink_cap, s)),
+        field("Terrain", "Trees Spawn Chance", false, c.tree_spawn_chance, |c, s| set(&mut c.tree_spawn_chance, s)).tinted(AgentType::Tree),
+        field("Terrain", "Tree Lifespan", true, c.tree_lifespan, |c, s| set(&mut c.tree_lifespan, s)).tinted(AgentType::Tree).range(1.0, MAX_FIELD_VALUE),
+        field("Terrain", "Tree Block Size", true, c.tree_block_size, |c, s| set(&mut c.tree_block_size, s)).tinted(AgentType::Tree).range(1.0, 100.0),
+        field("Terrain", "Tree Influence Radius", true, c.tree_influence_radius, |c, s| set(&mut c.tree_influence_radius, s)).tinted(AgentType::Tree).range(0.0, 100.0),
+        field("Terrain", "Tree Plant Chance", false, c.tree_plant_chance, |c, s| set(&mut c.tree_plant_chance, s)).tinted(AgentType::Tree),
+        field("Terrain", "Rocks", true, c.rock_count, |c, s| set(&mut c.rock_count, s)).tinted(AgentType::Rock),
+        field("Terrain", "Elevation Scale", true, c.elevation_scale, |c, s| set(&mut c.elevation_scale, s)).range(0.0, 1000.0),
+        field("Disease", "Spawn Chance", false, c.disease_spawn_chance, |c, s| set(&mut c.disease_spawn_chance, s)),
+        field("Disease", "Transmission Rate", false, c.transmission_rate, |c, s| set(&mut c.transmission_rate, s)),
+        field("Disease", "Duration", true, c.disease_duration, |c, s| set(&mut c.disease_duration, s)).range(1.0, MAX_FIELD_VALUE),
+        field("Disease", "Recovery Chance", false, c.recovery_chance, |c, s| set(&mut c.recovery_chance, s)),
+        field("Disease", "Energy Loss", true, c.disease_energy_loss, |c, s| set(&mut c.disease_energy_loss, s)),
+        field("Disease", "Immunity Duration", true, c.immunity_duration, |c, s| set(&mut c.immunity_duration, s)),
+        field("General", "Seed", true, c.seed, |c, s| set(&mut c.seed, s)).range(0.0, u64::MAX as f64),
+        field("General", "Day Length", true, c.day_length, |c, s| set(&mut c.day_length, s)),
+        field("General", "Night Growth Factor", false, c.night_growth_factor, |c, s| set(&mut c.night_growth_factor, s)).range(0.0, 10.0),
+        field("General", "Night Carnivore Move Factor", false, c.night_carnivore_move_factor, |c, s| set(&mut c.night_carnivore_move_factor, s)).range(0.0, 10.0),
+        field("General", "Season Length", true, c.season_length, |c, s| set(&mut c.season_length, s)),
+        field("General", "Weather Event Chance", false, c.weather_event_chance, |c, s| set(&mut c.weather_event_chance, s)),
+        field("General", "Weather Duration", true, c.weather_duration, |c, s| set(&mut c.weather_duration, s)).range(1.0, MAX_FIELD_VALUE),
+        field("General", "Move Energy Cost", true, c.move_energy_cost, |c, s| set(&mut c.move_energy_cost, s)),
+        field("General", "Time Scale", false, c.time_scale, |c, s| set(&mut c.time_scale, s)).range(0.0, 100.0),
+        field("General", "Overfed Decay Rate", false, c.overfed_decay_rate, |c, s| set(&mut c.overfed_decay_rate, s)),
+        field("General", "Mutation Std", false, c.mutation_std, |c, s| set(&mut c.mutation_std, s)),
+        field("General", "Corpse Decay Time", true, c.corpse_decay_time, |c, s| set(&mut c.corpse_decay_time, s)),
+        field("General", "Migration Rate", false, c.migration_rate, |c, s| set(&mut c.migration_rate, s)),
+        field("General", "GIF Frame Rate", true, c.gif_frame_rate, |c, s| set(&mut c.gif_frame_rate, s)).range(1.0, 100.0),
+        field("General", "GIF Downscale", false, c.gif_downscale, |c, s| set(&mut c.gif_downscale, s)).range(1.0, 16.0),
+        field("General", "GIF Max Frames", true, c.gif_max_frames, |c, s| set(&mut c.gif_max_frames, s)).range(1.0, 10000.0),
+        field("General", "Trail Length", true, c.trail_length, |c, s| set(&mut c.trail_length, s)).range(1.0, 100.0),
+    ]
+}
+
+fn parse_rate(input: &str, fallback: f32) -> f32 {
+    input.parse::<f32>().map(|rate| rate.clamp(0.0, 1.0)).unwrap_or(fallback)
+}
+
+fn config_from_fields(fields: &[ConfigField], base: &SimulationConfig) -> SimulationConfig {
+    let mut config = base.clone();
+    for field in fields {
+        (field.apply)(&mut config, &field.input);
+    }
+    config
+}
+
+// " (Name)" for named configs, to follow a simulation's number in titles.
+fn name_suffix(config: &SimulationConfig) -> String {
+    if config.name.is_empty() { String::new() } else { format!(" ({})", config.name) }
+}
+
+// Columns and rows of the panes simulations are laid out in.
+fn pane_grid(count: usize) -> (usize, usize) {
+    let cols = if count <= 3 { count.max(1) } else { count.div_ceil(2) };
+    (cols, count.div_ceil(cols).max(1))
+}
+
+// Starting configs offered for each simulation: the defaults, then variants
+// with a species or the terrain taken out so runs can be compared.
+fn default_configs(count: usize) -> Vec<SimulationConfig> {
+    let variants = [
+        ("Default", "Every species and terrain", SimulationConfig::default()),
+        ("No Omnivores", "Starts without omnivores", SimulationConfig { initial_omnivores: 0, ..Default::default() }),
+        ("No Carnivores", "Starts without carnivores", SimulationConfig { initial_carnivores: 0, ..Default::default() }),
+        ("No Terrain", "Water and trees never appear", SimulationConfig { water_spawn_chance: 0.0, tree_spawn_chance: 0.0, ..Default::default() }),
+        ("Herbivores Only", "Starts without carnivores or omnivores", SimulationConfig { initial_carnivores: 0, initial_omnivores: 0, ..Default::default() }),
+        ("Wrapped Edges", "The grid wraps around its edges", SimulationConfig { wrap_edges: true, ..Default::default() }),
+        ("Local Spread", "Plants only grow next to other plants", SimulationConfig { plant_spread_local: true, ..Default::default() }),
+        ("Day and Night", "Days last 100 iterations", SimulationConfig { day_length: 100, ..Default::default() }),
+    ];
+    let named = variants.map(|(name, description, config)| SimulationConfig { name: name.to_string(), description: Some(description.to_string()), ..config });
+    if count == 2 {
+        return vec![named[0].clone(), named[2].clone()];
+    }
+    named.into_iter().cycle().take(count).collect()
+}
+
+// Grid size that fills a simulation's pane at `cell_size`.
+fn default_grid_size(num_simulations: usize, cell_size: f32, screen_width: f32, offset_x: f32) -> (usize, usize) {
+    let (cols, rows) = pane_grid(num_simulations);
+    let half_width = ((screen_width - 2.0 * offset_x) / 2.0 - 50.0) / cell_size;
+    let pane_width = ((screen_width - 2.0 * offset_x) / cols as f32 - 50.0) / cell_size;
+    let grid_width = if cols == 1 { half_width as usize * 2 } else { pane_width as usize };
+    (grid_width, ((52.0 * 12.5) / rows as f32 / cell_size) as usize)
+}
+
+// Cell size and top-left corner of each grid. Simulations are laid out one
+// per pane, in the rows and columns given by `pane_grid`; the cell size
+// shrinks below `cell_size` if a grid would overflow its pane.
+fn grid_layout(sizes: &[(usize, usize)], cell_size: f32, screen_width: f32, screen_height: f32, offset_x: f32, offset_y: f32) -> (f32, Vec<(f32, f32)>) {
+    let (cols, rows) = pane_grid(sizes.len());
+    let pane_width = (screen_width - 2.0 * offset_x) / cols as f32;
+    let pane_height = (screen_height - offset_y - CONTROL_BAR_HEIGHT) / rows as f32 - GRID_STATS_HEIGHT;
+    let cell = sizes
+        .iter()
+        .fold(cell_size, |cell, &(width, height)| {
+            cell.min((pane_width - 50.0) / width as f32).min(pane_height / height as f32)
+        })
+        .max(1.0);
+    if let [(width, _)] = sizes {
+        return (cell, vec![((screen_width - *width as f32 * cell) / 2.0, offset_y)]);
+    }
+    let mut positions = Vec::new();
+    let mut y = offset_y;
+    for row in sizes.chunks(cols) {
+        for col in 0..row.len() {
+            positions.push((offset_x + col as f32 * pane_width, y));
+        }
+        let tallest = row.iter().map(|&(_, height)| height).max().unwrap_or(0);
+        y += tallest as f32 * cell + GRID_STATS_HEIGHT;
+    }
+    (cell, positions)
+}
+
+enum MenuRow {
+    Header(&'static str),
+    Field(usize),
+}
+
+// Fields interleaved with a header wherever the section changes.
+fn menu_rows(fields: &[ConfigField]) -> Vec<MenuRow> {
+    let mut rows = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i == 0 || fields[i - 1].section != field.section {
+            rows.push(MenuRow::Header(field.section));
+        }
+        rows.push(MenuRow::Field(i));
+    }
+    rows
+}
+
+// What the run-until prompt cycles through; plants count across species.
+const RUN_UNTIL_SPECIES: [(AgentType, &str); 5] = [
+    (AgentType::Plant, "Plants"),
+    (AgentType::Herbivore, "Herbivores"),
+    (AgentType::Carnivore, "Carnivores"),
+    (AgentType::Omnivore, "Omnivores"),
+    (AgentType::Scavenger, "Scavengers"),
+];
+
+const ANIMAL_NAMES: [&str; 4] = ["Herbivores", "Carnivores", "Omnivores", "Scavengers"];
+
+// Names of the living species, in `Population::living` order.
+fn species_names(config: &SimulationConfig) -> Vec<String> {
+    config.plant_species.iter().map(|species| format!("{} Plants", species.name)).chain(ANIMAL_NAMES.map(String::from)).collect()
+}
+
+struct ExtinctionEvent {
+    species: String,
+    iteration: usize,
+    reemerged: bool,
+}
+
+impl fmt::Display for ExtinctionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = if self.reemerged { "re-emerged" } else { "extinct" };
+        write!(f, "{} {} at iter {}", self.species, what, self.iteration)
+    }
+}
+
+// Population transitions to and from zero, in the order they happened.
+#[derive(Default)]
+struct ExtinctionLog {
+    events: Vec<ExtinctionEvent>,
+}
+
+impl ExtinctionLog {
+    fn observe(&mut self, before: &[usize], after: &[usize], config: &SimulationConfig, iteration: usize) {
+        for (species, (&old, &new)) in before.iter().zip(after).enumerate() {
+            if (old > 0) != (new > 0) {
+                self.events.push(ExtinctionEvent {
+                    species: species_names(config).swap_remove(species),
+                    iteration,
+                    reemerged: new > 0,
+                });
+            }
+        }
+    }
+
+    // Drops events from a future that is about to be replaced.
+    fn forget_after(&mut self, iteration: usize) {
+        self.events.retain(|event| event.iteration <= iteration);
+    }
+}
+
+// Highest population each species has reached and the first iteration it
+// was reached at. Stepping back doesn't lower a peak, since peaks describe
+// the run so far rather than the frame on screen.
+#[derive(Default)]
+struct PeakPopulations {
+    peaks: Vec<(usize, usize)>,
+}
+
+impl PeakPopulations {
+    fn observe(&mut self, populations: &[usize], iteration: usize) {
+        self.peaks.resize(populations.len(), (0, 0));
+        for (peak, &population) in self.peaks.iter_mut().zip(populations) {
+            if population > peak.0 {
+                *peak = (population, iteration);
+            }
+        }
+    }
 }
 
 struct SimulationInstance {
     ecosystem: Ecosystem,
-    history: Vec<Ecosystem>,
+    history: Vec<(Ecosystem, SimulationStats)>,
+    /// Living counts of each `history` frame, in `Population::living`
+    /// order, so the chart doesn't recount every frame's agents each time
+    /// it is drawn.
+    history_populations: Vec<Vec<usize>>,
     current_index: usize,
     stats: SimulationStats,
     selected: bool,
+    paused: bool,
+    last_snapshot: Option<String>,
+    extinctions: ExtinctionLog,
+    peaks: PeakPopulations,
+    previous_stats: SimulationStats,
+    /// When false, steps aren't kept in `history`, so frames can't be
+    /// stepped back through; the chart reads `recent_populations` instead.
+    record_history: bool,
+    recent_populations: VecDeque<Vec<usize>>,
+    /// Recent positions of each animal, by id, oldest first. None while
+    /// trails are hidden, so they cost nothing then.
+    trails: Option<HashMap<u32, VecDeque<(usize, usize)>>>,
+    /// Wall-clock time of the last `advance`, shown by the performance
+    /// overlay.
+    last_advance_time: Duration,
+    pause_on_extinction: bool,
+    /// Extinctions seen since the main loop last checked, as display text.
+    new_extinctions: Vec<String>,
 }
 
 impl SimulationInstance {
     fn new(config: SimulationConfig) -> Self {
+        let pause_on_extinction = config.pause_on_extinction;
         let ecosystem = Ecosystem::new_custom(config);
-        let mut history = Vec::new();
-        history.push(ecosystem.clone());
+        let stats = SimulationStats::default();
+        let history = vec![(ecosystem.clone(), stats.clone())];
+        let population = ecosystem.population().living();
+        let mut peaks = PeakPopulations::default();
+        peaks.observe(&population, 0);
         Self {
             ecosystem,
             history,
+            history_populations: vec![population],
             current_index: 0,
-            stats: SimulationStats::default(),
+            stats,
             selected: true,
+            paused: true,
+            last_snapshot: None,
+            extinctions: ExtinctionLog::default(),
+            peaks,
+            previous_stats: SimulationStats::default(),
+            record_history: true,
+            recent_populations: VecDeque::new(),
+            trails: None,
+            last_advance_time: Duration::ZERO,
+            pause_on_extinction,
+            new_extinctions: Vec::new(),
+        }
+    }
+
+    fn set_show_trails(&mut self, show: bool) {
+        self.trails = show.then(HashMap::new);
+        self.update_trails();
+    }
+
+    // Adds each animal's current position to its trail and drops the
+    // trails of animals that are gone.
+    fn update_trails(&mut self) {
+        let Some(trails) = &mut self.trails else { return };
+        let length = self.ecosystem.config.trail_length.max(1);
+        let eco = &self.ecosystem;
+        let mut updated = HashMap::with_capacity(trails.len());
+        for animal in eco.herbivores.iter().chain(&eco.carnivores).chain(&eco.omnivores).chain(&eco.scavengers) {
+            let mut trail = trails.remove(&animal.id).unwrap_or_default();
+            if trail.back() != Some(&(animal.x, animal.y)) {
+                trail.push_back((animal.x, animal.y));
+            }
+            while trail.len() > length {
+                trail.pop_front();
+            }
+            updated.insert(animal.id, trail);
+        }
+        *trails = updated;
+    }
+
+    fn clear_trails(&mut self) {
+        if let Some(trails) = &mut self.trails {
+            trails.clear();
         }
+        self.update_trails();
     }
 
-    fn advance(&mut self) {
+    // Turning history off drops every stored frame; turning it back on
+    // starts a new history at the current frame.
+    fn set_record_history(&mut self, record: bool) {
+        self.record_history = record;
+        self.truncate_history(0);
+        self.current_index = 0;
+        self.recent_populations.clear();
+        if record {
+            self.push_frame(self.ecosystem.clone());
+        }
+    }
+
+    fn push_frame(&mut self, ecosystem: Ecosystem) {
+        self.history_populations.push(ecosystem.population().living());
+        self.history.push((ecosystem, self.stats.clone()));
+    }
+
+    fn truncate_history(&mut self, len: usize) {
+        self.history.truncate(len);
+        self.history_populations.truncate(len);
+    }
+
+    fn step(&mut self) {
+        self.previous_stats = self.stats.clone();
+        let before = self.ecosystem.population().living();
         self.ecosystem.step(&mut self.stats);
-        self.history.push(self.ecosystem.clone());
+        let after = self.ecosystem.population().living();
+        let logged = self.extinctions.events.len();
+        self.extinctions.observe(&before, &after, &self.ecosystem.config, self.ecosystem.iteration_count);
+        self.peaks.observe(&after, self.ecosystem.iteration_count);
+        let died_out = self.extinctions.events[logged..].iter().filter(|event| !event.reemerged);
+        self.new_extinctions.extend(died_out.map(|event| event.to_string()));
+        self.update_trails();
+        if !self.record_history {
+            if self.recent_populations.len() == CHART_WINDOW {
+                self.recent_populations.pop_front();
+            }
+            self.recent_populations.push_back(after);
+        }
+    }
+
+    // Returns true when a new step was simulated rather than replayed.
+    fn advance(&mut self) -> bool {
+        let start = Instant::now();
+        let stepped = self.advance_untimed();
+        self.last_advance_time = start.elapsed();
+        stepped
+    }
+
+    fn advance_untimed(&mut self) -> bool {
+        if !self.record_history {
+            self.step();
+            return true;
+        }
         self.current_index += 1;
+        if let Some((ecosystem, stats)) = self.history.get(self.current_index) {
+            self.ecosystem = ecosystem.clone();
+            self.stats = stats.clone();
+            self.previous_stats = self.history[self.current_index - 1].1.clone();
+            self.update_trails();
+            false
+        } else {
+            self.step();
+            self.push_frame(self.ecosystem.clone());
+            true
+        }
     }
 
     fn go_back(&mut self) {
-        if self.current_index > 0 {
+        if self.record_history && self.current_index > 0 {
             self.current_index -= 1;
-            self.ecosystem = self.history[self.current_index].clone();
+            let (ecosystem, stats) = &self.history[self.current_index];
+            self.ecosystem = ecosystem.clone();
+            self.stats = stats.clone();
+            self.previous_stats = match self.current_index {
+                0 => stats.clone(),
+                index => self.history[index - 1].1.clone(),
+            };
+            self.clear_trails();
+        }
+    }
+
+    // Steps without adding a history frame. The caller must call `record`
+    // before the history is used again.
+    fn advance_unrecorded(&mut self) {
+        self.truncate_history(self.current_index + 1);
+        self.extinctions.forget_after(self.ecosystem.iteration_count);
+        self.step();
+    }
+
+    fn record(&mut self) {
+        if !self.record_history || self.history[self.current_index].0.iteration_count == self.ecosystem.iteration_count {
+            return;
+        }
+        self.truncate_history(self.current_index + 1);
+        self.push_frame(self.ecosystem.clone());
+        self.current_index += 1;
+    }
+
+    fn restore(&mut self, ecosystem: Ecosystem) {
+        self.extinctions.forget_after(ecosystem.iteration_count);
+        if self.record_history {
+            self.truncate_history(self.current_index + 1);
+            self.push_frame(ecosystem.clone());
+            self.current_index += 1;
         }
+        self.peaks.observe(&ecosystem.population().living(), ecosystem.iteration_count);
+        self.ecosystem = ecosystem;
+        self.previous_stats = self.stats.clone();
+        self.clear_trails();
+    }
+
+    // Births and deaths since the previous frame.
+    fn iteration_delta(&self) -> IterationDelta {
+        self.stats.delta_since(&self.previous_stats, self.ecosystem.config.plant_species.len())
     }
 
     fn iteration_count(&self) -> usize {
@@ -77,9 +861,577 @@ impl SimulationInstance {
     }
 }
 
+// Each cause an animal species died of with its count, most common first.
+fn format_death_causes(causes: &HashMap<String, usize>) -> String {
+    if causes.is_empty() {
+        return "No deaths yet".to_string();
+    }
+    let mut causes: Vec<(&String, &usize)> = causes.iter().collect();
+    causes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    causes.iter().map(|(cause, count)| format!("{}: {}", cause, count)).collect::<Vec<_>>().join(", ")
+}
+
+// One species' deaths by age, a bar per `LIFESPAN_BUCKET` iterations,
+// scaled to the fullest bucket.
+fn draw_lifespan_histogram(name: &str, buckets: &[usize], x: f32, y: f32, width: f32, height: f32, color: Color) {
+    let deaths: usize = buckets.iter().sum();
+    draw_text(&format!("{} ({} deaths)", name, deaths), x, y, 20.0, color);
+    let top = y + 10.0;
+    draw_rectangle_lines(x, top, width, height, 1.0, GRAY);
+    let tallest = buckets.iter().copied().max().unwrap_or(0);
+    if tallest == 0 {
+        draw_text("No deaths yet", x + 10.0, top + height / 2.0, 18.0, LIGHTGRAY);
+        return;
+    }
+    let bar_w = width / buckets.len() as f32;
+    for (i, &count) in buckets.iter().enumerate() {
+        let bar_h = height * count as f32 / tallest as f32;
+        draw_rectangle(x + i as f32 * bar_w, top + height - bar_h, (bar_w - 1.0).max(1.0), bar_h, color);
+    }
+    draw_text(&tallest.to_string(), x + 4.0, top + 16.0, 16.0, LIGHTGRAY);
+    draw_text("Age 0", x, top + height + 18.0, 16.0, LIGHTGRAY);
+    let oldest = format!("{}", buckets.len() * LIFESPAN_BUCKET);
+    draw_text(&oldest, x + width - measure_text(&oldest, None, 16, 1.0).width, top + height + 18.0, 16.0, LIGHTGRAY);
+}
+
+fn draw_performance(simulations: &[SimulationInstance], x: f32, y: f32) {
+    let height = 60.0 + simulations.len() as f32 * 18.0;
+    draw_rectangle(x, y, 300.0, height, Color::new(0.0, 0.0, 0.0, 0.8));
+    draw_rectangle_lines(x, y, 300.0, height, 1.0, WHITE);
+    draw_text(&format!("FPS: {}", get_fps()), x + 10.0, y + 20.0, 18.0, WHITE);
+    let agents: usize = simulations.iter().map(|sim| sim.ecosystem.population().living().iter().sum::<usize>()).sum();
+    draw_text(&format!("Live agents: {}", agents), x + 10.0, y + 40.0, 18.0, WHITE);
+    for (idx, sim) in simulations.iter().enumerate() {
+        let millis = sim.last_advance_time.as_secs_f64() * 1000.0;
+        draw_text(&format!("Sim {} last advance: {:.2} ms", idx + 1, millis), x + 10.0, y + 60.0 + idx as f32 * 18.0, 18.0, LIGHTGRAY);
+    }
+}
+
+// Fading squares on the cells each animal recently left, in its species'
+// colour, more opaque the more recent.
+fn draw_trails(eco: &Ecosystem, trails: &HashMap<u32, VecDeque<(usize, usize)>>, grid_x: f32, grid_y: f32, view_cell: f32, colors: &ColorScheme) {
+    let species = [
+        (&eco.herbivores, colors.herbivore),
+        (&eco.carnivores, colors.carnivore),
+        (&eco.omnivores, colors.omnivore),
+        (&eco.scavengers, colors.scavenger),
+    ];
+    for (animals, color) in species {
+        for animal in animals.iter() {
+            let Some(trail) = trails.get(&animal.id) else { continue };
+            let past = trail.len().saturating_sub(1);
+            for (age, &(x, y)) in trail.iter().take(past).enumerate() {
+                let alpha = 0.15 + 0.45 * (age + 1) as f32 / past as f32;
+                draw_rectangle(
+                    grid_x + (x as f32 + 0.25) * view_cell,
+                    grid_y + (y as f32 + 0.25) * view_cell,
+                    view_cell * 0.5,
+                    view_cell * 0.5,
+                    Color::new(color.r, color.g, color.b, alpha),
+                );
+            }
+        }
+    }
+}
+
+// One line per row and column, so the cost doesn't grow with the cell
+// count, plus row and column numbers every few cells.
+fn draw_grid_lines(width: usize, height: usize, grid_x: f32, grid_y: f32, view_cell: f32) {
+    let grid_w = width as f32 * view_cell;
+    let grid_h = height as f32 * view_cell;
+    for x in 0..=width {
+        let line_x = grid_x + x as f32 * view_cell - 0.5;
+        draw_line(line_x, grid_y, line_x, grid_y + grid_h, 1.0, GRID_LINE_COLOR);
+    }
+    for y in 0..=height {
+        let line_y = grid_y + y as f32 * view_cell - 0.5;
+        draw_line(grid_x, line_y, grid_x + grid_w, line_y, 1.0, GRID_LINE_COLOR);
+    }
+    for x in (0..width).step_by(GRID_LABEL_EVERY) {
+        draw_text(&x.to_string(), grid_x + x as f32 * view_cell, grid_y - 8.0, 12.0, LIGHTGRAY);
+    }
+    for y in (0..height).step_by(GRID_LABEL_EVERY) {
+        draw_text(&y.to_string(), grid_x - 26.0, grid_y + y as f32 * view_cell + view_cell.min(12.0), 12.0, LIGHTGRAY);
+    }
+}
+
+fn cell_agent(eco: &Ecosystem, x: usize, y: usize) -> Option<&'static AgentType> {
+    CELL_PRECEDENCE.iter().find(|agent_type| eco.occupancy.has(agent_type, x, y))
+}
+
+fn cell_color(eco: &Ecosystem, x: usize, y: usize, colors: &ColorScheme) -> Color {
+    match cell_agent(eco, x, y) {
+        Some(AgentType::Plant) => colors.plant(eco.occupancy.plant_species_at(x, y).unwrap_or(0)),
+        Some(agent_type) => colors.agent(agent_type),
+        None if eco.is_night() => colors.night_ground,
+        None => colors.ground,
+    }
+}
+
+struct MinimapLayout {
+    cell: f32,
+    tiles: Vec<(f32, f32)>,
+    width: f32,
+    height: f32,
+}
+
+// One tile per simulation, arranged like the grids, in a box whose
+// bottom-left corner is at (x, bottom).
+fn minimap_layout(sizes: &[(usize, usize)], x: f32, bottom: f32) -> MinimapLayout {
+    let (cols, _) = pane_grid(sizes.len());
+    let pane_width = MINIMAP_WIDTH / cols as f32;
+    let cell = sizes.iter().fold(f32::MAX, |cell, &(width, height)| {
+        cell.min((pane_width - MINIMAP_GAP) / width as f32).min(MINIMAP_PANE_HEIGHT / height as f32)
+    });
+    let mut tiles = Vec::new();
+    let mut y = MINIMAP_GAP;
+    for row in sizes.chunks(cols) {
+        for col in 0..row.len() {
+            tiles.push((MINIMAP_GAP + col as f32 * pane_width, y));
+        }
+        let tallest = row.iter().map(|&(_, height)| height).max().unwrap_or(0);
+        y += tallest as f32 * cell + MINIMAP_GAP;
+    }
+    let top = bottom - y;
+    let tiles = tiles.into_iter().map(|(tile_x, tile_y)| (x + tile_x, top + tile_y)).collect();
+    MinimapLayout { cell, tiles, width: MINIMAP_WIDTH + MINIMAP_GAP, height: y }
+}
+
+// Each simulation's cells as one pixel each, uploaded into a texture per
+// simulation, so the minimap costs one draw call per grid.
+fn refresh_minimap(textures: &mut Vec<Texture2D>, simulations: &[SimulationInstance], colors: &ColorScheme) {
+    let resized = textures.len() != simulations.len()
+        || textures.iter().zip(simulations).any(|(texture, sim)| {
+            texture.width() as usize != sim.ecosystem.width || texture.height() as usize != sim.ecosystem.height
+        });
+    if resized {
+        for texture in textures.drain(..) {
+            texture.delete();
+        }
+        for sim in simulations {
+            let texture = Texture2D::from_image(&Image::gen_image_color(sim.ecosystem.width as u16, sim.ecosystem.height as u16, BLACK));
+            texture.set_filter(FilterMode::Nearest);
+            textures.push(texture);
+        }
+    }
+    for (texture, sim) in textures.iter().zip(simulations) {
+        let eco = &sim.ecosystem;
+        let mut image = Image::gen_image_color(eco.width as u16, eco.height as u16, BLACK);
+        for y in 0..eco.height {
+            for x in 0..eco.width {
+                image.set_pixel(x as u32, y as u32, cell_color(eco, x, y, colors));
+            }
+        }
+        texture.update(&image);
+    }
+}
+
+// `viewport` is the focused simulation's visible part, in cells.
+fn draw_minimap(textures: &[Texture2D], minimap: &MinimapLayout, x: f32, focus: usize, viewport: Rect) {
+    let top = minimap.tiles.first().map_or(0.0, |&(_, y)| y - MINIMAP_GAP);
+    draw_rectangle(x, top, minimap.width, minimap.height, Color::new(0.0, 0.0, 0.0, 0.8));
+    draw_rectangle_lines(x, top, minimap.width, minimap.height, 1.0, WHITE);
+    for (texture, &(tile_x, tile_y)) in textures.iter().zip(&minimap.tiles) {
+        let size = vec2(texture.width() * minimap.cell, texture.height() * minimap.cell);
+        draw_texture_ex(*texture, tile_x, tile_y, WHITE, DrawTextureParams { dest_size: Some(size), ..Default::default() });
+    }
+    if viewport.w > 0.0 && viewport.h > 0.0 {
+        let (tile_x, tile_y) = minimap.tiles[focus];
+        draw_rectangle_lines(
+            tile_x + viewport.x * minimap.cell,
+            tile_y + viewport.y * minimap.cell,
+            viewport.w * minimap.cell,
+            viewport.h * minimap.cell,
+            1.5,
+            YELLOW,
+        );
+    }
+}
+
+const LEGEND: [(AgentType, &str); 8] = [
+    (AgentType::Herbivore, "Herbivore"),
+    (AgentType::Carnivore, "Carnivore"),
+    (AgentType::Omnivore, "Omnivore"),
+    (AgentType::Scavenger, "Scavenger"),
+    (AgentType::Corpse, "Corpse"),
+    (AgentType::Water, "Water"),
+    (AgentType::Tree, "Tree"),
+    (AgentType::Rock, "Rock"),
+];
+
+// Swatches for every cell colour, drawn in the left margin beside the grids,
+// with a row for each of `plant_species`.
+fn draw_legend(x: f32, y: f32, colors: &ColorScheme, glyphs: bool, plant_species: &[PlantSpecies]) {
+    let swatch = 12.0;
+    let mut rows: Vec<_> = plant_species
+        .iter()
+        .enumerate()
+        .map(|(species, plant)| (colors.plant(species), format!("{} Plant", plant.name), Some((&AgentType::Plant, species))))
+        .collect();
+    rows.extend(LEGEND.iter().map(|(agent_type, name)| (colors.agent(agent_type), name.to_string(), Some((agent_type, 0)))));
+    rows.push((colors.infected, "Infected".to_string(), None));
+    rows.push((colors.ground, "Empty".to_string(), None));
+    rows.push((colors.night_ground, "Night".to_string(), None));
+    let width = rows.iter().map(|(_, name, _)| measure_text(name, None, 14, 1.0).width + 28.0).fold(88.0, f32::max);
+    draw_rectangle(x, y, width, rows.len() as f32 * 18.0 + 8.0, Color::new(0.0, 0.0, 0.0, 0.8));
+    for (row, (color, name, agent)) in rows.into_iter().enumerate() {
+        let row_y = y + 6.0 + row as f32 * 18.0;
+        draw_rectangle(x + 4.0, row_y, swatch, swatch, color);
+        if let Some((agent_type, species)) = agent.filter(|_| glyphs) {
+            draw_glyph(agent_type, species, x + 4.0, row_y, swatch);
+        }
+        draw_text(&name, x + 20.0, row_y + 11.0, 14.0, WHITE);
+    }
+}
+
+// Shape drawn over a cell so species can be told apart without colour:
+// a dot for herbivores, a ring for carnivores, a cross for omnivores, a
+// triangle for scavengers, a small square for the second plant species and
+// a diamond for any later one. `species` is only used for plants.
+fn draw_glyph(agent_type: &AgentType, species: usize, x: f32, y: f32, size: f32) {
+    let (cx, cy, r) = (x + size / 2.0, y + size / 2.0, size * 0.25);
+    let ink = Color::new(0.0, 0.0, 0.0, 0.7);
+    match agent_type {
+        AgentType::Herbivore => draw_circle(cx, cy, r, ink),
+        AgentType::Carnivore => draw_circle_lines(cx, cy, r, 1.5, ink),
+        AgentType::Omnivore => {
+            draw_line(cx - r, cy - r, cx + r, cy + r, 1.5, ink);
+            draw_line(cx - r, cy + r, cx + r, cy - r, 1.5, ink);
+        },
+        AgentType::Scavenger => draw_triangle_lines(vec2(cx, cy - r), vec2(cx - r, cy + r), vec2(cx + r, cy + r), 1.5, ink),
+        AgentType::Plant if species == 1 => draw_rectangle_lines(cx - r, cy - r, r * 2.0, r * 2.0, 1.5, ink),
+        AgentType::Plant if species > 1 => {
+            let corners = [vec2(cx, cy - r), vec2(cx + r, cy), vec2(cx, cy + r), vec2(cx - r, cy)];
+            for (i, corner) in corners.iter().enumerate() {
+                let next = corners[(i + 1) % corners.len()];
+                draw_line(corner.x, corner.y, next.x, next.y, 1.5, ink);
+            }
+        },
+        _ => {},
+    }
+}
+
+fn format_average_energy(eco: &Ecosystem, agent_type: &AgentType) -> String {
+    eco.average_energy(agent_type).map_or("n/a".to_string(), |energy| format!("{:.1}", energy))
+}
+
+// Rows for each of `plant_species`, then the counters every simulation has,
+// then each animal species' death causes and lifespan buckets.
+fn stat_rows(stats: &SimulationStats, plant_species: &[PlantSpecies]) -> Vec<(String, usize)> {
+    let mut rows = Vec::new();
+    for (species, plant) in plant_species.iter().enumerate() {
+        rows.push((format!("{} Plant Births", plant.name), stats.plant_births_of(species)));
+        rows.push((format!("{} Plant Deaths", plant.name), stats.plant_deaths_of(species)));
+    }
+    let counters = [
+        ("Herbivore Births", stats.herbivore_births),
+        ("Herbivore Deaths", stats.herbivore_deaths),
+        ("Herbivore Consumptions", stats.herbivore_consumptions),
+        ("Carnivore Births", stats.carnivore_births),
+        ("Carnivore Deaths", stats.carnivore_deaths),
+        ("Carnivore Consumptions (H)", stats.carnivore_consumptions),
+        ("Carnivore Consumptions (O)", stats.carnivore_consumptions_omnivores),
+        ("Omnivore Births", stats.omnivore_births),
+        ("Omnivore Deaths", stats.omnivore_deaths),
+        ("Omnivore Consumptions (P)", stats.omnivore_consumptions_plants),
+        ("Omnivore Consumptions (H)", stats.omnivore_consumptions_herbivores),
+        ("Scavenger Births", stats.scavenger_births),
+        ("Scavenger Deaths", stats.scavenger_deaths),
+        ("Scavenger Consumptions", stats.scavenger_consumptions),
+        ("Water Births", stats.water_births),
+        ("Water Deaths", stats.water_deaths),
+        ("Tree Births", stats.tree_births),
+        ("Tree Deaths", stats.tree_deaths),
+        ("Infections", stats.infections),
+        ("Recoveries", stats.recoveries),
+        ("Disease Deaths", stats.disease_deaths),
+        ("Rain Events", stats.rain_events),
+        ("Drought Events", stats.drought_events),
+        ("Longest Lived Age", stats.longest_lived.as_ref().map_or(0, |record| record.age)),
+    ];
+    rows.extend(counters.map(|(label, value)| (label.to_string(), value)));
+    let animals = [
+        ("Herbivore", &stats.herbivore_death_causes, &stats.herbivore_lifespans),
+        ("Carnivore", &stats.carnivore_death_causes, &stats.carnivore_lifespans),
+        ("Omnivore", &stats.omnivore_death_causes, &stats.omnivore_lifespans),
+        ("Scavenger", &stats.scavenger_death_causes, &stats.scavenger_lifespans),
+    ];
+    for (name, causes, lifespans) in animals {
+        let mut causes: Vec<(&String, &usize)> = causes.iter().collect();
+        causes.sort();
+        rows.extend(causes.into_iter().map(|(cause, &count)| (format!("{} Deaths ({})", name, cause), count)));
+        rows.extend(lifespans.iter().enumerate().map(|(i, &count)| {
+            (format!("{} Lifespans {}-{}", name, i * LIFESPAN_BUCKET, (i + 1) * LIFESPAN_BUCKET - 1), count)
+        }));
+    }
+    rows
+}
+
+// Pairs of simulations whose grids touch on screen, with the edge of the
+// first one that faces the second.
+fn neighbour_pairs(count: usize) -> Vec<(usize, usize, Edge)> {
+    let (cols, _) = pane_grid(count);
+    let across = (0..count.saturating_sub(1)).filter(|i| !(i + 1).is_multiple_of(cols)).map(|i| (i, i + 1, Edge::Right));
+    let down = (0..count.saturating_sub(cols)).map(|i| (i, i + cols, Edge::Bottom));
+    across.chain(down).collect()
+}
+
+// Moves animals across the shared edges of simulations that all just
+// stepped. Animals that find their arrival cell taken stay home.
+fn migrate(simulations: &mut [SimulationInstance], stepped: &[bool]) {
+    let mut changed = vec![false; simulations.len()];
+    for (a, b, edge) in neighbour_pairs(simulations.len()) {
+        if !stepped[a] || !stepped[b] {
+            continue;
+        }
+        for (from, to, from_edge) in [(a, b, edge), (b, a, edge.opposite())] {
+            let rate = simulations[from].ecosystem.config.migration_rate;
+            for emigrant in simulations[from].ecosystem.emigrate(from_edge, rate) {
+                let along = match from_edge {
+                    Edge::Left | Edge::Right => emigrant.y,
+                    Edge::Top | Edge::Bottom => emigrant.x,
+                };
+                if let Err(emigrant) = simulations[to].ecosystem.immigrate(emigrant, from_edge.opposite(), along) {
+                    simulations[from].ecosystem.readmit(emigrant);
+                }
+            }
+        }
+        changed[a] = true;
+        changed[b] = true;
+    }
+    for (sim, changed) in simulations.iter_mut().zip(changed) {
+        if changed && sim.record_history {
+            sim.history[sim.current_index].0 = sim.ecosystem.clone();
+            sim.history_populations[sim.current_index] = sim.ecosystem.population().living();
+        }
+    }
+}
+
+fn advance_times(sim: &mut SimulationInstance, steps: usize) -> bool {
+    let mut stepped = false;
+    for _ in 0..steps {
+        if sim.pause_on_extinction && !sim.new_extinctions.is_empty() {
+            break;
+        }
+        stepped = sim.advance();
+    }
+    stepped
+}
+
+// Advances each running simulation `steps` times and reports whether its
+// last advance was a fresh step. With more than one running, each gets its
+// own thread; every ecosystem owns its RNG, so the outcome is the same as
+// advancing them one after another.
+fn advance_each(simulations: &mut [SimulationInstance], running: &[bool], steps: usize) -> Vec<bool> {
+    if running.iter().filter(|&&running| running).count() < 2 {
+        return simulations.iter_mut().zip(running).map(|(sim, &running)| running && advance_times(sim, steps)).collect();
+    }
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = simulations
+            .iter_mut()
+            .zip(running)
+            .map(|(sim, &running)| running.then(|| scope.spawn(move || advance_times(sim, steps))))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.is_some_and(|handle| handle.join().expect("simulation thread panicked")))
+            .collect()
+    })
+}
+
+// Migration has to happen between steps, so the threads then only take
+// one step at a time.
+fn advance_running(simulations: &mut [SimulationInstance], running: &[bool], steps: usize, migration: bool) {
+    if migration {
+        for _ in 0..steps {
+            let stepped = advance_each(simulations, running, 1);
+            migrate(simulations, &stepped);
+        }
+    } else {
+        advance_each(simulations, running, steps);
+    }
+}
+
+struct Jump {
+    sims: Vec<usize>,
+    start: usize,
+    target: usize,
+    record_every: usize,
+}
+
+const JUMP_RECORD_INTERVALS: [usize; 3] = [1, 10, 100];
+const JUMP_FRAME_BUDGET: f64 = 0.03;
+
+// Condition being put together in the run-until prompt: Up/Down picks the
+// kind of condition, Left/Right the species and digits the number.
+#[derive(Default)]
+struct RunUntilPrompt {
+    kind: usize,
+    species: usize,
+    number: String,
+}
+
+const RUN_UNTIL_KINDS: usize = 3;
+// Steps after which a run gives up on a condition that never comes true.
+const RUN_UNTIL_MAX_STEPS: usize = 100_000;
+
+impl RunUntilPrompt {
+    fn text(&self) -> String {
+        let species = RUN_UNTIL_SPECIES[self.species].1;
+        match self.kind {
+            0 => format!("iteration {}_", self.number),
+            1 => format!("{} < {}_", species, self.number),
+            _ => format!("{} extinct", species),
+        }
+    }
+
+    // None while a needed number is still missing.
+    fn condition(&self) -> Option<RunUntil> {
+        let species = RUN_UNTIL_SPECIES[self.species].0.clone();
+        match self.kind {
+            0 => self.number.parse().ok().map(RunUntil::Iteration),
+            1 => self.number.parse().ok().map(|count| RunUntil::SpeciesBelow(species, count)),
+            _ => Some(RunUntil::Extinct(species)),
+        }
+    }
+}
+
+struct RunToCondition {
+    sims: Vec<usize>,
+    steps: Vec<usize>,
+    condition: RunUntil,
+}
+
+struct GifRecording {
+    sim_index: usize,
+    cell_pixels: u32,
+    last_iteration: usize,
+    frames: Vec<image::RgbaImage>,
+}
+
+impl GifRecording {
+    fn capture(&mut self, sim: &SimulationInstance, colors: &ColorScheme) {
+        if !self.frames.is_empty() && sim.iteration_count() == self.last_iteration {
+            return;
+        }
+        let eco = &sim.ecosystem;
+        let px = self.cell_pixels;
+        let mut frame = image::RgbaImage::new(eco.width as u32 * px, eco.height as u32 * px);
+        for y in 0..eco.height {
+            for x in 0..eco.width {
+                let color: [u8; 4] = cell_color(eco, x, y, colors).into();
+                for dy in 0..px {
+                    for dx in 0..px {
+                        frame.put_pixel(x as u32 * px + dx, y as u32 * px + dy, image::Rgba(color));
+                    }
+                }
+            }
+        }
+        self.frames.push(frame);
+        self.last_iteration = sim.iteration_count();
+    }
+
+    fn write(self, path: &str, frame_rate: u32) -> Result<usize, image::ImageError> {
+        use image::codecs::gif::{GifEncoder, Repeat};
+        let count = self.frames.len();
+        let file = std::fs::File::create(path)?;
+        let mut encoder = GifEncoder::new_with_speed(file, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = image::Delay::from_numer_denom_ms(1000, frame_rate.max(1));
+        encoder.encode_frames(self.frames.into_iter().map(|frame| image::Frame::from_parts(frame, 0, 0, delay)))?;
+        Ok(count)
+    }
+}
+
+fn finish_recording(recording: GifRecording, frame_rate: u32) -> String {
+    match recording.write("run.gif", frame_rate) {
+        Ok(count) => format!("Recorded {} frames to run.gif", count),
+        Err(err) => format!("Writing run.gif failed: {}", err),
+    }
+}
+
+// Number of animals within `radius` (Chebyshev) of each cell, computed with
+// a summed-area table so the cost doesn't depend on the radius.
+fn animal_density(eco: &Ecosystem, radius: usize) -> Vec<u32> {
+    let (width, height) = (eco.width, eco.height);
+    let mut sums = vec![0u32; (width + 1) * (height + 1)];
+    for y in 0..height {
+        for x in 0..width {
+            let animals = eco.occupancy.count(&AgentType::Herbivore, x, y)
+                + eco.occupancy.count(&AgentType::Carnivore, x, y)
+                + eco.occupancy.count(&AgentType::Omnivore, x, y)
+                + eco.occupancy.count(&AgentType::Scavenger, x, y);
+            sums[(y + 1) * (width + 1) + x + 1] = animals
+                + sums[y * (width + 1) + x + 1]
+                + sums[(y + 1) * (width + 1) + x]
+                - sums[y * (width + 1) + x];
+        }
+    }
+    let mut density = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (x0, y0) = (x.saturating_sub(radius), y.saturating_sub(radius));
+            let (x1, y1) = ((x + radius + 1).min(width), (y + radius + 1).min(height));
+            density.push(
+                sums[y1 * (width + 1) + x1] + sums[y0 * (width + 1) + x0]
+                    - sums[y0 * (width + 1) + x1]
+                    - sums[y1 * (width + 1) + x0],
+            );
+        }
+    }
+    density
+}
+
+fn heat_color(value: u32, max: u32) -> Color {
+    let t = value as f32 / max.max(1) as f32;
+    Color::new(t, 0.2 * (1.0 - t), 1.0 - t, 1.0)
+}
+
+// Saves the framebuffer as drawn so far this frame. Uses the image's own
+// size, which is the real framebuffer resolution in fullscreen too.
+fn save_screenshot(path: &str) -> Result<(), image::ImageError> {
+    let screen = get_screen_data();
+    let (width, height) = (screen.width as usize, screen.height as usize);
+    let mut bytes = Vec::with_capacity(screen.bytes.len());
+    // OpenGL rows are stored bottom-up.
+    for row in screen.bytes.chunks_exact(width * 4).rev() {
+        bytes.extend_from_slice(row);
+    }
+    image::save_buffer(path, &bytes, width as u32, height as u32, image::ColorType::Rgba8)
+}
+
+fn draw_population_chart(sim: &SimulationInstance, x: f32, y: f32, width: f32, height: f32, colors: &ColorScheme) {
+    let samples: Vec<Vec<usize>> = if sim.record_history {
+        let start = sim.current_index.saturating_sub(CHART_WINDOW - 1);
+        sim.history_populations[start..=sim.current_index].to_vec()
+    } else {
+        sim.recent_populations.iter().cloned().collect()
+    };
+    let max = samples.iter().flatten().copied().max().unwrap_or(0).max(1);
+
+    draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.8));
+    draw_rectangle_lines(x, y, width, height, 1.0, WHITE);
+    draw_text(&max.to_string(), x + 4.0, y + 14.0, 14.0, WHITE);
+
+    if samples.len() < 2 {
+        return;
+    }
+    let step_x = width / (samples.len() - 1) as f32;
+    let to_y = |count: usize| y + height - count as f32 / max as f32 * height;
+    for (species, color) in colors.species(sim.ecosystem.config.plant_species.len()).iter().enumerate() {
+        for (i, pair) in samples.windows(2).enumerate() {
+            let x0 = x + i as f32 * step_x;
+            draw_line(x0, to_y(pair[0][species]), x0 + step_x, to_y(pair[1][species]), 1.5, *color);
+        }
+    }
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
     let mut app_state = AppState::SimulationSelector;
+    let mut color_scheme = 0;
+    let mut species_glyphs = false;
+    let mut show_legend = false;
     let mut cell_size: f32 = 12.5;
     let offset_x: f32 = 100.0;
     let offset_y: f32 = 50.0;
@@ -87,11 +1439,46 @@ async fn main() {
     let mut current_config_index = 0;
     let mut selected_field_index = 0;
     let mut configs: Vec<Vec<ConfigField>> = Vec::new();
+    let mut config_histories: Vec<ConfigHistory> = Vec::new();
+    let mut base_configs: Vec<SimulationConfig> = Vec::new();
+    let mut config_message: Option<String> = None;
+    let mut config_clipboard: Option<(usize, Vec<String>)> = None;
+    let mut sim_message: Option<String> = None;
     let mut simulations: Vec<SimulationInstance> = Vec::new();
     let mut all_selected = true;
+    let mut speed: usize = 1;
+    let mut inspected: Option<(usize, usize, usize)> = None;
+    let mut show_chart = true;
+    let mut render_mode = RenderMode::Normal;
+    let mut heatmap_radius: usize = 2;
+    let mut show_grid_lines = false;
+    let mut show_trails = false;
+    let mut show_performance = false;
+    let mut show_death_causes = false;
+    let mut alert_until = 0.0;
+    let mut recording: Option<GifRecording> = None;
+    let mut zoom: f32 = 1.0;
+    let mut camera_offset: (f32, f32) = (0.0, 0.0);
+    let mut minimap_textures: Vec<Texture2D> = Vec::new();
+    let mut last_mouse: (f32, f32) = mouse_position();
+    let mut jump_input: Option<String> = None;
+    let mut jump_record_every: usize = 1;
+    let mut jump: Option<Jump> = None;
+    let mut run_until_input: Option<RunUntilPrompt> = None;
+    let mut run_until: Option<RunToCondition> = None;
+    let mut compare_pair: (usize, usize) = (0, 1);
+    let mut compare_scroll: usize = 0;
+    let mut lifespan_sim: usize = 0;
+    let mut migration = false;
+    let mut session: Option<SessionRecorder> = None;
+    // False once migration or a restored snapshot has made the simulations
+    // differ from what their configs alone would give.
+    let mut session_replayable = true;
+    let map_file = std::env::args().skip(1).find(|arg| arg != WINDOWED_FLAG).map(PathBuf::from);
 
     loop {
         clear_background(BLACK);
+        let colors = &COLOR_SCHEMES[color_scheme];
 
         match app_state {
             AppState::SimulationSelector => {
@@ -110,7 +1497,7 @@ async fn main() {
                 draw_text("Rust.eze", center_x - 100.0, title_y + 55.0, 60.0, VIOLET);
 
                 let options_width = 500.0;
-                let options_height = 370.0;
+                let options_height = 120.0 + SIMULATION_COUNTS.len() as f32 * 60.0;
                 let options_x = center_x - options_width / 2.0;
                 let options_y = start_y;
 
@@ -118,146 +1505,60 @@ async fn main() {
                 draw_rectangle_lines(options_x, options_y, options_width, options_height, 2.0, WHITE);
                 draw_text("Select number of simulations", center_x - 180.0, options_y + 40.0, 30.0, WHITE);
 
-                let one_sim_color = if num_simulations == 1 { GREEN } else { WHITE };
-                let two_sim_color = if num_simulations == 2 { GREEN } else { WHITE };
-                let four_sim_color = if num_simulations == 4 { GREEN } else { WHITE };
-                
-                let option_y = options_y + 100.0;
                 let option_height = 50.0;
                 let option_width = 300.0;
                 let option_x = center_x - option_width / 2.0;
 
-                if num_simulations == 1 {
-                    draw_rectangle(option_x, option_y, option_width, option_height, Color::new(0.0, 0.5, 0.0, 0.3));
-                }
-                draw_rectangle_lines(option_x, option_y, option_width, option_height, 1.0, one_sim_color);
-                draw_text("1 Simulation", center_x - 75.0, option_y + 35.0, 25.0, one_sim_color);
-
-                if num_simulations == 2 {
-                    draw_rectangle(option_x, option_y + 70.0, option_width, option_height, Color::new(0.0, 0.5, 0.0, 0.3));
-                }
-                draw_rectangle_lines(option_x, option_y + 70.0, option_width, option_height, 1.0, two_sim_color);
-                draw_text("2 Simulations", center_x - 80.0, option_y + 105.0, 25.0, two_sim_color);
-
-                if num_simulations == 4 {
-                    draw_rectangle(option_x, option_y + 140.0, option_width, option_height, Color::new(0.0, 0.5, 0.0, 0.3));
+                for (i, &count) in SIMULATION_COUNTS.iter().enumerate() {
+                    let option_y = options_y + 100.0 + i as f32 * 60.0;
+                    let color = if num_simulations == count { GREEN } else { WHITE };
+                    if num_simulations == count {
+                        draw_rectangle(option_x, option_y, option_width, option_height, Color::new(0.0, 0.5, 0.0, 0.3));
+                    }
+                    draw_rectangle_lines(option_x, option_y, option_width, option_height, 1.0, color);
+                    let label = if count == 1 { "1 Simulation".to_string() } else { format!("{} Simulations", count) };
+                    draw_text(&label, center_x - 80.0, option_y + 35.0, 25.0, color);
                 }
-                draw_rectangle_lines(option_x, option_y + 140.0, option_width, option_height, 1.0, four_sim_color);
-                draw_text("4 Simulations", center_x - 80.0, option_y + 175.0, 25.0, four_sim_color);
 
                 let instructions_y = options_y + options_height + 30.0;
                 draw_text("Up/Down: Select Option", center_x - 120.0, instructions_y, 20.0, WHITE);
                 draw_text("Enter: Continue to Configuration", center_x - 160.0, instructions_y + 30.0, 20.0, WHITE);
                 draw_text("Esc: Quit", center_x - 50.0, instructions_y + 60.0, 20.0, WHITE);
 
-                if is_key_pressed(KeyCode::Up) {
-                    match num_simulations {
-                        2 => num_simulations = 1,
-                        4 => num_simulations = 2,
-                        _ => {}
-                    }
+                let option = SIMULATION_COUNTS.iter().position(|&count| count == num_simulations).unwrap_or(0);
+                if is_key_pressed(KeyCode::Up) && option > 0 {
+                    num_simulations = SIMULATION_COUNTS[option - 1];
                 }
 
-                if is_key_pressed(KeyCode::Down) {
-                    match num_simulations {
-                        1 => num_simulations = 2,
-                        2 => num_simulations = 4,
-                        _ => {}
-                    }
+                if is_key_pressed(KeyCode::Down) && option < SIMULATION_COUNTS.len() - 1 {
+                    num_simulations = SIMULATION_COUNTS[option + 1];
                 }
 
                 if is_key_pressed(KeyCode::Enter) {
                     cell_size = match num_simulations {
                         1 => 12.5,
                         2 => 12.5 * 0.75, // Réduction d'un quart
-                        4 => 12.5 * 0.66, // Réduction d'un tiers
-                        _ => 12.5,
+                        3 | 4 => 12.5 * 0.66, // Réduction d'un tiers
+                        _ => 12.5 * 0.5,
                     };
                     
                     configs.clear();
+                    base_configs.clear();
+                    config_message = None;
                     
-                    let default_configs = match num_simulations {
-                        1 => vec![
-                            SimulationConfig::default(),
-                        ],
-                        2 => vec![
-                            SimulationConfig::default(),
-                            {
-                                let mut config = SimulationConfig::default();
-                                config.initial_carnivores = 0;
-                                config
-                            },
-                        ],
-                        4 => vec![
-                            SimulationConfig::default(),
-                            {
-                                let mut config = SimulationConfig::default();
-                                config.initial_omnivores = 0;
-                                config
-                            },
-                            {
-                                let mut config = SimulationConfig::default();
-                                config.initial_carnivores = 0;
-                                config
-                            },
-                            {
-                                let mut config = SimulationConfig::default();
-                                config.water_spawn_chance = 0.0;
-                                config.tree_spawn_chance = 0.0;
-                                config
-                            },
-                        ],
-                        _ => vec![SimulationConfig::default()],
-                    };
+                    let default_configs = default_configs(num_simulations);
 
+                    let (grid_width, grid_height) = default_grid_size(num_simulations, cell_size, screen_width, offset_x);
                     for config in default_configs {
-                        let fields = vec![
-                            ConfigField {
-                                label: "Initial Light Plants".to_string(),
-                                is_int: true,
-                                input: config.initial_light_plants.to_string(),
-                                color: GREEN,
-                            },
-                            ConfigField {
-                                label: "Initial Dark Plants".to_string(),
-                                is_int: true,
-                                input: config.initial_dark_plants.to_string(),
-                                color: DARK_GREEN,
-                            },
-                            ConfigField {
-                                label: "Initial Herbivores".to_string(),
-                                is_int: true,
-                                input: config.initial_herbivores.to_string(),
-                                color: PINK,
-                            },
-                            ConfigField {
-                                label: "Initial Carnivores".to_string(),
-                                is_int: true,
-                                input: config.initial_carnivores.to_string(),
-                                color: RED,
-                            },
-                            ConfigField {
-                                label: "Initial Omnivores".to_string(),
-                                is_int: true,
-                                input: config.initial_omnivores.to_string(),
-                                color: ORANGE,
-                            },
-                            ConfigField {
-                                label: "Lakes Spawn Chance".to_string(),
-                                is_int: false,
-                                input: config.water_spawn_chance.to_string(),
-                                color: BLUE,
-                            },
-                            ConfigField {
-                                label: "Trees Spawn Chance".to_string(),
-                                is_int: false,
-                                input: config.tree_spawn_chance.to_string(),
-                                color: BROWN,
-                            },
-                        ];
-                        configs.push(fields);
+                        let config = SimulationConfig { grid_width, grid_height, ..config };
+                        configs.push(config_fields(&config));
+                        base_configs.push(SimulationConfig {
+                            from_map_file: map_file.clone(),
+                            ..config
+                        });
                     }
 
+                    config_histories = configs.iter().map(|fields| ConfigHistory::new(fields)).collect();
                     current_config_index = 0;
                     selected_field_index = 0;
                     app_state = AppState::ConfigMenu;
@@ -275,44 +1576,103 @@ async fn main() {
                 draw_text("Rust.eze", start_x, y, 50.0, VIOLET);
                 y += 60.0;
                 
-                draw_text(&format!("Configuration for Simulation {}", current_config_index + 1), start_x, y, 30.0, YELLOW);
+                let base_config = &base_configs[current_config_index];
+                draw_text(&format!("Configuration for Simulation {}{}", current_config_index + 1, name_suffix(base_config)), start_x, y, 30.0, YELLOW);
                 y += 40.0;
+                if let Some(description) = &base_config.description {
+                    draw_text(description, start_x, y - 10.0, 20.0, LIGHTGRAY);
+                    y += 20.0;
+                }
                 
                 let fields = &mut configs[current_config_index];
-                for (i, field) in fields.iter().enumerate() {
-                    let font_size = if i == selected_field_index { 22.5 } else { 20.0 };
-                    let color = if i == selected_field_index { WHITE } else { field.color };
-                    draw_text(
-                        &format!("{}: {}", field.label, field.display_value()),
-                        start_x,
-                        y,
-                        font_size,
-                        color,
-                    );
+                let rows = menu_rows(fields);
+                // Keep room below the list for the instructions and messages.
+                let visible_rows = (((screen_height() - y - 390.0) / 30.0).max(3.0) as usize).min(rows.len());
+                let selected_row = rows.iter().position(|row| matches!(row, MenuRow::Field(i) if *i == selected_field_index)).unwrap_or(0);
+                let first_row = selected_row.saturating_sub(visible_rows / 2).min(rows.len() - visible_rows);
+                if first_row > 0 {
+                    draw_text("...", start_x, y - 15.0, 20.0, GRAY);
+                }
+                for row in &rows[first_row..first_row + visible_rows] {
+                    match row {
+                        MenuRow::Header(section) => {
+                            draw_text(section, start_x, y, 25.0, colors.tint(&section_tint(section)));
+                        },
+                        MenuRow::Field(i) => {
+                            let field = &fields[*i];
+                            let font_size = if *i == selected_field_index { 22.5 } else { 20.0 };
+                            let color = if *i == selected_field_index { WHITE } else { colors.tint(&field.tint) };
+                            draw_text(
+                                &format!("{}: {}", field.label, field.display_value()),
+                                start_x + 20.0,
+                                y,
+                                font_size,
+                                color,
+                            );
+                            if *i == selected_field_index {
+                                let before_cursor = format!("{}: {}", field.label, &field.input[..field.cursor()]);
+                                let caret_x = start_x + 20.0 + measure_text(&before_cursor, None, font_size as u16, 1.0).width + 1.0;
+                                draw_line(caret_x, y - font_size * 0.7, caret_x, y + 3.0, 2.0, YELLOW);
+                            }
+                        },
+                    }
                     y += 30.0;
                 }
+                if first_row + visible_rows < rows.len() {
+                    draw_text("...", start_x, y - 15.0, 20.0, GRAY);
+                }
                 
                 y += 30.0;
                 draw_text("Up/Down: Switch Field", start_x, y, 20.0, WHITE);
                 y += 30.0;
                 draw_text("Type Digits or '.' to Change Values", start_x, y, 20.0, WHITE);
                 y += 30.0;
-                draw_text("Backspace: Delete", start_x, y, 20.0, WHITE);
+                draw_text("Left/Right, Home/End: Move Cursor", start_x, y, 20.0, WHITE);
+                y += 30.0;
+                draw_text("Backspace / Delete: Delete Before / After Cursor", start_x, y, 20.0, WHITE);
                 y += 30.0;
                 
                 if current_config_index < num_simulations - 1 {
-                    draw_text("Right Arrow: Next Simulation", start_x, y, 20.0, WHITE);
+                    draw_text("Tab: Next Simulation", start_x, y, 20.0, WHITE);
                     y += 30.0;
                 }
                 
                 if current_config_index > 0 {
-                    draw_text("Left Arrow: Previous Simulation", start_x, y, 20.0, WHITE);
+                    draw_text("Shift+Tab: Previous Simulation", start_x, y, 20.0, WHITE);
                     y += 30.0;
                 }
                 
+                draw_text(&format!("S/L: Save/Load config_{}.toml", current_config_index + 1), start_x, y, 20.0, WHITE);
+                y += 30.0;
+                draw_text("R: Reset to Defaults (Shift+R: All Simulations)", start_x, y, 20.0, WHITE);
+                y += 30.0;
+                draw_text("Z: Randomize", start_x, y, 20.0, WHITE);
+                y += 30.0;
+                draw_text("Ctrl+Z / Ctrl+Y: Undo / Redo", start_x, y, 20.0, WHITE);
+                y += 30.0;
+                if num_simulations > 1 {
+                    draw_text("Y / Ctrl+V: Copy / Paste Settings", start_x, y, 20.0, WHITE);
+                    y += 30.0;
+                }
                 draw_text("Enter: Start Simulations", start_x, y, 20.0, WHITE);
                 y += 30.0;
                 draw_text("Esc: Back to Selector", start_x, y, 20.0, WHITE);
+
+                if let Some(message) = &config_message {
+                    y += 10.0;
+                    for line in message.lines() {
+                        y += 30.0;
+                        draw_text(line, start_x, y, 20.0, YELLOW);
+                    }
+                }
+                
+                let leaving_field = [KeyCode::Up, KeyCode::Down, KeyCode::Tab, KeyCode::Enter, KeyCode::S, KeyCode::Y, KeyCode::Z]
+                    .into_iter()
+                    .any(is_key_pressed);
+                if leaving_field {
+                    fields[selected_field_index].commit();
+                    config_histories[current_config_index].checkpoint(fields);
+                }
                 
                 if is_key_pressed(KeyCode::Up) && selected_field_index > 0 {
                     selected_field_index -= 1;
@@ -324,73 +1684,166 @@ async fn main() {
                 
                 let field = &mut fields[selected_field_index];
                 if let Some(ch) = get_char_pressed() {
-                    if ch.is_ascii_digit() || (ch == '.' && !field.is_int && !field.input.contains('.')) {
-                        field.input.push(ch);
-                    }
+                    field.push(ch);
                 }
                 
                 if is_key_pressed(KeyCode::Backspace) {
-                    field.input.pop();
+                    field.backspace();
                 }
                 
-                if is_key_pressed(KeyCode::Right) && current_config_index < num_simulations - 1 {
+                if is_key_pressed(KeyCode::Delete) {
+                    field.delete();
+                }
+                
+                if is_key_pressed(KeyCode::Left) {
+                    field.move_cursor(-1);
+                }
+                
+                if is_key_pressed(KeyCode::Right) {
+                    field.move_cursor(1);
+                }
+                
+                if is_key_pressed(KeyCode::Home) {
+                    field.cursor = 0;
+                }
+                
+                if is_key_pressed(KeyCode::End) {
+                    field.cursor = field.input.len();
+                }
+                
+                let shift_down = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+                if is_key_pressed(KeyCode::Tab) && !shift_down && current_config_index < num_simulations - 1 {
                     current_config_index += 1;
                     selected_field_index = 0;
                 }
                 
-                if is_key_pressed(KeyCode::Left) && current_config_index > 0 {
+                if is_key_pressed(KeyCode::Tab) && shift_down && current_config_index > 0 {
                     current_config_index -= 1;
                     selected_field_index = 0;
                 }
                 
-                if is_key_pressed(KeyCode::Enter) {
-                    simulations.clear();
-                    
-                    let screen_width = screen_width();
-                    let horizontal_spacing = (screen_width - 2.0 * offset_x) / 2.0;
-                    let grid_width = (horizontal_spacing - 50.0) / cell_size;
-                    
-                    let (grid_width, grid_height) = match num_simulations {
-                        1 => (grid_width as usize * 2, ((52.0 * 12.5) / cell_size) as usize),
-                        2 => (grid_width as usize, ((52.0 * 12.5) / cell_size) as usize),
-                        4 => (grid_width as usize, ((26.0 * 12.5) / cell_size) as usize),
-                        _ => (grid_width as usize, 52),
+                if is_key_pressed(KeyCode::S) {
+                    let path = PathBuf::from(format!("config_{}.toml", current_config_index + 1));
+                    let config = SimulationConfig {
+                        color_scheme: COLOR_SCHEMES[color_scheme].name.to_string(),
+                        species_glyphs,
+                        ..config_from_fields(&configs[current_config_index], &base_configs[current_config_index])
+                    };
+                    config_message = Some(match config.save_to_file(&path) {
+                        Ok(()) => format!("Saved {}", path.display()),
+                        Err(err) => format!("{}: {}", path.display(), err),
+                    });
+                }
+
+                if is_key_pressed(KeyCode::L) {
+                    let path = PathBuf::from(format!("config_{}.toml", current_config_index + 1));
+                    config_message = Some(match SimulationConfig::load_from_file(&path) {
+                        Ok(config) => {
+                            color_scheme = COLOR_SCHEMES.iter().position(|scheme| scheme.name == config.color_scheme).unwrap_or(0);
+                            species_glyphs = config.species_glyphs;
+                            configs[current_config_index] = config_fields(&config);
+                            config_histories[current_config_index].checkpoint(&configs[current_config_index]);
+                            base_configs[current_config_index] = config;
+                            format!("Loaded {}", path.display())
+                        },
+                        Err(err) => format!("{}: {}", path.display(), err),
+                    });
+                }
+
+                if is_key_pressed(KeyCode::R) {
+                    let (grid_width, grid_height) = default_grid_size(num_simulations, cell_size, screen_width(), offset_x);
+                    let defaults = || config_fields(&SimulationConfig { grid_width, grid_height, ..Default::default() });
+                    if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+                        for (fields, history) in configs.iter_mut().zip(&mut config_histories) {
+                            *fields = defaults();
+                            history.checkpoint(fields);
+                        }
+                        config_message = Some("Reset all simulations to defaults".to_string());
+                    } else {
+                        configs[current_config_index] = defaults();
+                        config_histories[current_config_index].checkpoint(&configs[current_config_index]);
+                        config_message = Some(format!("Reset simulation {} to defaults", current_config_index + 1));
+                    }
+                }
+
+                let ctrl_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+                if ctrl_down && (is_key_pressed(KeyCode::Z) || is_key_pressed(KeyCode::Y)) {
+                    let history = &mut config_histories[current_config_index];
+                    let fields = &mut configs[current_config_index];
+                    let (changed, done) = if is_key_pressed(KeyCode::Z) {
+                        (history.undo(fields), "Undid")
+                    } else {
+                        (history.redo(fields), "Redid")
                     };
+                    config_message = Some(match changed {
+                        Some(index) => {
+                            selected_field_index = index;
+                            format!("{} {} change", done, fields[index].label)
+                        },
+                        None => "Nothing to undo or redo".to_string(),
+                    });
+                }
+
+                if is_key_pressed(KeyCode::Z) && !ctrl_down {
+                    let seed: u64 = ::rand::random();
+                    let config = config_from_fields(&configs[current_config_index], &base_configs[current_config_index]).randomized(seed);
+                    configs[current_config_index] = config_fields(&config);
+                    config_histories[current_config_index].checkpoint(&configs[current_config_index]);
+                    config_message = Some(format!("Randomized with seed {}", seed));
+                }
+
+                if is_key_pressed(KeyCode::Y) && !ctrl_down {
+                    let inputs = configs[current_config_index].iter().map(|field| field.input.clone()).collect();
+                    config_clipboard = Some((current_config_index, inputs));
+                    config_message = Some(format!("Copied simulation {}", current_config_index + 1));
+                }
+
+                if ctrl_down && is_key_pressed(KeyCode::V) {
+                    config_message = Some(match &config_clipboard {
+                        Some((source, inputs)) => {
+                            for (field, input) in configs[current_config_index].iter_mut().zip(inputs) {
+                                field.input = input.clone();
+                            }
+                            config_histories[current_config_index].checkpoint(&configs[current_config_index]);
+                            format!("Pasted simulation {} into simulation {}", source + 1, current_config_index + 1)
+                        },
+                        None => "Nothing copied yet (Y copies the current simulation)".to_string(),
+                    });
+                }
+
+                if is_key_pressed(KeyCode::Enter) {
+                    let sim_configs: Vec<SimulationConfig> = configs
+                        .iter()
+                        .zip(&base_configs)
+                        .map(|(sim_config_fields, base_config)| config_from_fields(sim_config_fields, base_config))
+                        .collect();
+                    let problems: Vec<String> = sim_configs
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(idx, config)| config.validate().err().map(|issues| (idx, issues)))
+                        .flat_map(|(idx, issues)| issues.into_iter().map(move |issue| format!("Simulation {}: {}", idx + 1, issue)))
+                        .collect();
                     
-                    let default_config = SimulationConfig::default();
-                    for sim_config_fields in &configs {
-                        let config = SimulationConfig {
-                            grid_width,
-                            grid_height,
-                            initial_light_plants: sim_config_fields[0].input.parse().unwrap_or(default_config.initial_light_plants),
-                            initial_dark_plants: sim_config_fields[1].input.parse().unwrap_or(default_config.initial_dark_plants),
-                            initial_herbivores: sim_config_fields[2].input.parse().unwrap_or(default_config.initial_herbivores),
-                            initial_carnivores: sim_config_fields[3].input.parse().unwrap_or(default_config.initial_carnivores),
-                            initial_omnivores: sim_config_fields[4].input.parse().unwrap_or(default_config.initial_omnivores),
-                            water_spawn_chance: sim_config_fields[5].input.parse().unwrap_or(default_config.water_spawn_chance),
-                            water_lifespan: default_config.water_lifespan,
-                            tree_spawn_chance: sim_config_fields[6].input.parse().unwrap_or(default_config.tree_spawn_chance),
-                            tree_lifespan: default_config.tree_lifespan,
-                            plant_growth_rate: default_config.plant_growth_rate,
-                            herbivore_energy_gain: default_config.herbivore_energy_gain,
-                            herbivore_energy_loss: default_config.herbivore_energy_loss,
-                            herbivore_initial_energy: default_config.herbivore_initial_energy,
-                            herbivore_reproduction_threshold: default_config.herbivore_reproduction_threshold,
-                            carnivore_energy_gain: default_config.carnivore_energy_gain,
-                            carnivore_energy_loss: default_config.carnivore_energy_loss,
-                            carnivore_initial_energy: default_config.carnivore_initial_energy,
-                            carnivore_reproduction_threshold: default_config.carnivore_reproduction_threshold,
-                            omnivore_energy_gain_plants: default_config.omnivore_energy_gain_plants,
-                            omnivore_energy_gain_herbivores: default_config.omnivore_energy_gain_herbivores,
-                            omnivore_energy_loss: default_config.omnivore_energy_loss,
-                            omnivore_initial_energy: default_config.omnivore_initial_energy,
-                            omnivore_reproduction_threshold: default_config.omnivore_reproduction_threshold,
-                        };
-                        simulations.push(SimulationInstance::new(config));
+                    if problems.is_empty() {
+                        config_message = None;
+                        simulations.clear();
+                        inspected = None;
+                        recording = None;
+                        session = None;
+                        session_replayable = true;
+                        zoom = 1.0;
+                        camera_offset = (0.0, 0.0);
+                        simulations.extend(sim_configs.into_iter().map(SimulationInstance::new));
+                        for sim in &mut simulations {
+                            sim.set_show_trails(show_trails);
+                        }
+                        
+                        all_selected = true;
+                        sim_message = None;
+                        app_state = AppState::Simulation;
+                    } else {
+                        config_message = Some(problems.join("\n"));
                     }
-                    
-                    all_selected = true;
-                    app_state = AppState::Simulation;
                 }
                 
                 if is_key_pressed(KeyCode::Escape) {
@@ -400,32 +1853,212 @@ async fn main() {
             AppState::Simulation => {
                 let screen_width = screen_width();
                 let screen_height = screen_height();
-                let horizontal_spacing = (screen_width - 2.0 * offset_x) / 2.0;
-                
-                let grid_positions = match num_simulations {
-                    1 => {
-                        let grid_width_pixels = simulations[0].ecosystem.width as f32 * cell_size;
-                        vec![
-                            ((screen_width - grid_width_pixels) / 2.0, offset_y)
-                        ]
-                    },
-                    2 => vec![
-                        (offset_x, offset_y),
-                        (offset_x + horizontal_spacing, offset_y),
-                    ],
-                    4 => {
-                        let grid_height = simulations[0].ecosystem.height as f32 * cell_size;
-                        let stats_height = 40.0;
-                        let total_height = grid_height + stats_height + 20.0;
-                        vec![
-                            (offset_x, offset_y),
-                            (offset_x + horizontal_spacing, offset_y),
-                            (offset_x, offset_y + total_height + 20.0),
-                            (offset_x + horizontal_spacing, offset_y + total_height + 20.0),
-                        ]
-                    },
-                    _ => vec![(offset_x, offset_y)],
-                };
+                let sizes: Vec<(usize, usize)> = simulations.iter().map(|sim| (sim.ecosystem.width, sim.ecosystem.height)).collect();
+                let (cell_size, layout) = grid_layout(&sizes, cell_size, screen_width, screen_height, offset_x, offset_y);
+                let pane_width = (screen_width - 2.0 * offset_x) / pane_grid(simulations.len()).0 as f32;
+                
+                let mut view_cell = cell_size * zoom;
+                let (mouse_x, mouse_y) = mouse_position();
+                let wheel = mouse_wheel().1;
+                let mut zoom_step = 1.0;
+                if is_key_pressed(KeyCode::RightBracket) || wheel > 0.0 {
+                    zoom_step = 1.25;
+                } else if is_key_pressed(KeyCode::LeftBracket) || wheel < 0.0 {
+                    zoom_step = 0.8;
+                }
+                if zoom_step != 1.0 {
+                    // Keep the grid point under the mouse where it is.
+                    let new_zoom = (zoom * zoom_step).clamp(MIN_ZOOM, MAX_ZOOM);
+                    let (base_x, base_y) = layout[0];
+                    let scale = new_zoom / zoom;
+                    camera_offset.0 = mouse_x - (mouse_x - base_x - camera_offset.0) * scale - base_x;
+                    camera_offset.1 = mouse_y - (mouse_y - base_y - camera_offset.1) * scale - base_y;
+                    zoom = new_zoom;
+                    view_cell = cell_size * zoom;
+                }
+                if is_mouse_button_down(MouseButton::Right) {
+                    camera_offset.0 += mouse_x - last_mouse.0;
+                    camera_offset.1 += mouse_y - last_mouse.1;
+                }
+                last_mouse = (mouse_x, mouse_y);
+                if is_key_pressed(KeyCode::Key0) && jump_input.is_none() && run_until_input.is_none() {
+                    zoom = 1.0;
+                    view_cell = cell_size;
+                    camera_offset = (0.0, 0.0);
+                }
+                // The minimap appears once part of a grid is off screen; clicking
+                // one of its tiles centres the view on that spot.
+                let view_height = screen_height - CONTROL_BAR_HEIGHT;
+                let minimap = minimap_layout(&sizes, 6.0, view_height - 10.0);
+                let minimap_shown = sizes.iter().zip(&layout).any(|(&(width, height), &(x, y))| {
+                    let (grid_x, grid_y) = (x + camera_offset.0, y + camera_offset.1);
+                    grid_x < 0.0 || grid_y < 0.0 || grid_x + width as f32 * view_cell > screen_width || grid_y + height as f32 * view_cell > view_height
+                });
+                let mut minimap_clicked = false;
+                if minimap_shown && is_mouse_button_pressed(MouseButton::Left) {
+                    for (idx, (&(width, height), &(tile_x, tile_y))) in sizes.iter().zip(&minimap.tiles).enumerate() {
+                        let cell_x = (mouse_x - tile_x) / minimap.cell;
+                        let cell_y = (mouse_y - tile_y) / minimap.cell;
+                        if cell_x >= 0.0 && cell_y >= 0.0 && cell_x < width as f32 && cell_y < height as f32 {
+                            let (base_x, base_y) = layout[idx];
+                            camera_offset.0 = screen_width / 2.0 - base_x - cell_x * view_cell;
+                            camera_offset.1 = view_height / 2.0 - base_y - cell_y * view_cell;
+                            minimap_clicked = true;
+                        }
+                    }
+                }
+                {
+                    // Keep at least a corner of the first grid on screen.
+                    let (base_x, base_y) = layout[0];
+                    let grid_w = simulations[0].ecosystem.width as f32 * view_cell;
+                    let grid_h = simulations[0].ecosystem.height as f32 * view_cell;
+                    camera_offset.0 = camera_offset.0.clamp(VISIBLE_MARGIN - base_x - grid_w, screen_width - VISIBLE_MARGIN - base_x);
+                    camera_offset.1 = camera_offset.1.clamp(VISIBLE_MARGIN - base_y - grid_h, screen_height - VISIBLE_MARGIN - base_y);
+                }
+                let grid_positions: Vec<(f32, f32)> = layout
+                    .iter()
+                    .map(|&(x, y)| (x + camera_offset.0, y + camera_offset.1))
+                    .collect();
+                
+                let mut jump_started = false;
+                if let Some(input) = jump_input.as_mut() {
+                    while let Some(ch) = get_char_pressed() {
+                        if ch.is_ascii_digit() {
+                            input.push(ch);
+                        }
+                    }
+                    if is_key_pressed(KeyCode::Backspace) {
+                        input.pop();
+                    }
+                    if is_key_pressed(KeyCode::K) {
+                        let current = JUMP_RECORD_INTERVALS.iter().position(|&k| k == jump_record_every).unwrap_or(0);
+                        jump_record_every = JUMP_RECORD_INTERVALS[(current + 1) % JUMP_RECORD_INTERVALS.len()];
+                    }
+                    if is_key_pressed(KeyCode::Enter) {
+                        if let Ok(target) = input.parse::<usize>() {
+                            let sims: Vec<usize> = (0..simulations.len())
+                                .filter(|&i| (simulations[i].selected || all_selected) && simulations[i].iteration_count() < target)
+                                .collect();
+                            if !sims.is_empty() {
+                                let start = sims.iter().map(|&i| simulations[i].iteration_count()).min().unwrap_or(0);
+                                jump = Some(Jump { sims, start, target, record_every: jump_record_every });
+                            }
+                        }
+                        jump_input = None;
+                        jump_started = true;
+                    } else if is_key_pressed(KeyCode::Escape) {
+                        jump_input = None;
+                        jump_started = true;
+                    }
+                } else if jump.is_none() && run_until_input.is_none() && run_until.is_none() && is_key_pressed(KeyCode::J) {
+                    while get_char_pressed().is_some() {}
+                    jump_input = Some(String::new());
+                }
+                
+                if let Some(active) = &jump {
+                    let cancelled = !jump_started && is_key_pressed(KeyCode::Escape);
+                    let frame_start = get_time();
+                    let mut done = cancelled;
+                    while !done && get_time() - frame_start < JUMP_FRAME_BUDGET {
+                        done = true;
+                        for &i in &active.sims {
+                            let sim = &mut simulations[i];
+                            if sim.iteration_count() >= active.target {
+                                continue;
+                            }
+                            done = false;
+                            sim.advance_unrecorded();
+                            let iteration = sim.iteration_count();
+                            if (iteration - active.start).is_multiple_of(active.record_every) && iteration < active.target {
+                                sim.record();
+                            }
+                        }
+                    }
+                    if done {
+                        for &i in &active.sims {
+                            simulations[i].record();
+                        }
+                        sim_message = Some(if cancelled {
+                            "Jump cancelled".to_string()
+                        } else {
+                            format!("Jumped to iteration {}", active.target)
+                        });
+                        jump = None;
+                        jump_started = true;
+                    }
+                }
+
+                if let Some(prompt) = run_until_input.as_mut() {
+                    while let Some(ch) = get_char_pressed() {
+                        if ch.is_ascii_digit() {
+                            prompt.number.push(ch);
+                        }
+                    }
+                    if is_key_pressed(KeyCode::Backspace) {
+                        prompt.number.pop();
+                    }
+                    if is_key_pressed(KeyCode::Up) {
+                        prompt.kind = (prompt.kind + RUN_UNTIL_KINDS - 1) % RUN_UNTIL_KINDS;
+                    }
+                    if is_key_pressed(KeyCode::Down) {
+                        prompt.kind = (prompt.kind + 1) % RUN_UNTIL_KINDS;
+                    }
+                    if is_key_pressed(KeyCode::Left) {
+                        prompt.species = (prompt.species + RUN_UNTIL_SPECIES.len() - 1) % RUN_UNTIL_SPECIES.len();
+                    }
+                    if is_key_pressed(KeyCode::Right) {
+                        prompt.species = (prompt.species + 1) % RUN_UNTIL_SPECIES.len();
+                    }
+                    if is_key_pressed(KeyCode::Enter) {
+                        if let Some(condition) = prompt.condition() {
+                            let sims: Vec<usize> = (0..simulations.len()).filter(|&i| simulations[i].selected || all_selected).collect();
+                            run_until = Some(RunToCondition { steps: vec![0; sims.len()], sims, condition });
+                        }
+                        run_until_input = None;
+                        jump_started = true;
+                    } else if is_key_pressed(KeyCode::Escape) {
+                        run_until_input = None;
+                        jump_started = true;
+                    }
+                } else if jump_input.is_none() && jump.is_none() && run_until.is_none() && is_key_pressed(KeyCode::A) {
+                    while get_char_pressed().is_some() {}
+                    run_until_input = Some(RunUntilPrompt::default());
+                }
+
+                if let Some(active) = run_until.as_mut() {
+                    let cancelled = !jump_started && is_key_pressed(KeyCode::Escape);
+                    let frame_start = get_time();
+                    let mut done = cancelled;
+                    while !done && get_time() - frame_start < JUMP_FRAME_BUDGET {
+                        done = true;
+                        for (&i, steps) in active.sims.iter().zip(active.steps.iter_mut()) {
+                            let sim = &mut simulations[i];
+                            if *steps >= RUN_UNTIL_MAX_STEPS || active.condition.is_met(&sim.ecosystem) {
+                                continue;
+                            }
+                            done = false;
+                            sim.advance_unrecorded();
+                            *steps += 1;
+                        }
+                    }
+                    if done {
+                        let results: Vec<String> = active
+                            .sims
+                            .iter()
+                            .zip(&active.steps)
+                            .map(|(&i, &steps)| {
+                                simulations[i].record();
+                                let outcome = if active.condition.is_met(&simulations[i].ecosystem) { "ran" } else { "stopped after" };
+                                format!("sim {} {} {} steps", i + 1, outcome, steps)
+                            })
+                            .collect();
+                        let what = if cancelled { "cancelled" } else { "done" };
+                        sim_message = Some(format!("Run until {} {}: {}", active.condition, what, results.join(", ")));
+                        run_until = None;
+                        jump_started = true;
+                    }
+                }
+                let busy = jump_input.is_some() || jump.is_some() || run_until_input.is_some() || run_until.is_some() || jump_started;
                 
                 if is_key_pressed(KeyCode::Tab) {
                     if all_selected {
@@ -451,7 +2084,7 @@ async fn main() {
                     }
                 }
                 
-                if is_key_pressed(KeyCode::Right) {
+                if is_key_pressed(KeyCode::Right) && !busy {
                     for sim in &mut simulations {
                         if sim.selected || all_selected {
                             sim.advance();
@@ -459,7 +2092,7 @@ async fn main() {
                     }
                 }
                 
-                if is_key_pressed(KeyCode::Left) {
+                if is_key_pressed(KeyCode::Left) && !busy {
                     for sim in &mut simulations {
                         if sim.selected || all_selected {
                             sim.go_back();
@@ -467,18 +2100,230 @@ async fn main() {
                     }
                 }
                 
-                if is_key_down(KeyCode::Space) {
+                if is_key_pressed(KeyCode::P) {
                     for sim in &mut simulations {
                         if sim.selected || all_selected {
-                            sim.advance();
+                            sim.paused = !sim.paused;
                         }
                     }
                 }
                 
-                if is_key_pressed(KeyCode::Escape) {
+                if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+                    speed = (speed + 1).min(MAX_SPEED);
+                }
+                
+                if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+                    speed = speed.saturating_sub(1).max(1);
+                }
+                
+                if is_key_pressed(KeyCode::N) && !busy {
+                    for sim in &mut simulations {
+                        if sim.selected || all_selected {
+                            let record = !sim.record_history;
+                            sim.set_record_history(record);
+                        }
+                    }
+                }
+                
+                if is_key_pressed(KeyCode::M) {
+                    migration = !migration;
+                    sim_message = Some(if neighbour_pairs(simulations.len()).is_empty() {
+                        "Migration needs 2 or more simulations".to_string()
+                    } else {
+                        format!("Migration {}", if migration { "on" } else { "off" })
+                    });
+                }
+                
+                let space_down = is_key_down(KeyCode::Space);
+                let running: Vec<bool> = simulations
+                    .iter()
+                    .map(|sim| !busy && (!sim.paused || (space_down && (sim.selected || all_selected))))
+                    .collect();
+                if running.contains(&true) {
+                    advance_running(&mut simulations, &running, speed, migration);
+                    if migration && !neighbour_pairs(simulations.len()).is_empty() {
+                        session_replayable = false;
+                    }
+                }
+                let mut alerts = Vec::new();
+                for (idx, sim) in simulations.iter_mut().enumerate() {
+                    let extinctions = std::mem::take(&mut sim.new_extinctions);
+                    if sim.pause_on_extinction && running[idx] && !extinctions.is_empty() {
+                        sim.paused = true;
+                        alerts.push(format!("Sim {} paused: {}", idx + 1, extinctions.join(", ")));
+                    }
+                }
+                if !alerts.is_empty() {
+                    sim_message = Some(alerts.join(" | "));
+                    alert_until = get_time() + ALERT_SECONDS;
+                }
+
+                if is_key_pressed(KeyCode::X) {
+                    let selected: Vec<usize> = (0..simulations.len()).filter(|&idx| simulations[idx].selected || all_selected).collect();
+                    let enable = !selected.iter().all(|&idx| simulations[idx].pause_on_extinction);
+                    for &idx in &selected {
+                        simulations[idx].pause_on_extinction = enable;
+                    }
+                    sim_message = Some(format!("Pause on extinction {}", if enable { "on" } else { "off" }));
+                }
+
+                let stop_session = is_key_pressed(KeyCode::W) || (session.is_some() && !session_replayable);
+                if stop_session {
+                    if let Some(recorder) = session.take() {
+                        let saved = match recorder.save_to_file(Path::new(SESSION_FILE)) {
+                            Ok(()) => format!("Session saved to {}", SESSION_FILE),
+                            Err(err) => format!("{}: {}", SESSION_FILE, err),
+                        };
+                        sim_message = Some(if session_replayable { saved } else { format!("{} (stopped: migration and restored snapshots can't be replayed)", saved) });
+                    } else if session_replayable {
+                        session = Some(SessionRecorder::new(simulations.iter().map(|sim| sim.ecosystem.config.clone()).collect()));
+                        sim_message = Some("Recording session (W to stop)".to_string());
+                    } else {
+                        sim_message = Some("Sessions can't be recorded after migration or a restored snapshot".to_string());
+                    }
+                }
+                // Moves since the session stopped being replayable are left out.
+                if let Some(recorder) = &mut session {
+                    for (idx, sim) in simulations.iter().enumerate() {
+                        recorder.observe(idx, sim.iteration_count());
+                    }
+                }
+                
+                if is_key_pressed(KeyCode::E) {
+                    let mut written = Vec::new();
+                    for (idx, sim) in simulations.iter_mut().enumerate() {
+                        if sim.selected || all_selected {
+                            let path = format!("snapshot_{}_{}.json", idx + 1, sim.iteration_count());
+                            match std::fs::write(&path, sim.ecosystem.to_json()) {
+                                Ok(()) => {
+                                    written.push(path.clone());
+                                    sim.last_snapshot = Some(path);
+                                },
+                                Err(err) => written.push(format!("{} failed: {}", path, err)),
+                            }
+                        }
+                    }
+                    sim_message = Some(format!("Snapshot: {}", written.join(", ")));
+                }
+
+                if is_key_pressed(KeyCode::O) {
+                    let mut restored = Vec::new();
+                    for sim in &mut simulations {
+                        if !(sim.selected || all_selected) {
+                            continue;
+                        }
+                        let Some(path) = sim.last_snapshot.clone() else { continue };
+                        let loaded = std::fs::read_to_string(&path)
+                            .map_err(|err| err.to_string())
+                            .and_then(|text| Ecosystem::from_json(&text).map_err(|err| err.to_string()));
+                        match loaded {
+                            Ok(ecosystem) => {
+                                sim.restore(ecosystem);
+                                session_replayable = false;
+                                restored.push(path);
+                            },
+                            Err(err) => restored.push(format!("{} failed: {}", path, err)),
+                        }
+                    }
+                    sim_message = Some(format!("Restored: {}", restored.join(", ")));
+                }
+
+                if is_key_pressed(KeyCode::Escape) && !busy {
                     app_state = AppState::StatsScreen;
                 }
                 
+                if is_key_pressed(KeyCode::C) {
+                    show_chart = !show_chart;
+                }
+                
+                if is_key_pressed(KeyCode::H) {
+                    render_mode = match render_mode {
+                        RenderMode::Heatmap => RenderMode::Normal,
+                        _ => RenderMode::Heatmap,
+                    };
+                }
+                
+                if is_key_pressed(KeyCode::L) {
+                    render_mode = match render_mode {
+                        RenderMode::Elevation => RenderMode::Normal,
+                        _ => RenderMode::Elevation,
+                    };
+                    if render_mode == RenderMode::Elevation && simulations.iter().all(|s| s.ecosystem.elevation_at(0, 0).is_none()) {
+                        sim_message = Some("No elevation map: set elevation_scale in the config".to_string());
+                    }
+                }
+                
+                if is_key_pressed(KeyCode::R) {
+                    heatmap_radius = heatmap_radius % 5 + 1;
+                }
+
+                if is_key_pressed(KeyCode::B) {
+                    show_grid_lines = !show_grid_lines;
+                }
+
+                if is_key_pressed(KeyCode::I) {
+                    show_performance = !show_performance;
+                }
+
+                if is_key_pressed(KeyCode::U) {
+                    color_scheme = (color_scheme + 1) % COLOR_SCHEMES.len();
+                    sim_message = Some(format!("Colours: {}", COLOR_SCHEMES[color_scheme].name));
+                }
+
+                if is_key_pressed(KeyCode::K) && jump_input.is_none() {
+                    show_legend = !show_legend;
+                }
+
+                if is_key_pressed(KeyCode::D) {
+                    species_glyphs = !species_glyphs;
+                    sim_message = Some(format!("Species glyphs {}", if species_glyphs { "on" } else { "off" }));
+                }
+
+                if is_key_pressed(KeyCode::T) {
+                    show_trails = !show_trails;
+                    for sim in &mut simulations {
+                        sim.set_show_trails(show_trails);
+                    }
+                }
+                
+                if is_key_pressed(KeyCode::G) {
+                    if let Some(finished) = recording.take() {
+                        let frame_rate = simulations[finished.sim_index].ecosystem.config.gif_frame_rate;
+                        sim_message = Some(finish_recording(finished, frame_rate));
+                    } else {
+                        let sim_index = simulations.iter().position(|s| s.selected).unwrap_or(0);
+                        let downscale = simulations[sim_index].ecosystem.config.gif_downscale.max(1.0);
+                        recording = Some(GifRecording {
+                            sim_index,
+                            cell_pixels: ((cell_size / downscale).round() as u32).max(1),
+                            last_iteration: 0,
+                            frames: Vec::new(),
+                        });
+                        sim_message = Some(format!("Recording simulation {} (G to stop)", sim_index + 1));
+                    }
+                }
+                
+                if let Some(active) = recording.as_mut() {
+                    let sim = &simulations[active.sim_index];
+                    active.capture(sim, colors);
+                    if active.frames.len() >= sim.ecosystem.config.gif_max_frames {
+                        let frame_rate = sim.ecosystem.config.gif_frame_rate;
+                        sim_message = recording.take().map(|finished| finish_recording(finished, frame_rate));
+                    }
+                }
+                
+                if is_mouse_button_pressed(MouseButton::Left) && !minimap_clicked {
+                    inspected = simulations.iter().enumerate().find_map(|(idx, sim)| {
+                        let (grid_x, grid_y) = grid_positions[idx];
+                        let cell_x = ((mouse_x - grid_x) / view_cell).floor();
+                        let cell_y = ((mouse_y - grid_y) / view_cell).floor();
+                        let inside = cell_x >= 0.0 && cell_y >= 0.0
+                            && (cell_x as usize) < sim.ecosystem.width
+                            && (cell_y as usize) < sim.ecosystem.height;
+                        inside.then_some((idx, cell_x as usize, cell_y as usize))
+                    });
+                }
+                
                 for (idx, sim) in simulations.iter().enumerate() {
                     let (grid_x, grid_y) = grid_positions[idx];
                     let eco = &sim.ecosystem;
@@ -489,135 +2334,557 @@ async fn main() {
                     draw_rectangle_lines(
                         grid_x - 5.0,
                         grid_y - 5.0,
-                        eco.width as f32 * cell_size + 10.0,
-                        eco.height as f32 * cell_size + 10.0,
+                        eco.width as f32 * view_cell + 10.0,
+                        eco.height as f32 * view_cell + 10.0,
                         border_thickness,
                         border_color
                     );
                     
+                    let infected = eco.infected_cells();
+                    let heat = (render_mode == RenderMode::Heatmap).then(|| {
+                        let density = animal_density(eco, heatmap_radius);
+                        let max = density.iter().copied().max().unwrap_or(0);
+                        (density, max)
+                    });
                     for y in 0..eco.height {
                         for x in 0..eco.width {
-                            let mut color = LIGHTGRAY;
-                            
-                            if eco.trees.iter().any(|t| t.x == x && t.y == y) {
-                                color = BROWN;
-                            } else if eco.waters.iter().any(|w| w.x == x && w.y == y) {
-                                color = BLUE;
-                            } else if eco.carnivores.iter().any(|c| c.x == x && c.y == y) {
-                                color = RED;
-                            } else if eco.herbivores.iter().any(|h| h.x == x && h.y == y) {
-                                color = PINK;
-                            } else if eco.omnivores.iter().any(|o| o.x == x && o.y == y) {
-                                color = ORANGE;
-                            } else if eco.plants.iter().any(|p| p.x == x && p.y == y) {
-                                if eco.plants.iter().any(|p| p.x == x && p.y == y && p.agent_type == AgentType::DarkPlant) {
-                                    color = DARK_GREEN;
-                                } else {
-                                    color = GREEN;
+                            let mut color = cell_color(eco, x, y, colors);
+                            if infected[y * eco.width + x] {
+                                let tint = colors.infected;
+                                color = Color::new((color.r + tint.r) / 2.0, (color.g + tint.g) / 2.0, (color.b + tint.b) / 2.0, 1.0);
+                            }
+                            if let Some((density, max)) = &heat {
+                                color = heat_color(density[y * eco.width + x], *max);
+                            } else if render_mode == RenderMode::Elevation {
+                                if let Some(elevation) = eco.elevation_at(x, y) {
+                                    let shade = 0.35 + 0.65 * elevation as f32 / 255.0;
+                                    color = Color::new(color.r * shade, color.g * shade, color.b * shade, 1.0);
                                 }
                             }
                             
                             draw_rectangle(
-                                grid_x + x as f32 * cell_size,
-                                grid_y + y as f32 * cell_size,
-                                cell_size - 1.0,
-                                cell_size - 1.0,
+                                grid_x + x as f32 * view_cell,
+                                grid_y + y as f32 * view_cell,
+                                view_cell - 1.0,
+                                view_cell - 1.0,
                                 color
                             );
+                            // Glyphs would be unreadable on tiny cells.
+                            if species_glyphs && view_cell >= MIN_GLYPH_CELL {
+                                if let Some(agent_type) = cell_agent(eco, x, y) {
+                                    let species = eco.occupancy.plant_species_at(x, y).unwrap_or(0);
+                                    draw_glyph(agent_type, species, grid_x + x as f32 * view_cell, grid_y + y as f32 * view_cell, view_cell - 1.0);
+                                }
+                            }
                         }
                     }
+                    if let Some(trails) = &sim.trails {
+                        draw_trails(eco, trails, grid_x, grid_y, view_cell, colors);
+                    }
+                    if show_grid_lines {
+                        draw_grid_lines(eco.width, eco.height, grid_x, grid_y, view_cell);
+                    }
                     
                     let stats_x = grid_x;
-                    let stats_y = grid_y + (eco.height as f32 * cell_size) + 18.0;
+                    let stats_y = grid_y + (eco.height as f32 * view_cell) + 18.0;
                     
-                    draw_text(&format!("Sim {}: Iteration {}", idx + 1, sim.iteration_count()), stats_x, stats_y, 18.0, YELLOW);
+                    let run_state = if sim.paused { "" } else { " (Running)" };
+                    let run_state = if sim.record_history { run_state.to_string() } else { format!("{} [No Rewind]", run_state) };
+                    let season = if eco.config.season_length > 0 { format!(" - {}", eco.current_season()) } else { String::new() };
+                    let weather = if eco.config.weather_event_chance > 0.0 { format!(" - {}", eco.current_weather()) } else { String::new() };
+                    draw_text(&format!("Sim {}{}: Iteration {}{}{}{}", idx + 1, name_suffix(&eco.config), sim.iteration_count(), season, weather, run_state), stats_x, stats_y, 18.0, YELLOW);
                     
-                    let total_light_plants = eco.plants.iter().filter(|p| p.agent_type == AgentType::LightPlant).count();
-                    let total_dark_plants = eco.plants.iter().filter(|p| p.agent_type == AgentType::DarkPlant).count();
+                    let population = eco.population();
+                    let mut plants_x = stats_x;
+                    for (species, plant) in eco.config.plant_species.iter().enumerate() {
+                        let text = format!("{} Plants: {}", plant.name, population.plants[species]);
+                        draw_text(&text, plants_x, stats_y + 16.0, 15.0, colors.plant(species));
+                        plants_x += measure_text(&text, None, 15, 1.0).width.max(120.0) + 10.0;
+                    }
+                    // Narrow panes, or many plant species, put the animals
+                    // on a line of their own.
+                    let (animals_x, animals_y) = if pane_width < 660.0 || plants_x > stats_x + 270.0 {
+                        (stats_x, stats_y + 32.0)
+                    } else {
+                        (stats_x + 270.0, stats_y + 16.0)
+                    };
+                    draw_text(&format!("Herbivores: {}", population.herbivores), animals_x, animals_y, 15.0, colors.herbivore);
+                    draw_text(&format!("Carnivores: {}", population.carnivores), animals_x + 120.0, animals_y, 15.0, colors.carnivore);
+                    draw_text(&format!("Omnivores: {}", population.omnivores), animals_x + 240.0, animals_y, 15.0, colors.omnivore);
+                    if population.scavengers > 0 || eco.config.initial_scavengers > 0 {
+                        draw_text(&format!("Scavengers: {}", population.scavengers), animals_x + 350.0, animals_y, 15.0, colors.scavenger);
+                    }
+                }
+                
+                if let Some((idx, x, y)) = inspected {
+                    let (grid_x, grid_y) = grid_positions[idx];
+                    draw_rectangle_lines(grid_x + x as f32 * view_cell - 1.0, grid_y + y as f32 * view_cell - 1.0, view_cell + 1.0, view_cell + 1.0, 2.0, VIOLET);
                     
-                    draw_text(&format!("Light Plants: {}", total_light_plants), stats_x, stats_y + 16.0, 15.0, GREEN);
-                    draw_text(&format!("Dark Plants: {}", total_dark_plants), stats_x + 140.0, stats_y + 16.0, 15.0, DARK_GREEN);
-                    draw_text(&format!("Herbivores: {}", eco.herbivores.len()), stats_x + 270.0, stats_y + 16.0, 15.0, PINK);
-                    draw_text(&format!("Carnivores: {}", eco.carnivores.len()), stats_x + 390.0, stats_y + 16.0, 15.0, RED);
-                    draw_text(&format!("Omnivores: {}", eco.omnivores.len()), stats_x + 510.0, stats_y + 16.0, 15.0, ORANGE);
+                    let agents = simulations[idx].ecosystem.agents_at(x, y);
+                    let shown = agents.len().clamp(1, 8);
+                    let panel_width = 300.0;
+                    let panel_x = screen_width - panel_width - 10.0;
+                    let panel_y = offset_y;
+                    draw_rectangle(panel_x, panel_y, panel_width, 35.0 + shown as f32 * 20.0, Color::new(0.0, 0.0, 0.0, 0.8));
+                    draw_rectangle_lines(panel_x, panel_y, panel_width, 35.0 + shown as f32 * 20.0, 1.0, WHITE);
+                    draw_text(&format!("Sim {} cell ({}, {})", idx + 1, x, y), panel_x + 10.0, panel_y + 20.0, 18.0, YELLOW);
+                    if agents.is_empty() {
+                        draw_text("Empty", panel_x + 10.0, panel_y + 42.0, 16.0, WHITE);
+                    }
+                    for (line, agent) in agents.iter().take(shown).enumerate() {
+                        let born = agent.birth_iteration.map_or("-".to_string(), |b| b.to_string());
+                        let infected = if agent.infected { " Infected" } else { "" };
+                        let kind = match agent.agent_type {
+                            AgentType::Plant => format!("{} Plant", simulations[idx].ecosystem.config.plant_species[agent.species].name),
+                            _ => format!("{:?}", agent.agent_type),
+                        };
+                        draw_text(&format!("#{} {} Energy: {} Born: {}{}", agent.id, kind, agent.energy, born, infected),
+                                  panel_x + 10.0, panel_y + 42.0 + line as f32 * 20.0, 16.0, colors.of(agent));
+                    }
+                }
+                
+                if show_legend {
+                    // Simulations may have different plant species; the
+                    // one with the most is listed.
+                    let plant_species = simulations.iter().map(|sim| &sim.ecosystem.config.plant_species).max_by_key(|species| species.len());
+                    draw_legend(6.0, offset_y, colors, species_glyphs, plant_species.map_or(&[], |species| species.as_slice()));
+                }
+
+                if minimap_shown {
+                    refresh_minimap(&mut minimap_textures, &simulations, colors);
+                    let focus = simulations.iter().position(|s| s.selected).unwrap_or(0);
+                    let (grid_x, grid_y) = grid_positions[focus];
+                    let eco = &simulations[focus].ecosystem;
+                    let left = (-grid_x / view_cell).clamp(0.0, eco.width as f32);
+                    let top = (-grid_y / view_cell).clamp(0.0, eco.height as f32);
+                    let right = ((screen_width - grid_x) / view_cell).clamp(0.0, eco.width as f32);
+                    let bottom = ((view_height - grid_y) / view_cell).clamp(0.0, eco.height as f32);
+                    draw_minimap(&minimap_textures, &minimap, 6.0, focus, Rect::new(left, top, right - left, bottom - top));
+                }
+
+                if show_performance {
+                    draw_performance(&simulations, screen_width - 310.0, offset_y);
+                }
+
+                if show_chart {
+                    let chart_index = simulations.iter().position(|s| s.selected).unwrap_or(0);
+                    draw_population_chart(&simulations[chart_index], screen_width - 310.0, screen_height - 230.0, 300.0, 150.0, colors);
                 }
                 
+                if let Some(input) = &jump_input {
+                    let (box_w, box_h) = (420.0, 70.0);
+                    let (box_x, box_y) = ((screen_width - box_w) / 2.0, (screen_height - box_h) / 2.0);
+                    draw_rectangle(box_x, box_y, box_w, box_h, Color::new(0.0, 0.0, 0.0, 0.85));
+                    draw_rectangle_lines(box_x, box_y, box_w, box_h, 2.0, WHITE);
+                    draw_text(&format!("Jump to iteration: {}_", input), box_x + 10.0, box_y + 28.0, 20.0, WHITE);
+                    draw_text(&format!("K: Record every {} frame(s) | Enter: Start | Esc: Cancel", jump_record_every),
+                              box_x + 10.0, box_y + 54.0, 16.0, LIGHTGRAY);
+                }
+                if let Some(active) = &jump {
+                    let reached = active.sims.iter().map(|&i| simulations[i].iteration_count()).min().unwrap_or(active.target);
+                    let progress = (reached - active.start) as f32 / (active.target - active.start).max(1) as f32;
+                    let (bar_w, bar_h) = (400.0, 20.0);
+                    let (bar_x, bar_y) = ((screen_width - bar_w) / 2.0, screen_height / 2.0);
+                    draw_rectangle(bar_x - 10.0, bar_y - 35.0, bar_w + 20.0, bar_h + 45.0, Color::new(0.0, 0.0, 0.0, 0.85));
+                    draw_text(&format!("Jumping to iteration {} ({:.0}%) | Esc: Cancel", active.target, progress * 100.0),
+                              bar_x, bar_y - 10.0, 18.0, WHITE);
+                    draw_rectangle(bar_x, bar_y, bar_w * progress.clamp(0.0, 1.0), bar_h, GREEN);
+                    draw_rectangle_lines(bar_x, bar_y, bar_w, bar_h, 2.0, WHITE);
+                }
+                
+                if let Some(prompt) = &run_until_input {
+                    let (box_w, box_h) = (460.0, 70.0);
+                    let (box_x, box_y) = ((screen_width - box_w) / 2.0, (screen_height - box_h) / 2.0);
+                    draw_rectangle(box_x, box_y, box_w, box_h, Color::new(0.0, 0.0, 0.0, 0.85));
+                    draw_rectangle_lines(box_x, box_y, box_w, box_h, 2.0, WHITE);
+                    draw_text(&format!("Run until: {}", prompt.text()), box_x + 10.0, box_y + 28.0, 20.0, WHITE);
+                    draw_text("Up/Down: Condition | Left/Right: Species | Enter: Start | Esc: Cancel", box_x + 10.0, box_y + 54.0, 16.0, LIGHTGRAY);
+                }
+                if let Some(active) = &run_until {
+                    let steps = active.steps.iter().max().copied().unwrap_or(0);
+                    let text = format!("Running until {} ({} steps) | Esc: Cancel", active.condition, steps);
+                    let (box_w, box_h) = (460.0, 40.0);
+                    let (box_x, box_y) = ((screen_width - box_w) / 2.0, (screen_height - box_h) / 2.0);
+                    draw_rectangle(box_x, box_y, box_w, box_h, Color::new(0.0, 0.0, 0.0, 0.85));
+                    draw_text(&text, box_x + 10.0, box_y + 26.0, 18.0, WHITE);
+                }
+
                 let control_y = screen_height - 20.0;
-                draw_text("Space: Continuous Update | Left/Right: Previous/Next Frame | Tab: Cycle Selection | Esc: Statistics", 
+                let frame_keys = if simulations.iter().all(|sim| sim.record_history) {
+                    "Left/Right: Previous/Next Frame"
+                } else {
+                    "Right: Next Frame (Rewind Off)"
+                };
+                if let Some(message) = &sim_message {
+                    let flash = get_time() < alert_until && (get_time() * 4.0) as i64 % 2 == 0;
+                    draw_text(message, offset_x, control_y - 45.0, 18.0, if flash { RED } else { YELLOW });
+                }
+                draw_text(&format!("Space: Continuous Update | P: Play/Pause | +/-: Speed ({}x) | {} | Tab: Cycle Selection | J: Jump | A: Run Until | E/O: Export/Restore Snapshot | Esc: Statistics", speed, frame_keys), 
+                          offset_x, control_y - 20.0, 18.0, WHITE);
+                draw_text(&format!("C: Chart | H/R: Heatmap/Radius ({}) | L: Elevation | B: Grid Lines | T: Trails | I: Performance | U/D: Colours/Glyphs | K: Legend | X: Pause on Extinction | [/]/Wheel: Zoom | Right Drag: Pan | 0: Reset View | F12: Screenshot | G: Record GIF | M: Migration ({}) | N: Rewind History | W: Record Session{}", heatmap_radius, if migration { "On" } else { "Off" }, if session.is_some() { " (On)" } else { "" }), 
                           offset_x, control_y, 18.0, WHITE);
+                
+                if is_key_pressed(KeyCode::F12) {
+                    let iteration = simulations.iter().find(|s| s.selected).map_or(0, |s| s.iteration_count());
+                    let timestamp = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs());
+                    let path = format!("rusteze_sim_{}_{}.png", iteration, timestamp);
+                    sim_message = Some(match save_screenshot(&path) {
+                        Ok(()) => format!("Screenshot: {}", path),
+                        Err(err) => format!("Screenshot {} failed: {}", path, err),
+                    });
+                }
             },
             AppState::StatsScreen => {
                 draw_text("Simulation Statistics", offset_x, offset_y + 15.0, 30.0, WHITE);
                 
-                let column_width = 450.0;
+                let (num_cols, num_rows) = pane_grid(num_simulations);
+                let column_width = ((screen_width() - offset_x) / num_cols as f32).min(450.0);
                 
-                let num_rows = if num_simulations <= 2 { 1 } else { 2 };
-                
-                for idx in 0..simulations.len() {
-                    let row = idx / 2;
-                    let col = idx % 2;
+                for (idx, sim) in simulations.iter().enumerate() {
+                    let row = idx / num_cols;
+                    let col = idx % num_cols;
                     
                     let x_pos = offset_x + (col as f32) * column_width;
-                    let y_pos = offset_y + 60.0 + (row as f32) * 350.0;
+                    let y_pos = offset_y + 60.0 + (row as f32) * 430.0;
                     
-                    let sim = &simulations[idx];
-                    
-                    draw_text(&format!("Simulation {}", idx + 1), x_pos, y_pos, 25.0, YELLOW);
+                    draw_text(&format!("Simulation {}{}", idx + 1, name_suffix(&sim.ecosystem.config)), x_pos, y_pos, 25.0, YELLOW);
                     
                     let mut line_y = y_pos + 30.0;
-                    draw_text(&format!("Iteration Count: {}", sim.iteration_count()), x_pos, line_y, 20.0, WHITE);
+                    draw_text(&format!("Iteration Count: {}  Diversity: {:.3}", sim.iteration_count(), sim.ecosystem.shannon_diversity()),
+                              x_pos, line_y, 20.0, WHITE);
                     line_y += 25.0;
+                    if sim.ecosystem.config.season_length > 0 {
+                        draw_text(&format!("Season: {}", sim.ecosystem.current_season()), x_pos, line_y, 20.0, WHITE);
+                        line_y += 25.0;
+                    }
+                    if sim.ecosystem.config.weather_event_chance > 0.0 {
+                        draw_text(&format!("Weather: {}  Rain Events: {}  Droughts: {}", sim.ecosystem.current_weather(), sim.stats.rain_events, sim.stats.drought_events),
+                                  x_pos, line_y, 20.0, WHITE);
+                        line_y += 25.0;
+                    }
                     
                     let stats = &sim.stats;
+                    let population = sim.ecosystem.population();
+                    let delta = sim.iteration_delta();
+                    let step_text = |species: usize| format!("Step: +{} -{}", delta.births[species], delta.deaths[species]);
+                    
+                    let plant_species = &sim.ecosystem.config.plant_species;
+                    for (species, plant) in plant_species.iter().enumerate() {
+                        draw_text(&format!("{} Plants ({})", plant.name, population.plants[species]), x_pos, line_y, 20.0, colors.plant(species));
+                        draw_text(&step_text(species), x_pos + 340.0, line_y, 18.0, colors.plant(species));
+                        line_y += 20.0;
+                        draw_text(&format!("Births: {} Deaths: {}", stats.plant_births_of(species), stats.plant_deaths_of(species)),
+                                   x_pos, line_y, 18.0, colors.plant(species));
+                        line_y += 25.0;
+                    }
+                    // The animals' step counts follow the plants'.
+                    let animal = plant_species.len();
                     
-                    draw_text("Light Plants", x_pos, line_y, 20.0, GREEN);
+                    draw_text(&format!("Herbivores ({}) Avg Energy: {}", population.herbivores, format_average_energy(&sim.ecosystem, &AgentType::Herbivore)), x_pos, line_y, 20.0, colors.herbivore);
+                    draw_text(&step_text(animal), x_pos + 340.0, line_y, 18.0, colors.herbivore);
                     line_y += 20.0;
-                    draw_text(&format!("Births: {} Deaths: {}", stats.light_plant_births, stats.light_plant_deaths),
-                               x_pos, line_y, 18.0, GREEN);
+                    if show_death_causes {
+                        draw_text(&format_death_causes(&stats.herbivore_death_causes), x_pos, line_y, 16.0, colors.herbivore);
+                    } else {
+                        draw_text(&format!("Births: {} Deaths: {} Consumptions: {}",
+                                         stats.herbivore_births, stats.herbivore_deaths, stats.herbivore_consumptions),
+                                   x_pos, line_y, 18.0, colors.herbivore);
+                    }
                     line_y += 25.0;
                     
-                    draw_text("Dark Plants", x_pos, line_y, 20.0, DARK_GREEN);
+                    draw_text(&format!("Carnivores ({}) Avg Energy: {}", population.carnivores, format_average_energy(&sim.ecosystem, &AgentType::Carnivore)), x_pos, line_y, 20.0, colors.carnivore);
+                    draw_text(&step_text(animal + 1), x_pos + 340.0, line_y, 18.0, colors.carnivore);
                     line_y += 20.0;
-                    draw_text(&format!("Births: {} Deaths: {}", stats.dark_plant_births, stats.dark_plant_deaths),
-                               x_pos, line_y, 18.0, DARK_GREEN);
+                    if show_death_causes {
+                        draw_text(&format_death_causes(&stats.carnivore_death_causes), x_pos, line_y, 16.0, colors.carnivore);
+                    } else {
+                        draw_text(&format!("Births: {} Deaths: {} H: {} O: {}",
+                                         stats.carnivore_births, stats.carnivore_deaths,
+                                         stats.carnivore_consumptions, stats.carnivore_consumptions_omnivores),
+                                   x_pos, line_y, 18.0, colors.carnivore);
+                    }
                     line_y += 25.0;
                     
-                    draw_text("Herbivores", x_pos, line_y, 20.0, PINK);
+                    draw_text(&format!("Omnivores ({}) Avg Energy: {}", population.omnivores, format_average_energy(&sim.ecosystem, &AgentType::Omnivore)), x_pos, line_y, 20.0, colors.omnivore);
+                    draw_text(&step_text(animal + 2), x_pos + 340.0, line_y, 18.0, colors.omnivore);
                     line_y += 20.0;
-                    draw_text(&format!("Births: {} Deaths: {} Consumptions: {}",
-                                     stats.herbivore_births, stats.herbivore_deaths, stats.herbivore_consumptions),
-                               x_pos, line_y, 18.0, PINK);
+                    if show_death_causes {
+                        draw_text(&format_death_causes(&stats.omnivore_death_causes), x_pos, line_y, 16.0, colors.omnivore);
+                    } else {
+                        draw_text(&format!("Births: {} Deaths: {} P: {} H: {}",
+                                         stats.omnivore_births, stats.omnivore_deaths, 
+                                         stats.omnivore_consumptions_plants, stats.omnivore_consumptions_herbivores),
+                                   x_pos, line_y, 18.0, colors.omnivore);
+                    }
                     line_y += 25.0;
-                    
-                    draw_text("Carnivores", x_pos, line_y, 20.0, RED);
+
+                    if population.scavengers > 0 || stats.scavenger_births + stats.scavenger_deaths > 0 || sim.ecosystem.config.initial_scavengers > 0 {
+                        draw_text(&format!("Scavengers ({}) Avg Energy: {}", population.scavengers, format_average_energy(&sim.ecosystem, &AgentType::Scavenger)), x_pos, line_y, 20.0, colors.scavenger);
+                        draw_text(&step_text(animal + 3), x_pos + 340.0, line_y, 18.0, colors.scavenger);
+                        line_y += 20.0;
+                        if show_death_causes {
+                            draw_text(&format_death_causes(&stats.scavenger_death_causes), x_pos, line_y, 16.0, colors.scavenger);
+                        } else {
+                            draw_text(&format!("Births: {} Deaths: {} Corpses Eaten: {}",
+                                             stats.scavenger_births, stats.scavenger_deaths, stats.scavenger_consumptions),
+                                       x_pos, line_y, 18.0, colors.scavenger);
+                        }
+                        line_y += 25.0;
+                    }
+
+                    let config = &sim.ecosystem.config;
+                    if config.water_spawn_chance > 0.0 || config.tree_spawn_chance > 0.0 || population.waters + population.trees > 0 {
+                        let oldest = |agent_type: &AgentType, lifespan: usize| {
+                            sim.ecosystem.oldest_age(agent_type).map_or(String::new(), |age| format!(" (oldest {}/{})", age, lifespan))
+                        };
+                        let lakes = format!("Lakes: {}{}", population.waters, oldest(&AgentType::Water, config.water_lifespan));
+                        draw_text(&lakes, x_pos, line_y, 18.0, colors.water);
+                        let trees_x = x_pos + measure_text(&lakes, None, 18, 1.0).width + 20.0;
+                        draw_text(&format!("Trees: {}{}", population.trees, oldest(&AgentType::Tree, config.tree_lifespan)), trees_x, line_y, 18.0, colors.tree);
+                        line_y += 25.0;
+                    }
+
+                    // Each species' peak population and the iteration it was
+                    // reached at, in the species' colour.
+                    draw_text("Peaks:", x_pos, line_y, 18.0, LIGHTGRAY);
+                    let mut peak_x = x_pos + 55.0;
+                    for (&(population, iteration), color) in sim.peaks.peaks.iter().zip(colors.species(plant_species.len())) {
+                        let text = format!("{} @ {}", population, iteration);
+                        draw_text(&text, peak_x, line_y, 18.0, color);
+                        peak_x += measure_text(&text, None, 18, 1.0).width + 14.0;
+                    }
+                    line_y += 25.0;
+
+                    draw_text("Record Holder", x_pos, line_y, 20.0, VIOLET);
+                    if stats.infections > 0 || sim.ecosystem.config.disease_spawn_chance > 0.0 {
+                        draw_text(&format!("Disease Infections: {} Recoveries: {} Deaths: {}", stats.infections, stats.recoveries, stats.disease_deaths),
+                                  x_pos + 150.0, line_y, 18.0, colors.infected);
+                    }
                     line_y += 20.0;
-                    draw_text(&format!("Births: {} Deaths: {} Consumptions: {}",
-                                     stats.carnivore_births, stats.carnivore_deaths, stats.carnivore_consumptions),
-                               x_pos, line_y, 18.0, RED);
+                    let record_text = match &stats.longest_lived {
+                        Some(record) => format!("#{} {:?} Age: {} Gen: {} Parent: {} ({})",
+                                                record.id, record.agent_type, record.age, record.generation,
+                                                record.parent_id.map_or("none".to_string(), |id| format!("#{}", id)),
+                                                if record.alive { "alive" } else { "dead" }),
+                        None => "None yet".to_string(),
+                    };
+                    draw_text(&record_text, x_pos, line_y, 18.0, VIOLET);
                     line_y += 25.0;
                     
-                    draw_text("Omnivores", x_pos, line_y, 20.0, ORANGE);
-                    line_y += 20.0;
-                    draw_text(&format!("Births: {} Deaths: {} P: {} H: {}",
-                                     stats.omnivore_births, stats.omnivore_deaths, 
-                                     stats.omnivore_consumptions_plants, stats.omnivore_consumptions_herbivores),
-                               x_pos, line_y, 18.0, ORANGE);
+                    draw_text("Extinctions", x_pos, line_y, 20.0, LIGHTGRAY);
+                    let events = &sim.extinctions.events;
+                    if events.is_empty() {
+                        line_y += 20.0;
+                        draw_text("None yet", x_pos, line_y, 18.0, LIGHTGRAY);
+                    }
+                    let shown = events.len().saturating_sub(3);
+                    if shown > 0 {
+                        draw_text(&format!("({} earlier)", shown), x_pos + 120.0, line_y, 18.0, LIGHTGRAY);
+                    }
+                    for event in &events[shown..] {
+                        line_y += 20.0;
+                        draw_text(&event.to_string(), x_pos, line_y, 18.0, LIGHTGRAY);
+                    }
                 }
                 
-                let instructions_y = offset_y + 40.0 + (num_rows as f32) * 350.0 + 20.0;
+                let instructions_y = offset_y + 40.0 + (num_rows as f32) * 430.0 + 20.0;
                 draw_text("Press Esc to Return to Simulations", offset_x, instructions_y, 20.0, WHITE);
-                draw_text("Press X to Quit", offset_x, instructions_y + 30.0, 20.0, WHITE);
+                draw_text("Press V to Compare Two Simulations", offset_x, instructions_y + 30.0, 20.0, WHITE);
+                draw_text("Press E to Export Statistics to JSON", offset_x, instructions_y + 60.0, 20.0, WHITE);
+                draw_text("Press B to Edit the Configurations and Restart", offset_x, instructions_y + 90.0, 20.0, WHITE);
+                draw_text(&format!("Press D to Show {} | L: Lifespan Histograms", if show_death_causes { "Births and Consumptions" } else { "Deaths by Cause" }),
+                          offset_x, instructions_y + 120.0, 20.0, WHITE);
+                draw_text("Press X to Quit", offset_x, instructions_y + 150.0, 20.0, WHITE);
+                if let Some(message) = &sim_message {
+                    draw_text(message, offset_x, instructions_y + 190.0, 20.0, YELLOW);
+                }
                 
                 if is_key_pressed(KeyCode::Escape) {
                     app_state = AppState::Simulation;
                 }
+
+                if is_key_pressed(KeyCode::D) {
+                    show_death_causes = !show_death_causes;
+                }
+
+                // The fields are rebuilt from each simulation's live config,
+                // so they show exactly what the simulation ran with.
+                if is_key_pressed(KeyCode::B) {
+                    base_configs = simulations.iter().map(|sim| sim.ecosystem.config.clone()).collect();
+                    configs = base_configs.iter().map(config_fields).collect();
+                    config_histories = configs.iter().map(|fields| ConfigHistory::new(fields)).collect();
+                    current_config_index = 0;
+                    selected_field_index = 0;
+                    config_message = None;
+                    app_state = AppState::ConfigMenu;
+                }
+
+                if is_key_pressed(KeyCode::E) {
+                    let written: Vec<String> = simulations
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, sim)| {
+                            let path = format!("stats_{}.json", idx + 1);
+                            match sim.stats.write_json(&sim.ecosystem, Path::new(&path)) {
+                                Ok(()) => path,
+                                Err(err) => format!("{} failed: {}", path, err),
+                            }
+                        })
+                        .collect();
+                    sim_message = Some(format!("Statistics: {}", written.join(", ")));
+                }
+                
+                if is_key_pressed(KeyCode::V) && simulations.len() >= 2 {
+                    compare_pair = (0, 1);
+                    compare_scroll = 0;
+                    app_state = AppState::CompareScreen;
+                }
+
+                if is_key_pressed(KeyCode::L) {
+                    lifespan_sim = lifespan_sim.min(simulations.len() - 1);
+                    app_state = AppState::LifespanScreen;
+                }
                 
                 if is_key_pressed(KeyCode::X) {
                     break;
                 }
             },
+            
+            AppState::CompareScreen => {
+                let count = simulations.len();
+                if is_key_pressed(KeyCode::A) {
+                    compare_pair.0 = (compare_pair.0 + 1) % count;
+                }
+                if is_key_pressed(KeyCode::B) {
+                    compare_pair.1 = (compare_pair.1 + 1) % count;
+                }
+                let (a, b) = compare_pair;
+                
+                draw_text("Compare Simulations", offset_x, offset_y + 15.0, 30.0, WHITE);
+                
+                let label_x = offset_x;
+                let a_x = offset_x + 280.0;
+                let b_x = a_x + 120.0;
+                let delta_x = b_x + 120.0;
+                let mut line_y = offset_y + 60.0;
+                draw_text("Stat", label_x, line_y, 22.0, YELLOW);
+                draw_text(&format!("A: Sim {}", a + 1), a_x, line_y, 22.0, YELLOW);
+                draw_text(&format!("B: Sim {}", b + 1), b_x, line_y, 22.0, YELLOW);
+                draw_text("Delta (B - A)", delta_x, line_y, 22.0, YELLOW);
+                line_y += 28.0;
+                
+                // Rows are lined up by label: A's rows in order, then any
+                // only B has, with 0 for the simulation that lacks a row.
+                let [rows_a, rows_b] = [a, b].map(|sim| stat_rows(&simulations[sim].stats, &simulations[sim].ecosystem.config.plant_species));
+                let mut rows = vec![("Iteration Count".to_string(), simulations[a].iteration_count(), simulations[b].iteration_count())];
+                for (label, value_a) in &rows_a {
+                    let value_b = rows_b.iter().find(|(other, _)| other == label).map_or(0, |(_, value)| *value);
+                    rows.push((label.clone(), *value_a, value_b));
+                }
+                for (label, value_b) in rows_b {
+                    if !rows_a.iter().any(|(other, _)| *other == label) {
+                        rows.push((label, 0, value_b));
+                    }
+                }
+                
+                // The table scrolls when there are more rows than fit.
+                let visible = (((screen_height() - line_y - 60.0) / 22.0).max(1.0)) as usize;
+                let max_scroll = rows.len().saturating_sub(visible);
+                if is_key_pressed(KeyCode::Down) {
+                    compare_scroll += 1;
+                }
+                if is_key_pressed(KeyCode::Up) {
+                    compare_scroll = compare_scroll.saturating_sub(1);
+                }
+                compare_scroll = compare_scroll.min(max_scroll);
+                for (label, value_a, value_b) in rows.into_iter().skip(compare_scroll).take(visible) {
+                    let delta = value_b as i64 - value_a as i64;
+                    let delta_color = match delta.signum() {
+                        1 => GREEN,
+                        -1 => RED,
+                        _ => WHITE,
+                    };
+                    draw_text(&label, label_x, line_y, 18.0, WHITE);
+                    draw_text(&value_a.to_string(), a_x, line_y, 18.0, WHITE);
+                    draw_text(&value_b.to_string(), b_x, line_y, 18.0, WHITE);
+                    draw_text(&format!("{:+}", delta), delta_x, line_y, 18.0, delta_color);
+                    line_y += 22.0;
+                }
+                
+                draw_text("A/B: Cycle Simulation A/B | Up/Down: Scroll | Esc: Back to Statistics", offset_x, line_y + 20.0, 20.0, WHITE);
+                
+                if is_key_pressed(KeyCode::Escape) {
+                    app_state = AppState::StatsScreen;
+                }
+            },
+
+            AppState::LifespanScreen => {
+                let count = simulations.len();
+                if is_key_pressed(KeyCode::Right) {
+                    lifespan_sim = (lifespan_sim + 1) % count;
+                }
+                if is_key_pressed(KeyCode::Left) {
+                    lifespan_sim = (lifespan_sim + count - 1) % count;
+                }
+                let sim = &simulations[lifespan_sim];
+                let stats = &sim.stats;
+
+                draw_text(&format!("Lifespans: Simulation {}{}", lifespan_sim + 1, name_suffix(&sim.ecosystem.config)), offset_x, offset_y + 15.0, 30.0, WHITE);
+
+                let histograms = [
+                    ("Herbivores", &stats.herbivore_lifespans, colors.herbivore),
+                    ("Carnivores", &stats.carnivore_lifespans, colors.carnivore),
+                    ("Omnivores", &stats.omnivore_lifespans, colors.omnivore),
+                    ("Scavengers", &stats.scavenger_lifespans, colors.scavenger),
+                ];
+                let (panel_w, panel_h) = (((screen_width() - offset_x) / 2.0 - 40.0).min(600.0), 220.0);
+                for (i, (name, buckets, color)) in histograms.into_iter().enumerate() {
+                    let x = offset_x + (i % 2) as f32 * (panel_w + 40.0);
+                    let y = offset_y + 70.0 + (i / 2) as f32 * (panel_h + 70.0);
+                    draw_lifespan_histogram(name, buckets, x, y, panel_w, panel_h, color);
+                }
+
+                let instructions_y = offset_y + 70.0 + 2.0 * (panel_h + 70.0);
+                draw_text(&format!("Each bar counts the deaths at ages within {} iterations | Left/Right: Cycle Simulation | Esc: Back to Statistics", LIFESPAN_BUCKET),
+                          offset_x, instructions_y, 20.0, WHITE);
+
+                if is_key_pressed(KeyCode::Escape) {
+                    app_state = AppState::StatsScreen;
+                }
+            },
         }
         
         next_frame().await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threaded_advancement_matches_sequential() {
+        let configs: Vec<SimulationConfig> = (0..3).map(|seed| SimulationConfig { seed, grid_width: 30, grid_height: 30, ..SimulationConfig::default() }).collect();
+        let mut threaded: Vec<SimulationInstance> = configs.iter().cloned().map(SimulationInstance::new).collect();
+        let mut sequential: Vec<SimulationInstance> = configs.into_iter().map(SimulationInstance::new).collect();
+        advance_each(&mut threaded, &[true; 3], 50);
+        for sim in &mut sequential {
+            advance_times(sim, 50);
+        }
+        for (threaded, sequential) in threaded.iter().zip(&sequential) {
+            assert_eq!(threaded.iteration_count(), 50);
+            assert_eq!(threaded.ecosystem.to_json(), sequential.ecosystem.to_json());
+        }
+    }
+
+    #[test]
+    fn peaks_keep_the_first_iteration_of_each_high() {
+        let mut peaks = PeakPopulations::default();
+        for (iteration, populations) in [[1, 5], [3, 2], [3, 7], [2, 1]].iter().enumerate() {
+            peaks.observe(populations, iteration);
+        }
+        assert_eq!(peaks.peaks, vec![(3, 1), (7, 2)]);
+    }
+}
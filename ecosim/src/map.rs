@@ -0,0 +1,68 @@
+use crate::config::AgentType;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Map files are plain text, one grid row per line:
+//   '.' or ' ' empty   'L' first plant species   'D' second plant species
+//   'W' water          'T' tree          'R' rock
+//   'H' herbivore      'C' carnivore     'O' omnivore     'S' scavenger
+// Lines starting with ';' are comments and are not counted as rows.
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(std::io::Error),
+    UnknownSymbol { line: usize, column: usize, symbol: char },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Io(err) => write!(f, "could not read map file: {}", err),
+            MapError::UnknownSymbol { line, column, symbol } => {
+                write!(f, "unknown map symbol '{}' at line {}, column {}", symbol, line, column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<std::io::Error> for MapError {
+    fn from(err: std::io::Error) -> Self {
+        MapError::Io(err)
+    }
+}
+
+// Each cell is `(x, y, agent_type, species)`, where the plant species is 0
+// for everything but 'D'.
+pub fn load_map(path: &Path) -> Result<Vec<(usize, usize, AgentType, usize)>, MapError> {
+    let text = fs::read_to_string(path)?;
+    parse_map(&text)
+}
+
+pub fn parse_map(text: &str) -> Result<Vec<(usize, usize, AgentType, usize)>, MapError> {
+    let mut cells = Vec::new();
+    let rows = text.lines().enumerate().filter(|(_, line)| !line.starts_with(';'));
+    for (y, (line_index, line)) in rows.enumerate() {
+        for (x, symbol) in line.chars().enumerate() {
+            let (agent_type, species) = match symbol {
+                '.' | ' ' => continue,
+                'L' => (AgentType::Plant, 0),
+                'D' => (AgentType::Plant, 1),
+                'H' => (AgentType::Herbivore, 0),
+                'C' => (AgentType::Carnivore, 0),
+                'O' => (AgentType::Omnivore, 0),
+                'S' => (AgentType::Scavenger, 0),
+                'W' => (AgentType::Water, 0),
+                'T' => (AgentType::Tree, 0),
+                'R' => (AgentType::Rock, 0),
+                _ => {
+                    return Err(MapError::UnknownSymbol { line: line_index + 1, column: x + 1, symbol });
+                }
+            };
+            cells.push((x, y, agent_type, species));
+        }
+    }
+    Ok(cells)
+}
@@ -0,0 +1,176 @@
+use crate::config::{Agent, AgentType};
+
+// Layers of everything but plants; plant species follow, one layer each.
+const FIXED_LAYERS: usize = 8;
+
+// Per-cell agent counts, one layer per agent type and plant species. The
+// ecosystem keeps this in sync with its agent vectors so cell queries don't
+// need to scan them.
+#[derive(Clone, Default, PartialEq)]
+pub struct Occupancy {
+    width: usize,
+    height: usize,
+    layers: usize,
+    counts: Vec<u32>,
+}
+
+impl Occupancy {
+    pub fn new(width: usize, height: usize, plant_species: usize) -> Self {
+        let layers = FIXED_LAYERS + plant_species;
+        Self {
+            width,
+            height,
+            layers,
+            counts: vec![0; width * height * layers],
+        }
+    }
+
+    pub fn from_agents<'a>(width: usize, height: usize, plant_species: usize, agents: impl IntoIterator<Item = &'a Agent>) -> Self {
+        let mut occupancy = Self::new(width, height, plant_species);
+        for agent in agents {
+            occupancy.add(agent);
+        }
+        occupancy
+    }
+
+    fn layer(agent_type: &AgentType) -> usize {
+        match agent_type {
+            AgentType::Herbivore => 0,
+            AgentType::Carnivore => 1,
+            AgentType::Omnivore => 2,
+            AgentType::Water => 3,
+            AgentType::Tree => 4,
+            AgentType::Corpse => 5,
+            AgentType::Rock => 6,
+            AgentType::Scavenger => 7,
+            AgentType::Plant => FIXED_LAYERS,
+        }
+    }
+
+    fn agent_layer(agent: &Agent) -> usize {
+        Self::layer(&agent.agent_type) + if agent.agent_type == AgentType::Plant { agent.species } else { 0 }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        debug_assert!(x < self.width && y < self.height);
+        (y * self.width + x) * self.layers
+    }
+
+    pub fn add(&mut self, agent: &Agent) {
+        let index = self.index(agent.x, agent.y);
+        self.counts[index + Self::agent_layer(agent)] += 1;
+    }
+
+    pub fn remove(&mut self, agent: &Agent) {
+        let index = self.index(agent.x, agent.y);
+        let count = &mut self.counts[index + Self::agent_layer(agent)];
+        debug_assert!(*count > 0, "occupancy out of sync at ({}, {})", agent.x, agent.y);
+        *count = count.saturating_sub(1);
+    }
+
+    pub fn relocate(&mut self, agent: &Agent, x: usize, y: usize) {
+        self.remove(agent);
+        let index = self.index(x, y);
+        self.counts[index + Self::agent_layer(agent)] += 1;
+    }
+
+    // Plants count across all species.
+    pub fn count(&self, agent_type: &AgentType, x: usize, y: usize) -> u32 {
+        let cell = &self.counts[self.index(x, y)..][..self.layers];
+        match agent_type {
+            AgentType::Plant => cell[FIXED_LAYERS..].iter().sum(),
+            _ => cell[Self::layer(agent_type)],
+        }
+    }
+
+    pub fn has(&self, agent_type: &AgentType, x: usize, y: usize) -> bool {
+        self.count(agent_type, x, y) > 0
+    }
+
+    pub fn has_plant(&self, x: usize, y: usize) -> bool {
+        self.has(&AgentType::Plant, x, y)
+    }
+
+    pub fn has_plant_species(&self, species: usize, x: usize, y: usize) -> bool {
+        self.counts[self.index(x, y) + FIXED_LAYERS + species] > 0
+    }
+
+    /// The lowest plant species on a cell, if it has any plant.
+    pub fn plant_species_at(&self, x: usize, y: usize) -> Option<usize> {
+        let cell = &self.counts[self.index(x, y)..][..self.layers];
+        cell[FIXED_LAYERS..].iter().position(|&count| count > 0)
+    }
+
+    pub fn has_animal(&self, x: usize, y: usize) -> bool {
+        self.has(&AgentType::Herbivore, x, y) || self.has(&AgentType::Carnivore, x, y) || self.has(&AgentType::Omnivore, x, y)
+            || self.has(&AgentType::Scavenger, x, y)
+    }
+
+    pub fn has_terrain(&self, x: usize, y: usize) -> bool {
+        self.has(&AgentType::Water, x, y) || self.has(&AgentType::Tree, x, y) || self.has(&AgentType::Rock, x, y)
+    }
+}
+
+// Maps each cell to the lowest index of an agent standing on it in a
+// given vector, so hits don't have to scan. A slot becomes `Unknown` when
+// swap-removals make the lowest index uncertain; the next lookup rescans it.
+// Lookups therefore always return what `position` would.
+#[derive(Clone, Copy, PartialEq)]
+enum Slot {
+    Empty,
+    At(usize),
+    Unknown,
+}
+
+#[derive(Clone, Default)]
+pub struct CellIndex {
+    width: usize,
+    slots: Vec<Slot>,
+}
+
+impl CellIndex {
+    pub fn rebuild(&mut self, width: usize, height: usize, agents: &[Agent]) {
+        self.width = width;
+        self.slots.clear();
+        self.slots.resize(width * height, Slot::Empty);
+        for (index, agent) in agents.iter().enumerate().rev() {
+            self.slots[agent.y * width + agent.x] = Slot::At(index);
+        }
+    }
+
+    pub fn find(&mut self, agents: &[Agent], x: usize, y: usize) -> Option<usize> {
+        let slot = y * self.width + x;
+        match self.slots[slot] {
+            Slot::Empty => None,
+            Slot::At(index) if agents.get(index).is_some_and(|a| a.x == x && a.y == y) => Some(index),
+            _ => {
+                let found = agents.iter().position(|a| a.x == x && a.y == y);
+                self.slots[slot] = found.map_or(Slot::Empty, Slot::At);
+                found
+            }
+        }
+    }
+
+    pub fn pushed(&mut self, agents: &[Agent], index: usize) {
+        let agent = &agents[index];
+        let slot = &mut self.slots[agent.y * self.width + agent.x];
+        if *slot == Slot::Empty {
+            *slot = Slot::At(index);
+        }
+    }
+
+    pub fn swap_removed(&mut self, agents: &[Agent], removed: &Agent, index: usize) {
+        let removed_slot = removed.y * self.width + removed.x;
+        if self.slots[removed_slot] == Slot::At(index) {
+            self.slots[removed_slot] = Slot::Unknown;
+        }
+        if let Some(moved) = agents.get(index) {
+            let moved_slot = &mut self.slots[moved.y * self.width + moved.x];
+            if let Slot::At(lowest) = *moved_slot {
+                if lowest == agents.len() || index < lowest {
+                    *moved_slot = Slot::At(index);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::config::SimulationConfig;
+use crate::ecosystem::{Ecosystem, SimulationStats};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SessionEvent {
+    Advance { sim: usize, steps: usize },
+    Back { sim: usize, steps: usize },
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnknownSimulation(usize),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "could not access session file: {}", err),
+            ReplayError::Json(err) => write!(f, "invalid session: {}", err),
+            ReplayError::UnknownSimulation(sim) => write!(f, "session moves simulation {} but has no config for it", sim + 1),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Log of a session that is enough to rebuild it: each simulation's initial
+/// config (which holds its seed) and how far each simulation was moved
+/// forward or back. Since an ecosystem only depends on its config and seed,
+/// this replaces storing every frame.
+#[derive(Serialize, Deserialize)]
+pub struct SessionRecorder {
+    configs: Vec<SimulationConfig>,
+    events: Vec<SessionEvent>,
+    #[serde(skip)]
+    iterations: Vec<usize>,
+}
+
+impl SessionRecorder {
+    pub fn new(configs: Vec<SimulationConfig>) -> Self {
+        let iterations = vec![0; configs.len()];
+        Self { configs, events: Vec::new(), iterations }
+    }
+
+    pub fn advance(&mut self, sim: usize, steps: usize) {
+        self.iterations[sim] += steps;
+        // Simulations don't affect each other, so a simulation's advances
+        // can be merged even when others moved in between.
+        let last = self.events.iter_mut().rev().find(|event| match event {
+            SessionEvent::Advance { sim: s, .. } | SessionEvent::Back { sim: s, .. } => *s == sim,
+        });
+        match last {
+            Some(SessionEvent::Advance { steps: total, .. }) => *total += steps,
+            _ => self.events.push(SessionEvent::Advance { sim, steps }),
+        }
+    }
+
+    pub fn back(&mut self, sim: usize, steps: usize) {
+        self.iterations[sim] = self.iterations[sim].saturating_sub(steps);
+        self.events.push(SessionEvent::Back { sim, steps });
+    }
+
+    // Logs whatever moved simulation `sim` to `iteration` since it was last
+    // observed.
+    pub fn observe(&mut self, sim: usize, iteration: usize) {
+        let last = self.iterations[sim];
+        if iteration > last {
+            self.advance(sim, iteration - last);
+        } else if iteration < last {
+            self.back(sim, last - iteration);
+        }
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<(), ReplayError> {
+        let json = serde_json::to_string(self).map_err(ReplayError::Json)?;
+        fs::write(path, json).map_err(ReplayError::Io)
+    }
+}
+
+/// Rebuilds a session recorded by `SessionRecorder` and returns each
+/// simulation's final ecosystem and statistics.
+pub fn replay(path: &Path) -> Result<Vec<(Ecosystem, SimulationStats)>, ReplayError> {
+    let text = fs::read_to_string(path).map_err(ReplayError::Io)?;
+    let session: SessionRecorder = serde_json::from_str(&text).map_err(ReplayError::Json)?;
+    let mut iterations = vec![0usize; session.configs.len()];
+    for event in &session.events {
+        let (sim, forward, steps) = match *event {
+            SessionEvent::Advance { sim, steps } => (sim, true, steps),
+            SessionEvent::Back { sim, steps } => (sim, false, steps),
+        };
+        let iteration = iterations.get_mut(sim).ok_or(ReplayError::UnknownSimulation(sim))?;
+        *iteration = if forward { *iteration + steps } else { iteration.saturating_sub(steps) };
+    }
+    // A frame revisited after stepping back is the same frame, so only the
+    // final iteration of each simulation needs simulating.
+    Ok(session
+        .configs
+        .into_iter()
+        .zip(iterations)
+        .map(|(config, iteration)| {
+            let mut ecosystem = Ecosystem::new_custom(config);
+            let mut stats = SimulationStats::default();
+            for _ in 0..iteration {
+                ecosystem.step(&mut stats);
+            }
+            (ecosystem, stats)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaying_a_recording_gives_the_same_final_stats() {
+        let configs: Vec<SimulationConfig> = (0..2).map(|seed| SimulationConfig { seed, grid_width: 30, grid_height: 30, ..SimulationConfig::default() }).collect();
+        let mut recorder = SessionRecorder::new(configs.clone());
+        recorder.advance(0, 30);
+        recorder.advance(1, 20);
+        recorder.back(0, 10);
+        recorder.advance(0, 5);
+        let path = std::env::temp_dir().join(format!("ecosim-replay-test-{}.json", std::process::id()));
+        recorder.save_to_file(&path).unwrap();
+        let replayed = replay(&path);
+        fs::remove_file(&path).unwrap();
+        for ((ecosystem, stats), (config, steps)) in replayed.unwrap().iter().zip(configs.into_iter().zip([25, 20])) {
+            let mut expected = Ecosystem::new_custom(config);
+            let mut expected_stats = SimulationStats::default();
+            for _ in 0..steps {
+                expected.step(&mut expected_stats);
+            }
+            assert_eq!(ecosystem.iteration_count, steps);
+            // Values rather than JSON text, which follows the death cause maps' order.
+            assert_eq!(serde_json::to_value(stats).unwrap(), serde_json::to_value(&expected_stats).unwrap());
+        }
+    }
+}